@@ -3,16 +3,28 @@
 in vec2 uv_t;
 in vec3 norm;
 in float fog_factor;
+in vec2 light;
+in float occlusion;
 
 out vec4 Color;
 
 uniform vec3 fog_color;
 uniform float texture_offset = 0.0f;
+uniform float sky_intensity = 1.0f;
 
 uniform sampler2DArray texel;
 
+float light_curve(float level) {
+    return pow(0.8f, 15.0f * (1.0f - level));
+}
+
 void main()
 {
     Color = texture(texel, vec3(uv_t, texture_offset));
+    if (Color.a < 0.1f) {
+        discard;
+    }
+    float brightness = max(light_curve(light.x) * sky_intensity, light_curve(light.y));
+    Color.rgb *= max(brightness, 0.05f) * occlusion;
     Color = mix(vec4(fog_color, 1.0f), Color, fog_factor);
 }
@@ -11,10 +11,17 @@ uniform mat4 model;
 uniform vec3 camera_position;
 uniform float fog_min_dist;
 uniform float fog_max_dist;
+uniform int uv_rotation = 0;
+// Skylight and block light for every vertex of the face
+uniform vec2 vertex_light[4];
+// Ambient occlusion for every vertex of the face, 1 is not occluded
+uniform float vertex_occlusion[4];
 
 out vec2 uv_t;
 out vec3 norm;
 out float fog_factor;
+out vec2 light;
+out float occlusion;
 
 float compute_fog_factor(vec3 vert_pos) {
     float dist = length(vert_pos - camera_position);
@@ -25,6 +32,14 @@ float compute_fog_factor(vec3 vert_pos) {
     return fog_factor;
 }
 
+vec2 rotate_uv(vec2 uv, int quarter_turns) {
+    vec2 centered = uv - 0.5;
+    for (int i = 0; i < quarter_turns; i++) {
+        centered = vec2(-centered.y, centered.x);
+    }
+    return centered + 0.5;
+}
+
 void main()
 {
     vec4 vert_pos = model * vec4(position, 1.0f);
@@ -32,6 +47,8 @@ void main()
 
     fog_factor = compute_fog_factor(vert_pos.xyz);
 
-    uv_t = uv;
+    uv_t = rotate_uv(uv, uv_rotation);
+    light = vertex_light[gl_VertexID];
+    occlusion = vertex_occlusion[gl_VertexID];
     norm = a_norm;
 }
@@ -0,0 +1,31 @@
+#version 410 core
+
+in vec2 world_xz;
+
+out vec4 Color;
+
+uniform vec3 camera_position;
+uniform float extent;
+uniform float cell_size;
+// Part of the cells without clouds
+uniform float gaps;
+// Distance which the wind moved clouds along X axis
+uniform float offset;
+uniform vec3 color;
+uniform vec3 fog_color;
+
+float hash(vec2 cell) {
+    return fract(sin(dot(cell, vec2(12.9898f, 78.233f))) * 43758.5453f);
+}
+
+void main()
+{
+    vec2 cell = floor((world_xz - vec2(offset, 0.0f)) / cell_size);
+    if (hash(cell) < gaps) {
+        discard;
+    }
+
+    float dist = length(world_xz - camera_position.xz);
+    float fade = 1.0f - smoothstep(extent * 0.5f, extent, dist);
+    Color = vec4(mix(fog_color, color, fade), 0.8f * fade);
+}
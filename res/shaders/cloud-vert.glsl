@@ -0,0 +1,17 @@
+#version 410 core
+
+layout (location = 0) in vec2 corner;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform vec3 camera_position;
+uniform float height;
+uniform float extent;
+
+out vec2 world_xz;
+
+void main()
+{
+    world_xz = camera_position.xz + corner * extent;
+    gl_Position = projection * view * vec4(world_xz.x, height, world_xz.y, 1.0f);
+}
@@ -0,0 +1,20 @@
+#version 410 core
+
+in vec3 uv_o;
+in vec4 color_o;
+
+out vec4 Color;
+
+uniform bool textured;
+uniform sampler2DArray texel;
+
+void main()
+{
+    Color = color_o;
+    if (textured) {
+        Color *= texture(texel, uv_o);
+    }
+    if (Color.a < 0.01f) {
+        discard;
+    }
+}
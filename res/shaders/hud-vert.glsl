@@ -0,0 +1,17 @@
+#version 410 core
+
+layout (location = 0) in vec2 position;
+layout (location = 1) in vec3 uv;
+layout (location = 2) in vec4 a_color;
+
+uniform mat4 projection;
+
+out vec3 uv_o;
+out vec4 color_o;
+
+void main()
+{
+    gl_Position = projection * vec4(position, 0.0f, 1.0f);
+    uv_o = uv;
+    color_o = a_color;
+}
@@ -0,0 +1,21 @@
+#version 410 core
+
+in vec2 uv;
+
+out vec4 Color;
+
+uniform vec3 color;
+// Strength of the halo around the disc
+uniform float glow;
+
+void main()
+{
+    float dist = length(uv);
+    float disc = 1.0f - smoothstep(0.4f, 0.45f, dist);
+    float halo = glow * pow(max(1.0f - dist, 0.0f), 3.0f);
+    float alpha = clamp(disc + halo, 0.0f, 1.0f);
+    if (alpha < 0.01f) {
+        discard;
+    }
+    Color = vec4(color, alpha);
+}
@@ -0,0 +1,23 @@
+#version 410 core
+
+layout (location = 0) in vec2 corner;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform vec3 direction;
+uniform float distance;
+uniform float size;
+
+out vec2 uv;
+
+void main()
+{
+    vec3 forward = normalize(direction);
+    vec3 up_hint = abs(forward.y) > 0.99f ? vec3(1.0f, 0.0f, 0.0f) : vec3(0.0f, 1.0f, 0.0f);
+    vec3 right = normalize(cross(up_hint, forward));
+    vec3 up = cross(forward, right);
+
+    vec3 position = forward * distance + (right * corner.x + up * corner.y) * size;
+    gl_Position = projection * mat4(mat3(view)) * vec4(position, 1.0f);
+    uv = corner;
+}
@@ -0,0 +1,22 @@
+#version 410 core
+
+in vec3 direction;
+
+out vec4 Color;
+
+uniform vec3 horizon_color;
+uniform vec3 zenith_color;
+uniform vec3 sun_direction;
+
+void main()
+{
+    vec3 dir = normalize(direction);
+    float height = sqrt(clamp(dir.y, 0.0f, 1.0f));
+    vec3 color = mix(horizon_color, zenith_color, height);
+
+    // The sky is brighter around the sun
+    float sun_glow = pow(max(dot(dir, normalize(sun_direction)), 0.0f), 8.0f);
+    color += horizon_color * sun_glow * 0.4f;
+
+    Color = vec4(color, 1.0f);
+}
@@ -0,0 +1,16 @@
+#version 410 core
+
+layout (location = 0) in vec3 position;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform float radius;
+
+out vec3 direction;
+
+void main()
+{
+    // The dome moves with the camera, so only rotation of the view is applied
+    gl_Position = projection * mat4(mat3(view)) * vec4(position * radius, 1.0f);
+    direction = position;
+}
@@ -0,0 +1,32 @@
+use terraria3d_rs::cli::Options;
+use terraria3d_rs::server::Server;
+
+fn start(options: Options) {
+    if let Err(error) = Server::host(&options) {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let options = match Options::parse_server(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, Options::SERVER_USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", Options::SERVER_USAGE);
+        return;
+    }
+
+    // Chunks are generated on the stack of the main thread, like in the game
+    std::thread::Builder::new()
+        .stack_size(32 * 1024 * 1024)
+        .name(String::from("Main"))
+        .spawn(move || start(options))
+        .unwrap()
+        .join()
+        .unwrap();
+}
@@ -0,0 +1,278 @@
+use std::path::{Path, PathBuf};
+
+use crate::game::world::info::{parse_seed, random_seed, WorldInfo, WORLDS_DIRECTORY};
+use crate::settings::Settings;
+
+/// Options given on the command line, they override the settings for this run only
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Options {
+    /// Directory of the world to open, it's created if it doesn't exist
+    pub world: Option<PathBuf>,
+    pub seed: Option<u32>,
+    pub render_distance: Option<usize>,
+    pub window_size: Option<(u32, u32)>,
+    pub headless: bool,
+    /// Radius in chunks of the square which is generated before exiting
+    pub generate_only: Option<usize>,
+    /// Address of the server to join instead of opening a world
+    pub connect: Option<String>,
+    /// Name of the player on the server
+    pub name: Option<String>,
+    /// Port which the server listens on
+    pub port: Option<u16>,
+    pub help: bool,
+}
+
+impl Options {
+    pub const USAGE: &'static str = "Usage: terraria3d-rs [options]
+    --world <directory>       open the world in the directory, it's created if it doesn't exist
+    --seed <number|text>      seed of a new world, text is hashed into a number
+    --render-distance <n>     radius of the loaded area in chunks
+    --window-size <w>x<h>     size of the window, e.g. 1280x720
+    --headless                host the world without a window, like terraria3d-server
+    --port <n>                port of the headless world, 7777 by default
+    --generate-only <n>       generate and save chunks within n of the spawn and exit,
+                              no window is opened
+    --connect <host[:port]>   join the server, the default port is 7777
+    --name <name>             name of the player on the server
+    --help                    print this message
+
+Without --world or --seed the game starts in the main menu, the headless game hosts
+the world in data/worlds/server then.";
+
+    pub const SERVER_USAGE: &'static str = "Usage: terraria3d-server [options]
+    --world <directory>       run the world in the directory, it's created if it doesn't exist
+    --seed <number|text>      seed of a new world, text is hashed into a number
+    --render-distance <n>     radius of the simulated area in chunks
+    --port <n>                port for the players, 7777 by default
+    --help                    print this message
+
+Without --world the server runs the world in data/worlds/server.";
+
+    /// Parses options of the dedicated server, which has no window
+    pub fn parse_server(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::parse_all(args)?;
+        if options.window_size.is_some()
+            || options.headless
+            || options.generate_only.is_some()
+            || options.connect.is_some()
+            || options.name.is_some()
+        {
+            return Err(String::from(
+                "--window-size, --headless, --generate-only, --connect and --name are not server \
+                 options",
+            ));
+        }
+        if options.world.is_none() {
+            options.world = Some(Path::new(WORLDS_DIRECTORY).join("server"));
+        }
+        Ok(options)
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::parse_all(args)?;
+        if options.headless {
+            if options.window_size.is_some() || options.connect.is_some() || options.name.is_some()
+            {
+                return Err(String::from(
+                    "--window-size, --connect and --name cannot be used with --headless",
+                ));
+            }
+            let local = options.world.is_some() || options.seed.is_some();
+            if !local && options.generate_only.is_none() {
+                options.world = Some(Path::new(WORLDS_DIRECTORY).join("server"));
+            }
+        } else if options.port.is_some() {
+            return Err(String::from(
+                "--port needs --headless, the game uses --connect <host:port>",
+            ));
+        }
+        let local = options.world.is_some() || options.seed.is_some();
+        if options.connect.is_some() && (local || options.generate_only.is_some()) {
+            return Err(String::from(
+                "--connect cannot be used with --world, --seed or --generate-only",
+            ));
+        }
+        Ok(options)
+    }
+
+    /// Options of both the game and the server, they're checked by the callers
+    fn parse_all(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // Both "--seed 42" and "--seed=42" are accepted
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) => (String::from(name), Some(String::from(value))),
+                None => (arg, None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} needs a value", name))
+            };
+            match name.as_str() {
+                "--world" => options.world = Some(PathBuf::from(value()?)),
+                "--seed" => options.seed = Some(parse_seed(&value()?)),
+                "--render-distance" => {
+                    options.render_distance = Some(parse_number(&name, &value()?)?)
+                }
+                "--window-size" => options.window_size = Some(parse_size(&value()?)?),
+                "--headless" => options.headless = true,
+                "--generate-only" => options.generate_only = Some(parse_number(&name, &value()?)?),
+                "--connect" => options.connect = Some(value()?),
+                "--name" => options.name = Some(value()?),
+                "--port" => {
+                    let port = value()?;
+                    let port = port
+                        .parse::<u16>()
+                        .map_err(|_| format!("{} needs a port number, got: {}", name, port))?;
+                    options.port = Some(port);
+                }
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("Unknown option: {}", name)),
+            }
+        }
+        Ok(options)
+    }
+
+    /// The settings with the values given on the command line
+    pub fn apply(&self, settings: &Settings) -> Settings {
+        let mut settings = settings.clone();
+        if let Some(render_distance) = self.render_distance {
+            settings.render_distance = render_distance;
+        }
+        if let Some((width, height)) = self.window_size {
+            settings.window_width = width;
+            settings.window_height = height;
+        }
+        settings.clamped()
+    }
+
+    /// World which is opened right away instead of the main menu. With `--world` it's the world
+    /// in the directory, a new one is created there if needed. With only `--seed` a new world
+    /// is created among the other worlds.
+    pub fn world_info(&self) -> std::io::Result<Option<WorldInfo>> {
+        let seed = self.seed.unwrap_or_else(random_seed);
+        let Some(directory) = &self.world else {
+            return match self.seed {
+                Some(seed) => {
+                    WorldInfo::create(Path::new(WORLDS_DIRECTORY), "New World", seed).map(Some)
+                }
+                None => Ok(None),
+            };
+        };
+        if directory.exists() {
+            let info = WorldInfo::load(directory)?;
+            if self.seed.is_some_and(|seed| seed != info.seed()) {
+                eprintln!(
+                    "The world already exists, its seed {} is used instead",
+                    info.seed()
+                );
+            }
+            return Ok(Some(info));
+        }
+        let name = directory
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("New World"));
+        WorldInfo::create_at(directory, &name, seed).map(Some)
+    }
+}
+
+fn parse_number(name: &str, value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .map_err(|_| format!("{} needs a number, got: {}", name, value))
+}
+
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let size = value
+        .split_once(['x', 'X'])
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+    match size {
+        Some((width, height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(format!(
+            "Window size must look like 1280x720, got: {}",
+            value
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Options, String> {
+        Options::parse(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn options_are_parsed_with_separate_or_inline_values() {
+        let options = parse(
+            "--world data/ci --seed=hello --render-distance 3 --window-size 800x600 \
+             --generate-only 2",
+        )
+        .unwrap();
+        assert_eq!(options.world, Some(PathBuf::from("data/ci")));
+        assert_eq!(options.seed, Some(parse_seed("hello")));
+        assert_eq!(options.render_distance, Some(3));
+        assert_eq!(options.window_size, Some((800, 600)));
+        assert!(!options.headless);
+        assert_eq!(options.generate_only, Some(2));
+
+        let settings = options.apply(&Settings::default());
+        assert_eq!(settings.render_distance, 3);
+        assert_eq!((settings.window_width, settings.window_height), (800, 600));
+
+        let remote = parse("--connect 192.168.1.5:7000 --name=Alex").unwrap();
+        assert_eq!(remote.connect.as_deref(), Some("192.168.1.5:7000"));
+        assert_eq!(remote.name.as_deref(), Some("Alex"));
+    }
+
+    #[test]
+    fn wrong_options_are_rejected() {
+        assert_eq!(parse("").unwrap(), Options::default());
+        assert!(parse("--seed").is_err());
+        assert!(parse("--render-distance far").is_err());
+        assert!(parse("--window-size 800").is_err());
+        assert!(parse("--fly").is_err());
+        assert!(parse("--port 7777").is_err());
+        assert!(parse("--connect localhost --seed 1").is_err());
+    }
+
+    #[test]
+    fn headless_game_hosts_the_world_without_a_window() {
+        let options = parse("--headless --port=7000").unwrap();
+        assert!(options.headless);
+        assert_eq!(options.port, Some(7000));
+        assert_eq!(
+            options.world,
+            Some(Path::new(WORLDS_DIRECTORY).join("server"))
+        );
+        let options = parse("--headless --world data/ci").unwrap();
+        assert_eq!(options.world, Some(PathBuf::from("data/ci")));
+        // Generating is headless anyway, a new world is created as without the flag
+        let options = parse("--headless --generate-only 2").unwrap();
+        assert_eq!(options.world, None);
+        assert!(parse("--headless --window-size 800x600").is_err());
+        assert!(parse("--headless --connect localhost").is_err());
+        assert!(parse("--headless --name Alex").is_err());
+    }
+
+    #[test]
+    fn server_runs_the_server_world_by_default() {
+        let options = Options::parse_server(["--seed=7".to_string()]).unwrap();
+        assert_eq!(
+            options.world,
+            Some(Path::new(WORLDS_DIRECTORY).join("server"))
+        );
+        assert_eq!(options.seed, Some(7));
+        let options = Options::parse_server(["--port=7000".to_string()]).unwrap();
+        assert_eq!(options.port, Some(7000));
+        assert!(Options::parse_server(["--connect=localhost".to_string()]).is_err());
+        assert!(Options::parse_server(["--generate-only=2".to_string()]).is_err());
+        assert!(Options::parse_server(["--window-size=800x600".to_string()]).is_err());
+    }
+}
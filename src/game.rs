@@ -1,23 +1,94 @@
-use sdl2::{event::Event, keyboard::Keycode};
-use std::cell::RefCell;
-use std::rc::Rc;
+#[cfg(feature = "client")]
+use sdl2::{event::Event, keyboard::Keycode, mouse::MouseButton};
+#[cfg(feature = "client")]
+use std::{cell::RefCell, path::Path, rc::Rc};
 
+#[cfg(feature = "client")]
+use crate::settings::{Settings, SETTINGS_FILE};
+#[cfg(feature = "client")]
 use crate::window::Window;
 
 pub mod player;
-use player::Player;
+#[cfg(feature = "client")]
+use player::{inventory::HOTBAR_SIZE, Player};
 
 pub mod world;
-use world::World;
+#[cfg(feature = "client")]
+use world::{
+    block_state::BlockState,
+    chunk::Chunk,
+    info::{WorldInfo, WORLDS_DIRECTORY},
+    World,
+};
 
 pub mod storage;
 
-use crate::render::aim::Aim;
+pub mod command;
+#[cfg(feature = "client")]
+use command::{Command, Console};
 
+pub mod crafting;
+#[cfg(feature = "client")]
+use crafting::{RecipeBook, RECIPES_FILE};
+
+// Modules below need a window, the server is built without them
+
+#[cfg(feature = "client")]
+pub mod controls;
+#[cfg(feature = "client")]
+use controls::{Action, Input, KeyBindings, CONTROLS_FILE};
+
+#[cfg(feature = "client")]
+pub mod debug;
+#[cfg(feature = "client")]
+use debug::DebugOverlay;
+
+#[cfg(feature = "client")]
+pub mod ui;
+#[cfg(feature = "client")]
+use ui::InventoryUi;
+
+#[cfg(feature = "client")]
+pub mod menu;
+#[cfg(feature = "client")]
+use menu::{GameState, Menu, MenuAction};
+
+#[cfg(feature = "client")]
+pub mod remote;
+#[cfg(feature = "client")]
+use remote::Remote;
+
+#[cfg(feature = "client")]
+use crate::render::{aim::Aim, hud::Hud, outline::BlockOutline, sky::Sky, world::WorldRenderer};
+
+#[cfg(feature = "client")]
+use crate::net::{client::NetClient, protocol::Message, server_address};
+
+#[cfg(feature = "client")]
 pub struct Game {
+    state: GameState,
+    /// Set when the player quits from a menu or the console, the main loop stops then
+    quit: bool,
     player: Rc<RefCell<Player>>,
-    world: World,
+    /// Loaded only while playing or paused
+    world: Option<World>,
+    /// Connection with the server while playing on it or connecting to it
+    remote: Option<Remote>,
     aim: Aim,
+    hud: Hud,
+    menu: Menu,
+    bindings: KeyBindings,
+    inventory_ui: InventoryUi,
+    debug: DebugOverlay,
+    mouse: sdl2::mouse::MouseUtil,
+    outline: BlockOutline,
+    sky: Sky,
+    world_renderer: WorldRenderer,
+    console: Console,
+    recipes: RecipeBook,
+    settings: Settings,
+    /// Set when the window settings change, the main loop applies them to the window
+    window_changed: bool,
 
     timer: sdl2::TimerSubsystem,
     last_frame: u32,
@@ -27,25 +98,44 @@ pub struct Game {
 
 //TODO:
 //This is temporary values, need to change to methods from other structs!
-const DEFAULT_SEED: u32 = 5;
-const DEFAULT_BLOCK_SIZE: f32 = 1.;
+pub const DEFAULT_BLOCK_SIZE: f32 = 1.;
 
+#[cfg(feature = "client")]
 impl Game {
-    pub fn init(window: &Window) -> Result<Game, String> {
-        let player = Rc::new(RefCell::new(Player::new(
-            *super::window::ASPECT_RATIO.lock(),
-            45f32,
-            0.1f32,
-            200f32,
-        )));
+    pub fn init(window: &Window, settings: Settings) -> Result<Game, String> {
+        let mouse = window.mouse();
+        mouse.set_relative_mouse_mode(false);
+        let bindings = KeyBindings::load(CONTROLS_FILE).unwrap_or_else(|error| {
+            eprintln!(
+                "Key bindings cannot be loaded, the defaults are used: {}",
+                error
+            );
+            KeyBindings::new()
+        });
         Ok(Game {
-            player: player.clone(),
-            world: World::new(DEFAULT_SEED, DEFAULT_BLOCK_SIZE, player),
+            state: GameState::MainMenu,
+            quit: false,
+            player: Self::new_player(&settings),
+            world: None,
+            remote: None,
             aim: Aim::new(
                 window.width() as f32,
                 window.height() as f32,
                 nalgebra_glm::vec3(1., 1., 1.),
             ),
+            hud: Hud::new(window.width() as f32, window.height() as f32),
+            menu: Menu::new(),
+            bindings,
+            inventory_ui: InventoryUi::new(),
+            debug: DebugOverlay::new(),
+            mouse,
+            outline: BlockOutline::new(nalgebra_glm::vec3(0., 0., 0.)),
+            sky: Sky::new(),
+            world_renderer: WorldRenderer::new(),
+            console: Console::spawn(),
+            recipes: RecipeBook::load(RECIPES_FILE)?,
+            settings,
+            window_changed: false,
 
             timer: window.timer()?,
             last_frame: 0,
@@ -53,76 +143,676 @@ impl Game {
         })
     }
 
+    fn new_player(settings: &Settings) -> Rc<RefCell<Player>> {
+        let mut player = Player::new(
+            *crate::render::ASPECT_RATIO.lock(),
+            settings.fov.to_radians(),
+            0.1f32,
+            200f32,
+        );
+        player.set_sensitivity(settings.sensitivity);
+        Rc::new(RefCell::new(player))
+    }
+
+    /// Fits the projection and the overlays to the new size of the window
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let (width, height) = (width as f32, height as f32);
+        self.hud.resize(width, height);
+        self.aim.resize(width, height);
+        // The player of the next world gets the new aspect ratio when it's created
+        if let Some(world) = &self.world {
+            World::update_player_vision(world);
+        }
+    }
+
+    /// False once the player has quit from a menu or the console
+    pub fn is_running(&self) -> bool {
+        !self.quit
+    }
+
     pub fn handle(&mut self, event: Event) {
+        match self.state {
+            GameState::Playing if self.inventory_ui.is_open() => self.handle_inventory(event),
+            GameState::Playing => self.handle_playing(event),
+            _ => self.handle_menu(event),
+        }
+    }
+
+    fn handle_playing(&mut self, event: Event) {
         match event {
-            Event::MouseButtonDown { mouse_btn, .. } => match mouse_btn {
-                sdl2::mouse::MouseButton::Left => self.world.player_destroy_block_if_possible(),
-                sdl2::mouse::MouseButton::Right => self.world.player_place_block_if_possible(),
-                _ => (),
-            },
-            Event::KeyDown { keycode, .. } => {
-                if keycode.is_none() {
-                    return;
-                }
-                match keycode.unwrap() {
-                    Keycode::W => self.player.borrow_mut().move_forward(),
-                    Keycode::A => self.player.borrow_mut().move_left(),
-                    Keycode::S => self.player.borrow_mut().move_backward(),
-                    Keycode::D => self.player.borrow_mut().move_right(),
-                    Keycode::Space => self.player.borrow_mut().move_up(),
-                    Keycode::LShift => self.player.borrow_mut().move_down(),
-                    Keycode::Num1 => self.player.borrow_mut().select_hotbar_cell(0),
-                    Keycode::Num2 => self.player.borrow_mut().select_hotbar_cell(1),
-                    Keycode::Num3 => self.player.borrow_mut().select_hotbar_cell(2),
-                    Keycode::Num4 => self.player.borrow_mut().select_hotbar_cell(3),
-                    Keycode::Num5 => self.player.borrow_mut().select_hotbar_cell(4),
-                    Keycode::Num6 => self.player.borrow_mut().select_hotbar_cell(5),
-                    Keycode::Num7 => self.player.borrow_mut().select_hotbar_cell(6),
-                    Keycode::Num8 => self.player.borrow_mut().select_hotbar_cell(7),
-                    Keycode::Num9 => self.player.borrow_mut().select_hotbar_cell(8),
-                    Keycode::Num0 => self.player.borrow_mut().select_hotbar_cell(9),
-                    _ => (),
+            Event::KeyDown {
+                keycode,
+                repeat: false,
+                ..
+            } => {
+                if let Some(action) = self.bindings.key_action(keycode) {
+                    self.start_action(action);
                 }
             }
             Event::KeyUp { keycode, .. } => {
-                if keycode.is_none() {
-                    return;
+                if let Some(action) = self.bindings.key_action(keycode) {
+                    self.stop_action(action);
                 }
-                match keycode.unwrap() {
-                    Keycode::W => self.player.borrow_mut().stop_move_forward(),
-                    Keycode::A => self.player.borrow_mut().stop_move_left(),
-                    Keycode::S => self.player.borrow_mut().stop_move_backward(),
-                    Keycode::D => self.player.borrow_mut().stop_move_right(),
-                    Keycode::Space => self.player.borrow_mut().stop_move_up(),
-                    Keycode::LShift => self.player.borrow_mut().stop_move_down(),
-                    _ => (),
+            }
+            Event::MouseButtonDown { mouse_btn, .. } => {
+                if let Some(action) = self.bindings.action(Input::Mouse(mouse_btn)) {
+                    self.start_action(action);
+                }
+            }
+            Event::MouseButtonUp { mouse_btn, .. } => {
+                if let Some(action) = self.bindings.action(Input::Mouse(mouse_btn)) {
+                    self.stop_action(action);
                 }
             }
             Event::MouseMotion { xrel, yrel, .. } => self
                 .player
                 .borrow_mut()
                 .rotate_camera_by_offsets(xrel as f32, yrel as f32),
+            Event::MouseWheel { y, .. } => self.scroll_hotbar(y),
+            _ => (),
+        }
+    }
+
+    /// Called when the key or the button of the action is pressed
+    fn start_action(&mut self, action: Action) {
+        let Some(world) = self.world.as_mut() else {
+            return;
+        };
+        match action {
+            Action::MoveForward => self.player.borrow_mut().move_forward(),
+            Action::MoveBackward => self.player.borrow_mut().move_backward(),
+            Action::MoveLeft => self.player.borrow_mut().move_left(),
+            Action::MoveRight => self.player.borrow_mut().move_right(),
+            Action::Jump => self.player.borrow_mut().move_up(),
+            Action::Sneak => self.player.borrow_mut().move_down(),
+            Action::Attack => world.player_start_breaking(),
+            Action::Use => world.player_place_block_if_possible(),
+            Action::Hotbar(cell) => self.player.borrow_mut().select_hotbar_cell(cell),
+            Action::Inventory => self.toggle_inventory(),
+            Action::Pause => self.pause(),
+            Action::DebugOverlay => self.debug.toggle(),
+            Action::ToggleAmbientOcclusion => world.toggle_ambient_occlusion(),
+            Action::ToggleSmoothLighting => world.toggle_smooth_lighting(),
+        }
+    }
+
+    /// Called when the key or the button of the action is released
+    fn stop_action(&mut self, action: Action) {
+        match action {
+            Action::MoveForward => self.player.borrow_mut().stop_move_forward(),
+            Action::MoveBackward => self.player.borrow_mut().stop_move_backward(),
+            Action::MoveLeft => self.player.borrow_mut().stop_move_left(),
+            Action::MoveRight => self.player.borrow_mut().stop_move_right(),
+            Action::Jump => self.player.borrow_mut().stop_move_up(),
+            Action::Sneak => self.player.borrow_mut().stop_move_down(),
+            Action::Attack => {
+                if let Some(world) = self.world.as_mut() {
+                    world.player_stop_breaking();
+                }
+            }
             _ => (),
         }
     }
 
+    /// Action of the key or the mouse button which is pressed or released in the event
+    fn event_action(&self, event: &Event) -> Option<Action> {
+        match event {
+            Event::KeyDown { keycode, .. } | Event::KeyUp { keycode, .. } => {
+                self.bindings.key_action(*keycode)
+            }
+            Event::MouseButtonDown { mouse_btn, .. } | Event::MouseButtonUp { mouse_btn, .. } => {
+                self.bindings.action(Input::Mouse(*mouse_btn))
+            }
+            _ => None,
+        }
+    }
+
+    /// Events while the inventory screen is open, the mouse moves items instead of the camera.
+    /// Attack drags the whole stack and Use drags half of it.
+    fn handle_inventory(&mut self, event: Event) {
+        let screen = (self.hud.width(), self.hud.height());
+        let action = self.event_action(&event);
+        match (event, action) {
+            (Event::KeyDown { repeat: true, .. }, _) => (),
+            (
+                Event::KeyDown { .. } | Event::MouseButtonDown { .. },
+                Some(Action::Inventory | Action::Pause),
+            ) => self.toggle_inventory(),
+            (
+                Event::KeyDown { .. } | Event::MouseButtonDown { .. },
+                Some(action @ (Action::Attack | Action::Use)),
+            ) => {
+                let mut player = self.player.borrow_mut();
+                self.inventory_ui
+                    .start_drag(player.inventory_mut(), screen, action == Action::Use);
+            }
+            (
+                Event::KeyUp { .. } | Event::MouseButtonUp { .. },
+                Some(Action::Attack | Action::Use),
+            ) => {
+                let mut player = self.player.borrow_mut();
+                self.inventory_ui
+                    .drop_dragged(player.inventory_mut(), screen);
+            }
+            (Event::MouseMotion { x, y, .. }, _) => {
+                self.inventory_ui.move_cursor(x as f32, y as f32)
+            }
+            (Event::MouseWheel { y, .. }, _) => self.scroll_hotbar(y),
+            _ => (),
+        }
+    }
+
+    /// Events of the menus, Attack clicks buttons, Pause goes back and the keyboard types into
+    /// text fields
+    /// Menus use fixed inputs of the interface, Escape and the left mouse button, so they work
+    /// whatever the key bindings of the game are
+    fn handle_menu(&mut self, event: Event) {
+        let screen = (self.hud.width(), self.hud.height());
+        match event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match (self.state, keycode) {
+                (GameState::Paused, Keycode::Escape) => self.resume(),
+                (GameState::WorldList | GameState::CreateWorld, Keycode::Escape) => {
+                    self.apply(MenuAction::Back)
+                }
+                (GameState::CreateWorld, Keycode::Backspace) => self.menu.erase(),
+                (GameState::CreateWorld, Keycode::Tab) => self.menu.focus_next(),
+                (GameState::CreateWorld, Keycode::Return) => self.apply(MenuAction::CreateWorld),
+                _ => (),
+            },
+            Event::TextInput { text, .. } if self.state == GameState::CreateWorld => {
+                self.menu.type_text(&text)
+            }
+            Event::MouseMotion { x, y, .. } => self.menu.move_cursor(x as f32, y as f32),
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                ..
+            } => {
+                if let Some(action) = self.menu.click(self.state, screen) {
+                    self.apply(action);
+                }
+            }
+            Event::MouseWheel { y, .. } => self.menu.scroll(y),
+            _ => (),
+        }
+    }
+
+    fn apply(&mut self, action: MenuAction) {
+        match action {
+            MenuAction::OpenWorldList => {
+                self.menu.refresh_worlds();
+                self.state = GameState::WorldList;
+            }
+            MenuAction::OpenCreateWorld => {
+                self.menu.reset_form();
+                self.state = GameState::CreateWorld;
+            }
+            MenuAction::LoadWorld(index) => {
+                if let Some(info) = self.menu.worlds().get(index).cloned() {
+                    self.open_world(info);
+                }
+            }
+            MenuAction::CreateWorld => {
+                let name = self.menu.new_world_name().to_string();
+                let seed = self.menu.new_world_seed();
+                match WorldInfo::create(Path::new(WORLDS_DIRECTORY), &name, seed) {
+                    Ok(info) => self.open_world(info),
+                    Err(error) => self
+                        .menu
+                        .set_message(format!("World cannot be created: {}", error)),
+                }
+            }
+            MenuAction::Focus(field) => self.menu.focus(field),
+            MenuAction::Back => {
+                if self.state == GameState::CreateWorld {
+                    self.apply(MenuAction::OpenWorldList);
+                } else {
+                    self.state = GameState::MainMenu;
+                }
+            }
+            MenuAction::Resume => self.resume(),
+            MenuAction::SaveAndExit => {
+                self.close_world();
+                self.state = GameState::MainMenu;
+            }
+            MenuAction::Quit => self.quit = true,
+        }
+    }
+
+    /// Generates the world around the spawn and restores the player saved in it
+    pub fn open_world(&mut self, info: WorldInfo) {
+        self.close_world();
+        self.player = Self::new_player(&self.settings);
+        let mut world = World::new(
+            info,
+            DEFAULT_BLOCK_SIZE,
+            self.player.clone(),
+            &self.settings,
+        );
+        if let Err(error) = world.restore_player() {
+            eprintln!("Player cannot be restored: {}", error);
+        }
+        self.start_playing(world);
+    }
+
+    /// Connects to the server, its world is opened when the server welcomes the player
+    pub fn join_server(&mut self, address: &str, name: &str) {
+        self.close_world();
+        self.state = GameState::MainMenu;
+        let address = server_address(address);
+        match NetClient::connect(&address, name) {
+            Ok(client) => {
+                self.remote = Some(Remote::new(client));
+                self.menu
+                    .set_message(format!("Connecting to {}...", address));
+            }
+            Err(error) => self
+                .menu
+                .set_message(format!("{} cannot be reached: {}", address, error)),
+        }
+    }
+
+    /// Generates the world of the server from its seed, the changed chunks come after it
+    fn open_remote_world(&mut self, seed: u32) {
+        let Some(remote) = &self.remote else {
+            return;
+        };
+        let info = WorldInfo::remote(&remote.server_address(), seed);
+        if let Some(world) = self.world.take() {
+            world.close();
+        }
+        self.player = Self::new_player(&self.settings);
+        let world = World::remote(
+            info,
+            DEFAULT_BLOCK_SIZE,
+            self.player.clone(),
+            &self.settings,
+        );
+        self.start_playing(world);
+    }
+
+    fn start_playing(&mut self, world: World) {
+        self.world = Some(world);
+        self.inventory_ui = InventoryUi::new();
+        self.state = GameState::Playing;
+        self.mouse.set_relative_mouse_mode(true);
+        // Generation takes a while, it shouldn't count as a frame of the new world
+        self.last_frame = self.timer.ticks();
+    }
+
+    /// Saves and unloads the world if it's loaded, the world of a server is left instead
+    fn close_world(&mut self) {
+        match self.remote.take() {
+            Some(remote) => remote.disconnect("The player has left"),
+            None => self.save(),
+        }
+        if let Some(world) = self.world.take() {
+            world.close();
+        }
+    }
+
+    /// Closes the world before the game exits
+    pub fn close(mut self) {
+        self.close_world();
+    }
+
+    /// The world stops and the mouse is released for the pause menu
+    fn pause(&mut self) {
+        self.player.borrow_mut().stop_moving();
+        if let Some(world) = self.world.as_mut() {
+            world.player_stop_breaking();
+        }
+        self.state = GameState::Paused;
+        self.mouse.set_relative_mouse_mode(false);
+    }
+
+    fn resume(&mut self) {
+        self.state = GameState::Playing;
+        self.mouse.set_relative_mouse_mode(true);
+    }
+
+    /// The mouse is released while the inventory screen is open, so it can point at slots
+    fn toggle_inventory(&mut self) {
+        {
+            let mut player = self.player.borrow_mut();
+            self.inventory_ui.toggle(player.inventory_mut());
+            if self.inventory_ui.is_open() {
+                player.stop_moving();
+            }
+        }
+        if let Some(world) = self.world.as_mut() {
+            world.player_stop_breaking();
+        }
+        self.mouse
+            .set_relative_mouse_mode(!self.inventory_ui.is_open());
+    }
+
+    /// Scrolling up selects the previous cell, the selection wraps around the hotbar
+    fn scroll_hotbar(&mut self, offset: i32) {
+        let mut player = self.player.borrow_mut();
+        let cell = player.selected_hotbar_cell() as i32 - offset;
+        player.select_hotbar_cell(cell.rem_euclid(HOTBAR_SIZE as i32) as usize);
+    }
+
     pub fn update(&mut self) {
         let current = self.timer.ticks();
         let delta_time = (current - self.last_frame) as f32 / 1000.0;
 
-        self.world.update(delta_time);
-        self.world.update_state();
+        while let Some(command) = self.console.poll() {
+            self.execute(command);
+        }
+        self.receive_from_server();
+        if let Some(world) = self.world.as_mut() {
+            if self.state == GameState::Playing {
+                world.update(delta_time);
+                self.sky.update(delta_time);
+            }
+            world.update_state();
+            if let Some(notice) = world.take_notice() {
+                self.inventory_ui.show_notice(notice);
+            }
+        }
+        self.send_to_server();
+        self.debug.update(delta_time);
+        self.inventory_ui.update(delta_time);
 
         self.last_frame = current;
     }
 
+    /// Applies what has happened on the server since the last frame
+    fn receive_from_server(&mut self) {
+        let Some(remote) = self.remote.as_mut() else {
+            return;
+        };
+        for message in remote.poll() {
+            match message {
+                Message::Welcome { seed, .. } => self.open_remote_world(seed),
+                Message::ChunkData { x, z, blocks } => {
+                    let Some(world) = self.world.as_mut() else {
+                        continue;
+                    };
+                    let (x, z) = (x as isize, z as isize);
+                    match Chunk::decode_blocks(x, z, DEFAULT_BLOCK_SIZE, &blocks) {
+                        Ok(chunk) => world.replace_chunk(x, z, chunk),
+                        Err(error) => eprintln!("Chunk from the server is broken: {}", error),
+                    }
+                }
+                Message::BlockChange { x, y, z, block } => {
+                    if let Some(world) = self.world.as_mut() {
+                        world.set_block((x as isize, y as isize, z as isize), block);
+                    }
+                }
+                Message::Disconnect { reason } => {
+                    self.remote = None;
+                    if let Some(world) = self.world.take() {
+                        world.close();
+                    }
+                    self.state = GameState::MainMenu;
+                    self.mouse.set_relative_mouse_mode(false);
+                    self.menu.set_message(reason);
+                    return;
+                }
+                // The other players are updated by the connection
+                Message::Hello { .. }
+                | Message::PlayerJoined { .. }
+                | Message::PlayerMove { .. }
+                | Message::PlayerLeft { .. }
+                | Message::Chat { .. } => (),
+            }
+        }
+    }
+
+    /// Sends the blocks which the player has changed and where the player is
+    fn send_to_server(&mut self) {
+        let Some(world) = self.world.as_mut() else {
+            return;
+        };
+        // A world of this computer drops the changes, there is nobody to send them to
+        let changes = world.take_player_changes();
+        let Some(remote) = self.remote.as_mut() else {
+            return;
+        };
+        for ((x, y, z), block) in changes {
+            remote.send(&Self::block_change(x, y, z, block));
+        }
+        remote.send_move(&self.player.borrow());
+    }
+
+    fn block_change(x: isize, y: isize, z: isize, block: BlockState) -> Message {
+        Message::BlockChange {
+            x: x as i64,
+            y: y as i64,
+            z: z as i64,
+            block,
+        }
+    }
+
+    /// Saves the world if it's loaded, the world of a server is saved by the server
+    pub fn save(&self) {
+        let Some(world) = &self.world else {
+            return;
+        };
+        if self.remote.is_some() {
+            return;
+        }
+        if let Err(error) = world.save() {
+            eprintln!("World cannot be saved: {}", error);
+        }
+    }
+
+    fn execute(&mut self, command: Command) {
+        match command {
+            Command::Bind(action, input) => return self.bind(&action, &input),
+            Command::ListBindings => return self.list_bindings(),
+            Command::Set(name, value) => return self.change_setting(&name, &value),
+            Command::ListSettings => return self.list_settings(),
+            Command::Stop => {
+                self.quit = true;
+                return;
+            }
+            Command::Say(text) => return self.say(text),
+            Command::ListPlayers => return self.list_players(),
+            _ => (),
+        }
+        let Some(world) = self.world.as_mut() else {
+            eprintln!("Commands need a loaded world");
+            return;
+        };
+        let clock = world.clock_mut();
+        match command {
+            Command::SetTime(time) => clock.set_time_of_day(time),
+            Command::FreezeTime => clock.set_frozen(true),
+            Command::UnfreezeTime => clock.set_frozen(false),
+            Command::SetDayLength(seconds) => clock.set_day_length(seconds),
+            Command::SetGameMode(game_mode) => self.player.borrow_mut().set_game_mode(game_mode),
+            Command::Craft(name) => self.craft(&name),
+            Command::ListRecipes => self.list_recipes(),
+            Command::Save => self.save(),
+            Command::Bind(..)
+            | Command::ListBindings
+            | Command::Set(..)
+            | Command::ListSettings
+            | Command::Stop
+            | Command::Say(..)
+            | Command::ListPlayers => (),
+        }
+    }
+
+    fn say(&mut self, text: String) {
+        let Some(remote) = self.remote.as_mut() else {
+            eprintln!("Chat is available only on a server");
+            return;
+        };
+        let sender = String::new();
+        remote.send(&Message::Chat { sender, text });
+    }
+
+    fn list_players(&self) {
+        let Some(remote) = &self.remote else {
+            eprintln!("Players are listed only on a server");
+            return;
+        };
+        for player in remote.players() {
+            println!("{}", player.name);
+        }
+    }
+
+    /// Rebinds the action and saves the bindings, so they stay after restarting the game
+    fn bind(&mut self, action: &str, input: &str) {
+        let Some(action) = Action::from_name(action) else {
+            eprintln!("Unknown action: {}", action);
+            return;
+        };
+        let Some(input) = Input::from_name(input) else {
+            eprintln!("Unknown key or button: {}", input);
+            return;
+        };
+        self.bindings.bind(action, input);
+        println!("{} is bound to {}", action, input);
+        if let Err(error) = self.bindings.save(CONTROLS_FILE) {
+            eprintln!("Key bindings cannot be saved: {}", error);
+        }
+    }
+
+    /// Changes the setting, applies it to the world and the window and saves the settings
+    fn change_setting(&mut self, name: &str, value: &str) {
+        let previous = self.settings.clone();
+        if let Err(error) = self.settings.set(name, value) {
+            eprintln!("{}", error);
+            return;
+        }
+        self.player
+            .borrow_mut()
+            .set_sensitivity(self.settings.sensitivity);
+        if let Some(world) = self.world.as_mut() {
+            if self.settings.render_distance != previous.render_distance {
+                world.set_render_distance(self.settings.render_distance);
+            }
+            world.set_fov(self.settings.fov);
+        }
+        self.window_changed |= self.settings.window_differs(&previous);
+        if let Some(value) = self.settings.get(name) {
+            println!("{} = {}", name, value);
+        }
+        if let Err(error) = self.settings.save(SETTINGS_FILE) {
+            eprintln!("Settings cannot be saved: {}", error);
+        }
+    }
+
+    fn list_settings(&self) {
+        for name in Settings::NAMES {
+            if let Some(value) = self.settings.get(name) {
+                println!("{} = {}", name, value);
+            }
+        }
+    }
+
+    /// Settings to apply to the window if they have changed since the last call
+    pub fn take_window_settings(&mut self) -> Option<Settings> {
+        if !self.window_changed {
+            return None;
+        }
+        self.window_changed = false;
+        Some(self.settings.clone())
+    }
+
+    fn list_bindings(&self) {
+        for action in Action::all() {
+            match self.bindings.input(action) {
+                Some(input) => println!("{} = {}", action, input),
+                None => println!("{} is not bound", action),
+            }
+        }
+    }
+
+    fn craft(&mut self, name: &str) {
+        let Some(world) = &self.world else {
+            return;
+        };
+        let Some(recipe) = self.recipes.get(name) else {
+            eprintln!("Unknown recipe: {}", name);
+            return;
+        };
+        let near = world.stations_near_player();
+        match self.player.borrow_mut().craft(recipe, &near) {
+            Ok(item) => println!("Crafted {} x {}", item.count(), item.name()),
+            Err(error) => eprintln!("{} cannot be crafted: {}", name, error),
+        }
+    }
+
+    /// Prints recipes which can be crafted with the stations near the player
+    fn list_recipes(&self) {
+        let Some(world) = &self.world else {
+            return;
+        };
+        let near = world.stations_near_player();
+        for recipe in self.recipes.available(&near) {
+            println!("{} ({})", recipe.name(), recipe.station());
+        }
+    }
+
     pub fn render(&mut self) {
+        let Some(world) = &self.world else {
+            unsafe {
+                gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            }
+            self.menu.render(&mut self.hud, self.state);
+            self.hud.render();
+            return;
+        };
+        let sky = world.clock().sky_color();
         unsafe {
+            gl::ClearColor(sky.x, sky.y, sky.z, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
 
-        self.world.render();
-        self.aim.render();
+        let player = self.player.borrow();
+        self.sky.render(
+            player.projection(),
+            &player.look_at(),
+            &player.position(),
+            world.clock(),
+        );
+        self.world_renderer.render(world, &player);
+        if let Some(position) = world.target_block_position() {
+            self.outline.render(
+                player.projection(),
+                &player.look_at(),
+                &position,
+                DEFAULT_BLOCK_SIZE,
+            );
+        }
+        if let Some(remote) = &self.remote {
+            // Other players are two outlined blocks, from their feet to their eyes
+            for position in remote.players().filter_map(|other| other.position) {
+                for height in [1.5, 0.5] {
+                    let corner = position - nalgebra_glm::vec3(0.5, height, 0.5);
+                    self.outline.render(
+                        player.projection(),
+                        &player.look_at(),
+                        &corner,
+                        DEFAULT_BLOCK_SIZE,
+                    );
+                }
+            }
+        }
+        if self.state == GameState::Playing && !self.inventory_ui.is_open() {
+            self.aim.render();
+        }
+        self.inventory_ui.render(
+            &mut self.hud,
+            player.inventory(),
+            player.selected_hotbar_cell(),
+        );
+        self.debug.render(&mut self.hud, world, &player);
+        self.menu.render(&mut self.hud, self.state);
+        self.hud.render();
     }
 
     pub fn new_loop(&mut self) {
@@ -131,12 +821,12 @@ impl Game {
 
     const FPS60: f32 = 16.66668;
 
-    pub fn fps(&mut self) -> f32 {
+    /// Waits for the rest of the frame, so the game runs at about 60 frames per second
+    pub fn limit_frame_rate(&mut self) {
         let elapsed = (self.timer.performance_counter() - self.start_performance_counter) as f32
             / self.timer.performance_frequency() as f32
             * 1000.0;
         let normalized_fps = (Self::FPS60 - elapsed).floor();
         self.timer.delay(normalized_fps as u32);
-        1000f32 / elapsed
     }
 }
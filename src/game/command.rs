@@ -0,0 +1,201 @@
+use std::io::BufRead;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+
+use super::player::GameMode;
+use super::world::clock::WorldClock;
+
+/// Commands which can be typed into the console while the game runs
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+    /// Time of the day as a part of the full day, see `WorldClock`
+    SetTime(f32),
+    FreezeTime,
+    UnfreezeTime,
+    /// Length of the full day in seconds
+    SetDayLength(f32),
+    SetGameMode(GameMode),
+    /// Name of the recipe, see `RecipeBook`
+    Craft(String),
+    /// Recipes which can be crafted where the player stands
+    ListRecipes,
+    /// Names of the action and of the key or the mouse button, see `KeyBindings`
+    Bind(String, String),
+    ListBindings,
+    /// Name and value of a setting, see `Settings::set`
+    Set(String, String),
+    ListSettings,
+    /// Saves the loaded world right away
+    Save,
+    /// Saves the world and quits, it stops the server too
+    Stop,
+    /// Chat message to every player on the server
+    Say(String),
+    /// Players who are connected to the server
+    ListPlayers,
+}
+
+impl Command {
+    pub const USAGE: &'static str = "Commands:
+    time set <sunrise|day|noon|sunset|night|midnight|0.0..1.0>
+    time freeze
+    time unfreeze
+    daylength <seconds>
+    gamemode <survival|creative>
+    craft <recipe>
+    recipes
+    bind <action> <key|Mouse Left|Mouse Right|...>
+    bindings
+    set <render_distance|fov|sensitivity|window_width|window_height|vsync|fullscreen> <value>
+    settings
+    save
+    stop
+    say <message>
+    players";
+
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        match words.as_slice() {
+            ["time", "set", value] => Self::parse_time(value).map(Command::SetTime),
+            ["time", "freeze"] => Ok(Command::FreezeTime),
+            ["time", "unfreeze"] => Ok(Command::UnfreezeTime),
+            ["daylength", seconds] => match seconds.parse::<f32>() {
+                Ok(seconds) if seconds > 0.0 && seconds.is_finite() => {
+                    Ok(Command::SetDayLength(seconds))
+                }
+                _ => Err(format!(
+                    "Day length must be a positive number, got: {}",
+                    seconds
+                )),
+            },
+            ["gamemode", "survival"] => Ok(Command::SetGameMode(GameMode::Survival)),
+            ["gamemode", "creative"] => Ok(Command::SetGameMode(GameMode::Creative)),
+            ["craft", name @ ..] if !name.is_empty() => Ok(Command::Craft(name.join(" "))),
+            ["recipes"] => Ok(Command::ListRecipes),
+            ["bind", action, input @ ..] if !input.is_empty() => {
+                Ok(Command::Bind(action.to_string(), input.join(" ")))
+            }
+            ["bindings"] => Ok(Command::ListBindings),
+            ["set", name, value] => Ok(Command::Set(name.to_string(), value.to_string())),
+            ["settings"] => Ok(Command::ListSettings),
+            ["save"] => Ok(Command::Save),
+            ["stop"] => Ok(Command::Stop),
+            ["say", text @ ..] if !text.is_empty() => Ok(Command::Say(text.join(" "))),
+            ["players"] => Ok(Command::ListPlayers),
+            _ => Err(format!("Unknown command: {}\n{}", line.trim(), Self::USAGE)),
+        }
+    }
+
+    fn parse_time(value: &str) -> Result<f32, String> {
+        match value {
+            "sunrise" => Ok(WorldClock::SUNRISE),
+            "day" => Ok(WorldClock::SUNRISE + 0.05),
+            "noon" => Ok(WorldClock::NOON),
+            "sunset" => Ok(WorldClock::SUNSET),
+            "night" => Ok(WorldClock::SUNSET + 0.05),
+            "midnight" => Ok(WorldClock::MIDNIGHT),
+            number => match number.parse::<f32>() {
+                Ok(time) if (0.0..1.0).contains(&time) => Ok(time),
+                _ => Err(format!(
+                    "Time must be in range from 0 to 1, got: {}",
+                    number
+                )),
+            },
+        }
+    }
+}
+
+/// Reads commands from the standard input in a separate thread
+pub struct Console {
+    receiver: Receiver<String>,
+}
+
+impl Console {
+    pub fn spawn() -> Self {
+        let (sender, receiver) = channel();
+        std::thread::Builder::new()
+            .name(String::from("Console"))
+            .spawn(move || {
+                for line in std::io::stdin().lock().lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            })
+            .unwrap();
+        Self { receiver }
+    }
+
+    /// Returns the next typed command if there is any, wrong commands are reported and skipped
+    pub fn poll(&self) -> Option<Command> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => match Command::parse(&line) {
+                    Ok(command) => return Some(command),
+                    Err(error) => eprintln!("{}", error),
+                },
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_parsed_with_their_arguments() {
+        assert_eq!(
+            Command::parse("time set noon"),
+            Ok(Command::SetTime(WorldClock::NOON))
+        );
+        assert_eq!(Command::parse("  time set 0.3 "), Ok(Command::SetTime(0.3)));
+        assert_eq!(Command::parse("time freeze"), Ok(Command::FreezeTime));
+        assert_eq!(
+            Command::parse("daylength 60"),
+            Ok(Command::SetDayLength(60.0))
+        );
+        assert_eq!(
+            Command::parse("gamemode creative"),
+            Ok(Command::SetGameMode(GameMode::Creative))
+        );
+        assert_eq!(
+            Command::parse("craft wooden pickaxe"),
+            Ok(Command::Craft(String::from("wooden pickaxe")))
+        );
+        assert_eq!(
+            Command::parse("bind jump Mouse Right"),
+            Ok(Command::Bind(
+                String::from("jump"),
+                String::from("Mouse Right")
+            ))
+        );
+        assert_eq!(
+            Command::parse("say hello  there"),
+            Ok(Command::Say(String::from("hello there")))
+        );
+    }
+
+    #[test]
+    fn wrong_commands_are_rejected() {
+        // Missing arguments
+        assert!(Command::parse("time set").is_err());
+        assert!(Command::parse("daylength").is_err());
+        assert!(Command::parse("craft").is_err());
+        assert!(Command::parse("say").is_err());
+        assert!(Command::parse("bind jump").is_err());
+        // Bad numbers
+        assert!(Command::parse("time set 1.5").is_err());
+        assert!(Command::parse("time set later").is_err());
+        assert!(Command::parse("daylength -5").is_err());
+        assert!(Command::parse("daylength long").is_err());
+        assert!(Command::parse("daylength inf").is_err());
+        assert!(Command::parse("daylength NaN").is_err());
+        assert!(Command::parse("gamemode spectator").is_err());
+        assert!(Command::parse("fly").is_err());
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+
+use super::player::inventory::HOTBAR_SIZE;
+
+/// Where the key bindings are saved when they are changed
+pub const CONTROLS_FILE: &str = "data/controls.toml";
+
+/// What the player can do with a key or a mouse button while playing
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Sneak,
+    /// Breaks the target block while it's held
+    Attack,
+    /// Places the held block
+    Use,
+    /// Selects the cell of the hotbar, counted from 0
+    Hotbar(usize),
+    Inventory,
+    Pause,
+    DebugOverlay,
+    ToggleAmbientOcclusion,
+    ToggleSmoothLighting,
+}
+
+impl Action {
+    /// Every action in the order of the controls file
+    pub fn all() -> Vec<Action> {
+        let mut actions = vec![
+            Action::MoveForward,
+            Action::MoveBackward,
+            Action::MoveLeft,
+            Action::MoveRight,
+            Action::Jump,
+            Action::Sneak,
+            Action::Attack,
+            Action::Use,
+        ];
+        actions.extend((0..HOTBAR_SIZE).map(Action::Hotbar));
+        actions.extend([
+            Action::Inventory,
+            Action::Pause,
+            Action::DebugOverlay,
+            Action::ToggleAmbientOcclusion,
+            Action::ToggleSmoothLighting,
+        ]);
+        actions
+    }
+
+    /// Name of the action in the controls file and console commands
+    pub fn name(&self) -> String {
+        let name = match self {
+            Action::MoveForward => "move_forward",
+            Action::MoveBackward => "move_backward",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Jump => "jump",
+            Action::Sneak => "sneak",
+            Action::Attack => "attack",
+            Action::Use => "use",
+            Action::Hotbar(cell) => return format!("hotbar_{}", cell + 1),
+            Action::Inventory => "inventory",
+            Action::Pause => "pause",
+            Action::DebugOverlay => "debug_overlay",
+            Action::ToggleAmbientOcclusion => "toggle_ambient_occlusion",
+            Action::ToggleSmoothLighting => "toggle_smooth_lighting",
+        };
+        String::from(name)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().into_iter().find(|action| action.name() == name)
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Key or mouse button which triggers an action
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Input {
+    Key(Keycode),
+    Mouse(MouseButton),
+}
+
+/// Names of keys which are not a single letter or digit
+const KEY_NAMES: [(&str, Keycode); 28] = [
+    ("Space", Keycode::Space),
+    ("Escape", Keycode::Escape),
+    ("Tab", Keycode::Tab),
+    ("Return", Keycode::Return),
+    ("Backspace", Keycode::Backspace),
+    ("Left Shift", Keycode::LShift),
+    ("Right Shift", Keycode::RShift),
+    ("Left Ctrl", Keycode::LCtrl),
+    ("Right Ctrl", Keycode::RCtrl),
+    ("Left Alt", Keycode::LAlt),
+    ("Right Alt", Keycode::RAlt),
+    ("Up", Keycode::Up),
+    ("Down", Keycode::Down),
+    ("Left", Keycode::Left),
+    ("Right", Keycode::Right),
+    ("CapsLock", Keycode::CapsLock),
+    ("F1", Keycode::F1),
+    ("F2", Keycode::F2),
+    ("F3", Keycode::F3),
+    ("F4", Keycode::F4),
+    ("F5", Keycode::F5),
+    ("F6", Keycode::F6),
+    ("F7", Keycode::F7),
+    ("F8", Keycode::F8),
+    ("F9", Keycode::F9),
+    ("F10", Keycode::F10),
+    ("F11", Keycode::F11),
+    ("F12", Keycode::F12),
+];
+
+const MOUSE_NAMES: [(&str, MouseButton); 5] = [
+    ("Mouse Left", MouseButton::Left),
+    ("Mouse Right", MouseButton::Right),
+    ("Mouse Middle", MouseButton::Middle),
+    ("Mouse X1", MouseButton::X1),
+    ("Mouse X2", MouseButton::X2),
+];
+
+impl Input {
+    /// Parses names like "W", "7", "Left Shift" or "Mouse Left", letters can be in any case
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if let Some((_, button)) = MOUSE_NAMES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        {
+            return Some(Input::Mouse(*button));
+        }
+        if let Some((_, keycode)) = KEY_NAMES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        {
+            return Some(Input::Key(*keycode));
+        }
+        let mut characters = name.chars();
+        match (characters.next(), characters.next()) {
+            // Keycodes of letters and digits are their lowercase ASCII codes
+            (Some(character), None) if character.is_ascii_alphanumeric() => {
+                Keycode::from_i32(character.to_ascii_lowercase() as i32).map(Input::Key)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Input::Mouse(button) => MOUSE_NAMES
+                .iter()
+                .find(|(_, known)| known == button)
+                .map(|(name, _)| String::from(*name))
+                .unwrap_or_else(|| format!("{:?}", button)),
+            Input::Key(keycode) => {
+                if let Some((name, _)) = KEY_NAMES.iter().find(|(_, known)| known == keycode) {
+                    return String::from(*name);
+                }
+                match u8::try_from(*keycode as i32) {
+                    Ok(code) if code.is_ascii_alphanumeric() => {
+                        (code as char).to_ascii_uppercase().to_string()
+                    }
+                    _ => format!("{:?}", keycode),
+                }
+            }
+        }
+    }
+}
+
+impl Display for Input {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Inputs of every action, an action has at most one input and an input triggers at most one
+/// action
+#[derive(Clone, PartialEq, Debug)]
+pub struct KeyBindings {
+    inputs: HashMap<Action, Input>,
+}
+
+impl KeyBindings {
+    pub fn new() -> Self {
+        let mut inputs = HashMap::from([
+            (Action::MoveForward, Input::Key(Keycode::W)),
+            (Action::MoveBackward, Input::Key(Keycode::S)),
+            (Action::MoveLeft, Input::Key(Keycode::A)),
+            (Action::MoveRight, Input::Key(Keycode::D)),
+            (Action::Jump, Input::Key(Keycode::Space)),
+            (Action::Sneak, Input::Key(Keycode::LShift)),
+            (Action::Attack, Input::Mouse(MouseButton::Left)),
+            (Action::Use, Input::Mouse(MouseButton::Right)),
+            (Action::Inventory, Input::Key(Keycode::E)),
+            (Action::Pause, Input::Key(Keycode::Escape)),
+            (Action::DebugOverlay, Input::Key(Keycode::F3)),
+            (Action::ToggleAmbientOcclusion, Input::Key(Keycode::F6)),
+            (Action::ToggleSmoothLighting, Input::Key(Keycode::F7)),
+        ]);
+        for cell in 0..HOTBAR_SIZE {
+            let digit = (cell + 1) % 10;
+            if let Some(keycode) = Keycode::from_i32('0' as i32 + digit as i32) {
+                inputs.insert(Action::Hotbar(cell), Input::Key(keycode));
+            }
+        }
+        Self { inputs }
+    }
+
+    /// Reads the bindings from the controls file, actions which are not in the file keep their
+    /// default inputs. The defaults are used if there is no file yet.
+    pub fn load(path: &str) -> Result<Self, String> {
+        if !Path::new(path).exists() {
+            return Ok(Self::new());
+        }
+        let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+        Self::parse(&text)
+    }
+
+    /// Bindings of the controls file, an input can be bound to only one action there
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let entries: HashMap<String, String> =
+            toml::from_str(text).map_err(|error| error.to_string())?;
+        let mut inputs = HashMap::new();
+        for (action, input) in entries {
+            let Some(action) = Action::from_name(&action) else {
+                return Err(format!("Unknown action: {}", action));
+            };
+            let Some(input) = Input::from_name(&input) else {
+                return Err(format!("Unknown key or button of {}: {}", action, input));
+            };
+            let other = inputs
+                .iter()
+                .find(|(_, bound)| **bound == input)
+                .map(|(other, _): (&Action, _)| *other);
+            if let Some(other) = other {
+                // Sorted, so the message doesn't depend on the order of the entries
+                let mut names = [other.name(), action.name()];
+                names.sort();
+                return Err(format!(
+                    "{} is bound to both {} and {}",
+                    input, names[0], names[1]
+                ));
+            }
+            inputs.insert(action, input);
+        }
+        // Applied in a fixed order, so actions which give their inputs away always get the same
+        let mut bindings = Self::new();
+        for action in Action::all() {
+            if let Some(input) = inputs.get(&action) {
+                bindings.bind(action, *input);
+            }
+        }
+        Ok(bindings)
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        if let Some(directory) = Path::new(path).parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::write(path, self.to_toml())
+    }
+
+    fn to_toml(&self) -> String {
+        let mut text = String::from("# Key bindings, see `bind` in the console to change them\n");
+        for action in Action::all() {
+            if let Some(input) = self.input(action) {
+                text.push_str(&format!("{} = \"{}\"\n", action, input));
+            }
+        }
+        text
+    }
+
+    /// Binds the input to the action. The action which had the input before gets the previous
+    /// input of this action, so no action is left without an input.
+    pub fn bind(&mut self, action: Action, input: Input) {
+        let previous = self.inputs.insert(action, input);
+        let other = self
+            .inputs
+            .iter()
+            .find(|(other, bound)| **other != action && **bound == input)
+            .map(|(other, _)| *other);
+        if let Some(other) = other {
+            match previous {
+                Some(previous) => self.inputs.insert(other, previous),
+                None => self.inputs.remove(&other),
+            };
+        }
+    }
+
+    pub fn input(&self, action: Action) -> Option<Input> {
+        self.inputs.get(&action).copied()
+    }
+
+    pub fn action(&self, input: Input) -> Option<Action> {
+        self.inputs
+            .iter()
+            .find(|(_, bound)| **bound == input)
+            .map(|(action, _)| *action)
+    }
+
+    pub fn key_action(&self, keycode: Option<Keycode>) -> Option<Action> {
+        self.action(Input::Key(keycode?))
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_of_actions_and_inputs_round_trip() {
+        for action in Action::all() {
+            assert_eq!(Action::from_name(&action.name()), Some(action));
+        }
+        for (_, input) in KeyBindings::new().inputs {
+            assert_eq!(Input::from_name(&input.name()), Some(input));
+        }
+        assert_eq!(Input::from_name("q"), Some(Input::Key(Keycode::Q)));
+        assert_eq!(
+            Input::from_name("left shift"),
+            Some(Input::Key(Keycode::LShift))
+        );
+        assert_eq!(
+            Input::from_name("Mouse X2"),
+            Some(Input::Mouse(MouseButton::X2))
+        );
+        assert_eq!(Input::from_name("Nothing"), None);
+    }
+
+    #[test]
+    fn rebinding_swaps_inputs_of_actions() {
+        let mut bindings = KeyBindings::new();
+        bindings.bind(Action::Jump, Input::Key(Keycode::W));
+        assert_eq!(bindings.action(Input::Key(Keycode::W)), Some(Action::Jump));
+        assert_eq!(
+            bindings.action(Input::Key(Keycode::Space)),
+            Some(Action::MoveForward)
+        );
+        assert_eq!(bindings.key_action(None), None);
+    }
+
+    #[test]
+    fn file_overrides_only_listed_actions() {
+        let bindings =
+            KeyBindings::parse("jump = \"Left Ctrl\"\nhotbar_10 = \"Mouse Middle\"\n").unwrap();
+        assert_eq!(
+            bindings.input(Action::Jump),
+            Some(Input::Key(Keycode::LCtrl))
+        );
+        assert_eq!(
+            bindings.input(Action::Hotbar(9)),
+            Some(Input::Mouse(MouseButton::Middle))
+        );
+        assert_eq!(
+            bindings.input(Action::Sneak),
+            Some(Input::Key(Keycode::LShift))
+        );
+        assert_eq!(KeyBindings::parse(&bindings.to_toml()), Ok(bindings));
+
+        assert!(KeyBindings::parse("fly = \"F\"").is_err());
+        assert!(KeyBindings::parse("jump = \"Nothing\"").is_err());
+        assert_eq!(
+            KeyBindings::parse("sneak = \"Q\"\njump = \"Q\"\n"),
+            Err(String::from("Q is bound to both jump and sneak"))
+        );
+    }
+}
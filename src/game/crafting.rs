@@ -0,0 +1,471 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use super::player::inventory::{Count, Item, Tier, ToolKind, WeaponKind};
+use super::world::block_state::BlockState;
+use crate::render::block::BlockType;
+
+pub const RECIPES_FILE: &str = "res/recipes.toml";
+
+/// Block which has to be near the player to craft some recipes
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Station {
+    /// Recipes which can be crafted anywhere
+    #[default]
+    Hand,
+    Workbench,
+    Furnace,
+}
+
+impl Station {
+    pub fn of_block(block: BlockType) -> Option<Station> {
+        match block {
+            BlockType::WORKBENCH => Some(Station::Workbench),
+            BlockType::FURNACE => Some(Station::Furnace),
+            _ => None,
+        }
+    }
+
+    /// Hand recipes are always unlocked, others need their station among the near ones
+    pub fn is_unlocked(self, near: &[Station]) -> bool {
+        self == Station::Hand || near.contains(&self)
+    }
+}
+
+impl std::fmt::Display for Station {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Station::Hand => write!(f, "hand"),
+            Station::Workbench => write!(f, "workbench"),
+            Station::Furnace => write!(f, "furnace"),
+        }
+    }
+}
+
+/// Cells where the player puts ingredients, block ids row by row
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CraftingGrid {
+    width: usize,
+    cells: Vec<Option<u16>>,
+}
+
+impl CraftingGrid {
+    pub const SIZE: usize = 3;
+
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            cells: vec![None; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len().checked_div(self.width).unwrap_or(0)
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<u16> {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, id: Option<u16>) {
+        self.cells[y * self.width + x] = id;
+    }
+
+    /// The smallest grid with all filled cells, so a shape can be put anywhere in the grid
+    fn trimmed(&self) -> CraftingGrid {
+        let filled = (0..self.height())
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.get(x, y).is_some())
+            .collect::<Vec<_>>();
+        let (Some(xmin), Some(xmax)) = (
+            filled.iter().map(|&(x, _)| x).min(),
+            filled.iter().map(|&(x, _)| x).max(),
+        ) else {
+            return CraftingGrid::new(0, 0);
+        };
+        let ymin = filled.iter().map(|&(_, y)| y).min().unwrap();
+        let ymax = filled.iter().map(|&(_, y)| y).max().unwrap();
+
+        let mut trimmed = CraftingGrid::new(xmax - xmin + 1, ymax - ymin + 1);
+        for y in ymin..=ymax {
+            for x in xmin..=xmax {
+                trimmed.set(x - xmin, y - ymin, self.get(x, y));
+            }
+        }
+        trimmed
+    }
+
+    /// Ids of the filled cells in ascending order
+    fn ingredients(&self) -> Vec<u16> {
+        let mut ingredients = self.cells.iter().flatten().copied().collect::<Vec<u16>>();
+        ingredients.sort_unstable();
+        ingredients
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Shape {
+    /// Trimmed pattern, it matches wherever it's placed in the grid
+    Shaped(CraftingGrid),
+    /// Ingredient ids in ascending order, their placement doesn't matter
+    Shapeless(Vec<u16>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Recipe {
+    name: String,
+    station: Station,
+    shape: Shape,
+    result: Item,
+}
+
+impl Recipe {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn station(&self) -> Station {
+        self.station
+    }
+
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    pub fn result(&self) -> Item {
+        self.result
+    }
+
+    /// Ids of the ingredients with how many of each are used, in ascending order of ids
+    pub fn ingredients(&self) -> Vec<(u16, usize)> {
+        let ids = match &self.shape {
+            Shape::Shaped(pattern) => pattern.ingredients(),
+            Shape::Shapeless(ids) => ids.clone(),
+        };
+        let mut ingredients: Vec<(u16, usize)> = vec![];
+        for id in ids {
+            match ingredients.last_mut() {
+                Some((last, count)) if *last == id => *count += 1,
+                _ => ingredients.push((id, 1)),
+            }
+        }
+        ingredients
+    }
+
+    pub fn matches(&self, grid: &CraftingGrid) -> bool {
+        match &self.shape {
+            Shape::Shaped(pattern) => grid.trimmed() == *pattern,
+            Shape::Shapeless(ids) => grid.ingredients() == *ids,
+        }
+    }
+}
+
+/// Every recipe of the game, see `RECIPES_FILE` for the format
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct RecipeBook {
+    recipes: Vec<Recipe>,
+}
+
+impl RecipeBook {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| format!("Recipes cannot be read from {}: {}", path, error))?;
+        Self::parse(&source).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let file: RecipeFile = toml::from_str(source).map_err(|error| error.to_string())?;
+        let recipes = file
+            .recipes
+            .into_iter()
+            .map(RecipeEntry::into_recipe)
+            .collect::<Result<Vec<Recipe>, String>>()?;
+        Ok(Self { recipes })
+    }
+
+    pub fn recipes(&self) -> &[Recipe] {
+        &self.recipes
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Recipe> {
+        self.recipes.iter().find(|recipe| recipe.name == name)
+    }
+
+    /// Recipes which can be crafted with the stations near the player
+    pub fn available<'a>(&'a self, near: &'a [Station]) -> impl Iterator<Item = &'a Recipe> {
+        self.recipes
+            .iter()
+            .filter(|recipe| recipe.station.is_unlocked(near))
+    }
+
+    /// Recipe which the grid forms, if its station is near the player
+    pub fn find(&self, grid: &CraftingGrid, near: &[Station]) -> Option<&Recipe> {
+        self.recipes
+            .iter()
+            .find(|recipe| recipe.station.is_unlocked(near) && recipe.matches(grid))
+    }
+}
+
+#[derive(Deserialize)]
+struct RecipeFile {
+    #[serde(default, rename = "recipe")]
+    recipes: Vec<RecipeEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecipeEntry {
+    name: String,
+    #[serde(default)]
+    station: Station,
+    /// Rows of the shape, every character is a key of an ingredient or a space for an empty cell
+    pattern: Option<Vec<String>>,
+    #[serde(default)]
+    key: HashMap<char, String>,
+    shapeless: Option<Vec<String>>,
+    result: ResultEntry,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResultEntry {
+    block: Option<String>,
+    tool: Option<ToolKind>,
+    weapon: Option<WeaponKind>,
+    tier: Option<Tier>,
+    #[serde(default = "ResultEntry::default_count")]
+    count: usize,
+}
+
+impl ResultEntry {
+    fn default_count() -> usize {
+        1
+    }
+}
+
+impl RecipeEntry {
+    fn into_recipe(self) -> Result<Recipe, String> {
+        let name = self.name;
+        let block_id = |block: &str| {
+            BlockType::from_name(block)
+                .map(|block| BlockState::from(block).id())
+                .ok_or_else(|| format!("recipe {}: unknown block {}", name, block))
+        };
+
+        let shape = match (self.pattern, self.shapeless) {
+            (Some(rows), None) => {
+                let width = rows
+                    .iter()
+                    .map(|row| row.chars().count())
+                    .max()
+                    .unwrap_or(0);
+                if width > CraftingGrid::SIZE || rows.len() > CraftingGrid::SIZE {
+                    return Err(format!(
+                        "recipe {}: pattern is bigger than {}x{}",
+                        name,
+                        CraftingGrid::SIZE,
+                        CraftingGrid::SIZE
+                    ));
+                }
+                let mut pattern = CraftingGrid::new(width, rows.len());
+                for (y, row) in rows.iter().enumerate() {
+                    for (x, key) in row.chars().enumerate() {
+                        if key == ' ' {
+                            continue;
+                        }
+                        let block = self
+                            .key
+                            .get(&key)
+                            .ok_or_else(|| format!("recipe {}: unknown key '{}'", name, key))?;
+                        pattern.set(x, y, Some(block_id(block)?));
+                    }
+                }
+                let pattern = pattern.trimmed();
+                if pattern.cells.is_empty() {
+                    return Err(format!("recipe {}: pattern is empty", name));
+                }
+                Shape::Shaped(pattern)
+            }
+            (None, Some(blocks)) => {
+                if blocks.is_empty() || blocks.len() > CraftingGrid::SIZE * CraftingGrid::SIZE {
+                    return Err(format!(
+                        "recipe {}: wrong number of ingredients {}",
+                        name,
+                        blocks.len()
+                    ));
+                }
+                let mut ids = blocks
+                    .iter()
+                    .map(|block| block_id(block))
+                    .collect::<Result<Vec<u16>, String>>()?;
+                ids.sort_unstable();
+                Shape::Shapeless(ids)
+            }
+            _ => {
+                return Err(format!(
+                    "recipe {}: exactly one of pattern and shapeless is needed",
+                    name
+                ))
+            }
+        };
+
+        let result = match self.result {
+            ResultEntry {
+                block: Some(block),
+                tool: None,
+                weapon: None,
+                tier: None,
+                count,
+            } if count > 0 => {
+                Item::from_block(BlockState::new(block_id(&block)?), Count::Finite(count))
+            }
+            ResultEntry {
+                block: None,
+                tool: Some(kind),
+                weapon: None,
+                tier: Some(tier),
+                count: 1,
+            } => Item::tool(kind, tier),
+            ResultEntry {
+                block: None,
+                tool: None,
+                weapon: Some(kind),
+                tier: Some(tier),
+                count: 1,
+            } => Item::weapon(kind, tier),
+            _ => {
+                return Err(format!(
+                    "recipe {}: result must be some blocks, or one tool or weapon with a tier",
+                    name
+                ))
+            }
+        };
+        Ok(Recipe {
+            name,
+            station: self.station,
+            shape,
+            result,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(block: BlockType) -> Option<u16> {
+        Some(BlockState::from(block).id())
+    }
+
+    const BOOK: &str = r#"
+        [[recipe]]
+        name = "planks"
+        shapeless = ["LOG"]
+        result = { block = "PLANKS", count = 4 }
+
+        [[recipe]]
+        name = "stairs"
+        station = "workbench"
+        pattern = ["P  ", "PP ", "PPP"]
+        key = { P = "PLANKS" }
+        result = { block = "STAIRS", count = 4 }
+
+        [[recipe]]
+        name = "workbench"
+        pattern = ["PP", "PP"]
+        key = { P = "PLANKS" }
+        result = { block = "WORKBENCH" }
+    "#;
+
+    #[test]
+    fn shaped_recipe_matches_anywhere_in_grid() {
+        let book = RecipeBook::parse(BOOK).unwrap();
+        let mut grid = CraftingGrid::new(3, 3);
+        for (x, y) in [(1, 1), (2, 1), (1, 2), (2, 2)] {
+            grid.set(x, y, id(BlockType::PLANKS));
+        }
+
+        let recipe = book.find(&grid, &[]).unwrap();
+        assert_eq!(recipe.name(), "workbench");
+        assert_eq!(
+            recipe.ingredients(),
+            vec![(id(BlockType::PLANKS).unwrap(), 4)]
+        );
+        assert_eq!(
+            recipe.result(),
+            Item::from_block(BlockState::from(BlockType::WORKBENCH), Count::Finite(1))
+        );
+
+        grid.set(0, 0, id(BlockType::LOG));
+        assert!(book.find(&grid, &[]).is_none());
+    }
+
+    #[test]
+    fn shapeless_recipe_ignores_placement() {
+        let book = RecipeBook::parse(BOOK).unwrap();
+        let mut grid = CraftingGrid::new(3, 3);
+        grid.set(2, 1, id(BlockType::LOG));
+        assert_eq!(book.find(&grid, &[]).unwrap().name(), "planks");
+    }
+
+    #[test]
+    fn station_recipes_need_the_station_near() {
+        let book = RecipeBook::parse(BOOK).unwrap();
+        let mut grid = CraftingGrid::new(3, 3);
+        for (x, y) in [(0, 0), (0, 1), (1, 1), (0, 2), (1, 2), (2, 2)] {
+            grid.set(x, y, id(BlockType::PLANKS));
+        }
+
+        assert!(book.find(&grid, &[]).is_none());
+        assert_eq!(
+            book.find(&grid, &[Station::Workbench]).unwrap().name(),
+            "stairs"
+        );
+        assert_eq!(book.available(&[]).count(), 2);
+    }
+
+    #[test]
+    fn wrong_recipes_are_rejected() {
+        let unknown_block = r#"
+            [[recipe]]
+            name = "gold"
+            shapeless = ["GOLD"]
+            result = { block = "STONE" }
+        "#;
+        let unknown_key = r#"
+            [[recipe]]
+            name = "glass"
+            pattern = ["X"]
+            result = { block = "GLASS" }
+        "#;
+        let both_shapes = r#"
+            [[recipe]]
+            name = "glass"
+            pattern = ["D"]
+            key = { D = "DIRT" }
+            shapeless = ["DIRT"]
+            result = { block = "GLASS" }
+        "#;
+        let untiered_tool = r#"
+            [[recipe]]
+            name = "pickaxe"
+            shapeless = ["LOG"]
+            result = { tool = "PICKAXE" }
+        "#;
+        for source in [unknown_block, unknown_key, both_shapes, untiered_tool] {
+            assert!(RecipeBook::parse(source).is_err());
+        }
+    }
+
+    #[test]
+    fn game_recipes_are_valid() {
+        let book = RecipeBook::load(RECIPES_FILE).unwrap();
+        assert!(book.get("workbench").is_some());
+    }
+}
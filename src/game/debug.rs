@@ -0,0 +1,189 @@
+use nalgebra_glm::{vec4, Vec4};
+
+use super::player::Player;
+use super::storage::STORAGE;
+use super::world::{chunk::Chunk, World};
+use crate::render::hud::{BitmapFont, Hud, Rect};
+
+const TEXT_SIZE: f32 = 14.0;
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+const BACKGROUND_COLOR: Vec4 = Vec4::new(0.0, 0.0, 0.0, 0.45);
+
+/// Average frame rate over short periods, so the numbers don't flicker every frame
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FrameStats {
+    elapsed: f32,
+    frames: usize,
+    fps: f32,
+    frame_time: f32,
+}
+
+impl FrameStats {
+    /// How often the averages are updated in seconds
+    const PERIOD: f32 = 0.5;
+
+    pub fn new() -> Self {
+        Self {
+            elapsed: 0.0,
+            frames: 0,
+            fps: 0.0,
+            frame_time: 0.0,
+        }
+    }
+
+    pub fn add_frame(&mut self, delta_time: f32) {
+        self.elapsed += delta_time;
+        self.frames += 1;
+        if self.elapsed >= Self::PERIOD {
+            self.fps = self.frames as f32 / self.elapsed;
+            self.frame_time = self.elapsed / self.frames as f32;
+            self.elapsed = 0.0;
+            self.frames = 0;
+        }
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// Average time of a frame in seconds
+    pub fn frame_time(&self) -> f32 {
+        self.frame_time
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Screen with technical details about the game in the top left corner
+pub struct DebugOverlay {
+    visible: bool,
+    stats: FrameStats,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            stats: FrameStats::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.stats.add_frame(delta_time);
+    }
+
+    fn text(&self, world: &World, player: &Player) -> String {
+        let position = player.position();
+        let block_position = position / world.blocksize();
+        let chunk = (
+            (block_position.x / Chunk::WIDTH as f32).floor() as isize,
+            (block_position.z / Chunk::WIDTH as f32).floor() as isize,
+        );
+        let (chunks, meshes) = {
+            let storage = STORAGE.lock();
+            (storage.all_chunks().len(), storage.all_mesh().len())
+        };
+
+        let mut lines = vec![
+            format!(
+                "{:.0} fps ({:.2} ms)",
+                self.stats.fps(),
+                self.stats.frame_time() * 1000.0
+            ),
+            format!(
+                "XYZ: {:.2} / {:.2} / {:.2}",
+                position.x, position.y, position.z
+            ),
+            format!(
+                "Block: {} {} {}",
+                block_position.x.floor(),
+                block_position.y.floor(),
+                block_position.z.floor()
+            ),
+            format!("Chunk: {} {}", chunk.0, chunk.1),
+            format!("Facing: {:?}", player.horizontal_facing()),
+        ];
+
+        match world.target_block() {
+            Some(block) => {
+                let info = block.info();
+                lines.push(format!(
+                    "Target: {:?} (id {}, metadata {:#x})",
+                    info.name(),
+                    block.id(),
+                    block.metadata()
+                ));
+                for property in info.properties() {
+                    if let Some(value) = block.get(*property) {
+                        lines.push(format!("  {:?}: {}", property, value));
+                    }
+                }
+            }
+            None => lines.push(String::from("Target: none")),
+        }
+
+        lines.push(format!("Chunks: {} loaded, {} meshed", chunks, meshes));
+        lines.push(format!("Pending jobs: {}", world.pending_jobs()));
+        lines.push(format!("Seed: {}", world.seed()));
+        lines.join("\n")
+    }
+
+    pub fn render(&self, hud: &mut Hud, world: &World, player: &Player) {
+        if !self.visible {
+            return;
+        }
+        let text = self.text(world, player);
+        let (width, height) = BitmapFont::measure(&text, TEXT_SIZE);
+        hud.fill_rect(
+            Rect::new(
+                MARGIN,
+                MARGIN,
+                width + 2.0 * PADDING,
+                height + 2.0 * PADDING,
+            ),
+            BACKGROUND_COLOR,
+        );
+        hud.draw_text_with_shadow(
+            MARGIN + PADDING,
+            MARGIN + PADDING,
+            TEXT_SIZE,
+            &text,
+            vec4(1.0, 1.0, 1.0, 1.0),
+        );
+    }
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_stats_average_over_period() {
+        let mut stats = FrameStats::new();
+        for _ in 0..10 {
+            stats.add_frame(0.02);
+        }
+        assert_eq!(stats.fps(), 0.0);
+
+        for _ in 0..20 {
+            stats.add_frame(0.02);
+        }
+        assert!((stats.fps() - 50.0).abs() < 0.01);
+        assert!((stats.frame_time() - 0.02).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,398 @@
+use std::path::Path;
+
+use nalgebra_glm::Vec4;
+
+use super::world::info::{parse_seed, random_seed, WorldInfo, WORLDS_DIRECTORY};
+use crate::render::hud::{BitmapFont, Hud, Rect};
+
+const BUTTON_WIDTH: f32 = 360.0;
+const BUTTON_HEIGHT: f32 = 40.0;
+const BUTTON_GAP: f32 = 10.0;
+const TITLE_SIZE: f32 = 40.0;
+const TEXT_SIZE: f32 = 16.0;
+/// Distance from the top of the screen to the title
+const TITLE_MARGIN: f32 = 0.15;
+const VISIBLE_WORLDS: usize = 5;
+const MAX_FIELD_LENGTH: usize = 32;
+const DEFAULT_WORLD_NAME: &str = "New World";
+
+const BACKGROUND_COLOR: Vec4 = Vec4::new(0.12, 0.14, 0.2, 1.0);
+const DIM_COLOR: Vec4 = Vec4::new(0.0, 0.0, 0.0, 0.5);
+const BUTTON_COLOR: Vec4 = Vec4::new(0.3, 0.3, 0.32, 0.9);
+const HOVERED_COLOR: Vec4 = Vec4::new(0.45, 0.45, 0.5, 0.9);
+const FIELD_COLOR: Vec4 = Vec4::new(0.05, 0.05, 0.05, 0.9);
+const FRAME_COLOR: Vec4 = Vec4::new(0.05, 0.05, 0.05, 0.9);
+const FOCUSED_COLOR: Vec4 = Vec4::new(1.0, 1.0, 1.0, 1.0);
+const TEXT_COLOR: Vec4 = Vec4::new(1.0, 1.0, 1.0, 1.0);
+const HINT_COLOR: Vec4 = Vec4::new(0.7, 0.7, 0.7, 1.0);
+const ERROR_COLOR: Vec4 = Vec4::new(1.0, 0.4, 0.4, 1.0);
+
+/// Screens of the game, the world is loaded only while playing or paused
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameState {
+    MainMenu,
+    WorldList,
+    CreateWorld,
+    Playing,
+    Paused,
+}
+
+/// Text field of the create world screen
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Field {
+    Name,
+    Seed,
+}
+
+/// What a click on a menu button asks the game to do
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MenuAction {
+    OpenWorldList,
+    OpenCreateWorld,
+    /// Index of the world in `Menu::worlds`
+    LoadWorld(usize),
+    CreateWorld,
+    Focus(Field),
+    Back,
+    Resume,
+    /// Saves the world and goes back to the main menu
+    SaveAndExit,
+    /// Saves the world if it's loaded and closes the game
+    Quit,
+}
+
+struct Button {
+    rect: Rect,
+    label: String,
+    action: MenuAction,
+}
+
+/// Main menu, world selection, world creation and pause screens, they are used with the mouse
+pub struct Menu {
+    cursor: (f32, f32),
+    worlds: Vec<WorldInfo>,
+    /// Index of the first world shown in the list
+    scroll: usize,
+    name: String,
+    seed: String,
+    focused: Field,
+    /// Error shown under the buttons, e.g. when a world cannot be created
+    message: Option<String>,
+}
+
+impl Menu {
+    pub fn new() -> Self {
+        Self {
+            cursor: (0.0, 0.0),
+            worlds: vec![],
+            scroll: 0,
+            name: String::new(),
+            seed: String::new(),
+            focused: Field::Name,
+            message: None,
+        }
+    }
+
+    pub fn move_cursor(&mut self, x: f32, y: f32) {
+        self.cursor = (x, y);
+    }
+
+    /// Reads the saved worlds again, so the list shows new and deleted ones
+    pub fn refresh_worlds(&mut self) {
+        self.worlds = WorldInfo::list(Path::new(WORLDS_DIRECTORY));
+        self.scroll = 0;
+        self.message = None;
+    }
+
+    pub fn worlds(&self) -> &[WorldInfo] {
+        &self.worlds
+    }
+
+    /// Scrolling up shows earlier worlds
+    pub fn scroll(&mut self, offset: i32) {
+        let last = self.worlds.len().saturating_sub(VISIBLE_WORLDS);
+        self.scroll = (self.scroll as i32 - offset).clamp(0, last as i32) as usize;
+    }
+
+    /// Empties the fields of the create world screen
+    pub fn reset_form(&mut self) {
+        self.name.clear();
+        self.seed.clear();
+        self.focused = Field::Name;
+        self.message = None;
+    }
+
+    pub fn focus(&mut self, field: Field) {
+        self.focused = field;
+    }
+
+    pub fn focus_next(&mut self) {
+        self.focused = match self.focused {
+            Field::Name => Field::Seed,
+            Field::Seed => Field::Name,
+        };
+    }
+
+    /// Adds typed text to the focused field, control characters are ignored
+    pub fn type_text(&mut self, text: &str) {
+        let field = match self.focused {
+            Field::Name => &mut self.name,
+            Field::Seed => &mut self.seed,
+        };
+        for character in text.chars().filter(|c| !c.is_control()) {
+            if field.chars().count() == MAX_FIELD_LENGTH {
+                break;
+            }
+            field.push(character);
+        }
+    }
+
+    /// Removes the last character of the focused field
+    pub fn erase(&mut self) {
+        match self.focused {
+            Field::Name => self.name.pop(),
+            Field::Seed => self.seed.pop(),
+        };
+    }
+
+    pub fn new_world_name(&self) -> &str {
+        match self.name.trim() {
+            "" => DEFAULT_WORLD_NAME,
+            name => name,
+        }
+    }
+
+    /// Seed from the seed field, an empty field gives a random seed
+    pub fn new_world_seed(&self) -> u32 {
+        if self.seed.trim().is_empty() {
+            random_seed()
+        } else {
+            parse_seed(&self.seed)
+        }
+    }
+
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    /// Action of the button under the cursor
+    pub fn click(&self, state: GameState, screen: (f32, f32)) -> Option<MenuAction> {
+        self.buttons(state, screen)
+            .into_iter()
+            .find(|button| button.rect.contains(self.cursor.0, self.cursor.1))
+            .map(|button| button.action)
+    }
+
+    fn title(state: GameState) -> &'static str {
+        match state {
+            GameState::MainMenu => "Terraria 3D",
+            GameState::WorldList => "Select World",
+            GameState::CreateWorld => "Create World",
+            GameState::Paused => "Game Paused",
+            GameState::Playing => "",
+        }
+    }
+
+    fn button_rect(screen: (f32, f32), row: usize) -> Rect {
+        let top = screen.1 * TITLE_MARGIN + TITLE_SIZE * 2.0;
+        Rect::new(
+            (screen.0 - BUTTON_WIDTH) / 2.0,
+            top + row as f32 * (BUTTON_HEIGHT + BUTTON_GAP),
+            BUTTON_WIDTH,
+            BUTTON_HEIGHT,
+        )
+    }
+
+    fn buttons(&self, state: GameState, screen: (f32, f32)) -> Vec<Button> {
+        let button = |row: usize, label: &str, action: MenuAction| Button {
+            rect: Self::button_rect(screen, row),
+            label: String::from(label),
+            action,
+        };
+        match state {
+            GameState::MainMenu => vec![
+                button(0, "Singleplayer", MenuAction::OpenWorldList),
+                button(1, "Quit", MenuAction::Quit),
+            ],
+            GameState::WorldList => {
+                let mut buttons = self
+                    .worlds
+                    .iter()
+                    .enumerate()
+                    .skip(self.scroll)
+                    .take(VISIBLE_WORLDS)
+                    .enumerate()
+                    .map(|(row, (index, info))| {
+                        button(row, info.name(), MenuAction::LoadWorld(index))
+                    })
+                    .collect::<Vec<Button>>();
+                let row = VISIBLE_WORLDS.min(self.worlds.len());
+                buttons.push(button(row, "Create New World", MenuAction::OpenCreateWorld));
+                buttons.push(button(row + 1, "Back", MenuAction::Back));
+                buttons
+            }
+            // Rows in between are taken by the labels of the fields
+            GameState::CreateWorld => vec![
+                button(1, &self.name, MenuAction::Focus(Field::Name)),
+                button(3, &self.seed, MenuAction::Focus(Field::Seed)),
+                button(4, "Create", MenuAction::CreateWorld),
+                button(5, "Back", MenuAction::Back),
+            ],
+            GameState::Paused => vec![
+                button(0, "Resume", MenuAction::Resume),
+                button(1, "Save and Quit to Title", MenuAction::SaveAndExit),
+                button(2, "Save and Quit Game", MenuAction::Quit),
+            ],
+            GameState::Playing => vec![],
+        }
+    }
+
+    fn draw_centered(
+        hud: &mut Hud,
+        screen: (f32, f32),
+        y: f32,
+        size: f32,
+        text: &str,
+        color: Vec4,
+    ) {
+        let (width, _) = BitmapFont::measure(text, size);
+        hud.draw_text_with_shadow((screen.0 - width) / 2.0, y, size, text, color);
+    }
+
+    pub fn render(&self, hud: &mut Hud, state: GameState) {
+        if state == GameState::Playing {
+            return;
+        }
+        let screen = (hud.width(), hud.height());
+        let background = if state == GameState::Paused {
+            DIM_COLOR
+        } else {
+            BACKGROUND_COLOR
+        };
+        hud.fill_rect(Rect::new(0.0, 0.0, screen.0, screen.1), background);
+        Self::draw_centered(
+            hud,
+            screen,
+            screen.1 * TITLE_MARGIN,
+            TITLE_SIZE,
+            Self::title(state),
+            TEXT_COLOR,
+        );
+
+        for button in self.buttons(state, screen) {
+            let rect = button.rect;
+            let text_y = rect.y + (rect.height - TEXT_SIZE) / 2.0;
+            if let MenuAction::Focus(field) = button.action {
+                hud.fill_rect(rect, FIELD_COLOR);
+                let focused = field == self.focused;
+                let frame = if focused { FOCUSED_COLOR } else { HINT_COLOR };
+                hud.stroke_rect(rect, 2.0, frame);
+                let caret = if focused { "_" } else { "" };
+                hud.draw_text(
+                    rect.x + 10.0,
+                    text_y,
+                    TEXT_SIZE,
+                    &format!("{}{}", button.label, caret),
+                    TEXT_COLOR,
+                );
+                continue;
+            }
+            let hovered = rect.contains(self.cursor.0, self.cursor.1);
+            hud.fill_rect(rect, if hovered { HOVERED_COLOR } else { BUTTON_COLOR });
+            hud.stroke_rect(rect, 2.0, FRAME_COLOR);
+            Self::draw_centered(hud, screen, text_y, TEXT_SIZE, &button.label, TEXT_COLOR);
+        }
+
+        let mut notes = vec![];
+        match state {
+            GameState::WorldList if self.worlds.is_empty() => {
+                notes.push((String::from("No worlds yet"), HINT_COLOR));
+            }
+            GameState::WorldList if self.worlds.len() > VISIBLE_WORLDS => {
+                notes.push((String::from("Scroll to see more worlds"), HINT_COLOR));
+            }
+            GameState::CreateWorld => {
+                for (row, label) in [(0, "World name"), (2, "Seed (empty for random)")] {
+                    let rect = Self::button_rect(screen, row);
+                    hud.draw_text_with_shadow(
+                        rect.x,
+                        rect.y + rect.height - TEXT_SIZE - 4.0,
+                        TEXT_SIZE,
+                        label,
+                        HINT_COLOR,
+                    );
+                }
+            }
+            _ => (),
+        }
+        if let Some(message) = &self.message {
+            notes.push((message.clone(), ERROR_COLOR));
+        }
+
+        let mut y = self
+            .buttons(state, screen)
+            .last()
+            .map(|button| button.rect.y + button.rect.height + BUTTON_GAP * 2.0)
+            .unwrap_or(screen.1 / 2.0);
+        for (text, color) in notes {
+            Self::draw_centered(hud, screen, y, TEXT_SIZE, &text, color);
+            y += TEXT_SIZE * 1.5;
+        }
+    }
+}
+
+impl Default for Menu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: (f32, f32) = (1280.0, 720.0);
+
+    fn click_row(menu: &mut Menu, state: GameState, row: usize) -> Option<MenuAction> {
+        let rect = Menu::button_rect(SCREEN, row);
+        menu.move_cursor(rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+        menu.click(state, SCREEN)
+    }
+
+    #[test]
+    fn buttons_are_found_under_the_cursor() {
+        let mut menu = Menu::new();
+        assert_eq!(
+            click_row(&mut menu, GameState::MainMenu, 0),
+            Some(MenuAction::OpenWorldList)
+        );
+        assert_eq!(
+            click_row(&mut menu, GameState::Paused, 1),
+            Some(MenuAction::SaveAndExit)
+        );
+        assert_eq!(
+            click_row(&mut menu, GameState::WorldList, 1),
+            Some(MenuAction::Back)
+        );
+        menu.move_cursor(0.0, 0.0);
+        assert_eq!(menu.click(GameState::MainMenu, SCREEN), None);
+    }
+
+    #[test]
+    fn typed_text_goes_to_the_focused_field() {
+        let mut menu = Menu::new();
+        menu.reset_form();
+        assert_eq!(menu.new_world_name(), DEFAULT_WORLD_NAME);
+
+        menu.type_text("My world\n");
+        menu.focus_next();
+        menu.type_text("12345");
+        menu.erase();
+        assert_eq!(menu.new_world_name(), "My world");
+        assert_eq!(menu.new_world_seed(), 1234);
+
+        menu.focus(Field::Name);
+        menu.type_text(&"x".repeat(MAX_FIELD_LENGTH * 2));
+        assert_eq!(menu.new_world_name().chars().count(), MAX_FIELD_LENGTH);
+    }
+}
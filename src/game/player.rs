@@ -1,8 +1,16 @@
 use nalgebra_glm::*;
 
+pub mod data;
+use data::PlayerData;
+
 pub mod inventory;
 use inventory::*;
 
+use super::crafting::{Recipe, Station};
+use super::world::block_state::BlockState;
+use crate::render::block::Block;
+use crate::render::mesh::RenderPosition;
+
 struct PlayerMove {
     forward: bool,
     backward: bool,
@@ -12,6 +20,13 @@ struct PlayerMove {
     down: bool,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameMode {
+    Survival,
+    /// Blocks are never used up, break fast and drop nothing
+    Creative,
+}
+
 pub struct Player {
     projection: Mat4,
 
@@ -30,7 +45,10 @@ pub struct Player {
 
     move_direction: PlayerMove,
 
+    game_mode: GameMode,
     inventory: Inventory,
+    /// Inventory of the other game mode
+    stashed_inventory: Option<Inventory>,
     cell_in_hotbar: usize,
 }
 
@@ -84,7 +102,9 @@ impl Player {
                 down: false,
             },
 
+            game_mode: GameMode::Survival,
             inventory: Inventory::new(),
+            stashed_inventory: None,
             cell_in_hotbar: 0,
         }
     }
@@ -98,7 +118,7 @@ impl Player {
     }
 
     pub fn update_vision(&mut self, fovy: f32, near: f32, far: f32) {
-        self.projection = perspective(*crate::window::ASPECT_RATIO.lock(), fovy, near, far);
+        self.projection = perspective(*crate::render::ASPECT_RATIO.lock(), fovy, near, far);
     }
 
     pub fn move_forward(&mut self) {
@@ -149,6 +169,15 @@ impl Player {
         self.move_direction.down = false;
     }
 
+    pub fn stop_moving(&mut self) {
+        self.stop_move_forward();
+        self.stop_move_backward();
+        self.stop_move_left();
+        self.stop_move_right();
+        self.stop_move_up();
+        self.stop_move_down();
+    }
+
     pub fn process_move(&mut self, new_position: Vec3) {
         self.position = new_position;
     }
@@ -185,7 +214,10 @@ impl Player {
             Self::DEFAULT_MAX_UP_ROTATION,
         );
         self.yaw += xoffset * self.sensitivity;
+        self.update_target();
+    }
 
+    fn update_target(&mut self) {
         let pitch_angle = radians(&vec1(self.pitch));
         let yaw_angle = radians(&vec1(self.yaw));
 
@@ -197,10 +229,30 @@ impl Player {
         self.target = normalize(&direction);
     }
 
+    /// Horizontal direction where the player looks, derived from yaw
+    pub fn horizontal_facing(&self) -> RenderPosition {
+        match self.yaw.rem_euclid(360.0) {
+            yaw if (45.0..135.0).contains(&yaw) => RenderPosition::SOUTH,
+            yaw if (135.0..225.0).contains(&yaw) => RenderPosition::WEST,
+            yaw if (225.0..315.0).contains(&yaw) => RenderPosition::NORTH,
+            _ => RenderPosition::EAST,
+        }
+    }
+
     pub fn position(&self) -> Vec3 {
         self.position
     }
 
+    /// Rotation of the camera around the vertical axis in degrees
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    /// Rotation of the camera up or down in degrees
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
     pub fn projection(&self) -> &Mat4 {
         &self.projection
     }
@@ -223,12 +275,94 @@ impl Player {
         hitbox
     }
 
-    pub fn get_block_in_hand(&mut self) -> Option<Item> {
-        self.inventory.get_item_from_hotbar(self.cell_in_hotbar)
+    /// Item in the selected hotbar cell, it stays in the inventory
+    pub fn held_item(&self) -> Option<Item> {
+        self.inventory.hotbar_item(self.cell_in_hotbar).copied()
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    pub fn inventory_mut(&mut self) -> &mut Inventory {
+        &mut self.inventory
+    }
+
+    pub fn selected_hotbar_cell(&self) -> usize {
+        self.cell_in_hotbar
+    }
+
+    /// Uses up one item of the selected hotbar cell, infinite stacks are not changed
+    pub fn consume_block_in_hand(&mut self) {
+        self.inventory.get_item_from_hotbar(self.cell_in_hotbar);
     }
 
-    pub fn pick_block(&mut self, block: u64, total: Count) {
-        self.inventory.pick_item(Item::from_block(block, total));
+    pub fn game_mode(&self) -> GameMode {
+        self.game_mode
+    }
+
+    /// The creative mode has its own inventory, the survival one is restored when it's turned off
+    pub fn set_game_mode(&mut self, game_mode: GameMode) {
+        if self.game_mode == game_mode {
+            return;
+        }
+        self.game_mode = game_mode;
+        let other = self
+            .stashed_inventory
+            .take()
+            .unwrap_or_else(Inventory::creative);
+        self.stashed_inventory = Some(std::mem::replace(&mut self.inventory, other));
+    }
+
+    /// How many times faster the player breaks the block with the held item than by hand
+    pub fn mining_speed(&self, block: &Block) -> f32 {
+        self.held_item()
+            .map_or(1.0, |item| item.mining_speed(block))
+    }
+
+    /// Wears the held tool or weapon after breaking a block, returns it if it's broken
+    pub fn wear_held_item(&mut self) -> Option<Item> {
+        if self.game_mode == GameMode::Creative {
+            return None;
+        }
+        let uses = self.held_item()?.mining_wear();
+        self.inventory.wear_hotbar_item(self.cell_in_hotbar, uses)
+    }
+
+    /// Puts blocks into the inventory, returns what didn't fit into it
+    pub fn pick_block(&mut self, block: BlockState, total: Count) -> Option<Item> {
+        self.inventory.pick_item(Item::from_block(block, total))
+    }
+
+    pub fn craft(&mut self, recipe: &Recipe, near: &[Station]) -> Result<Item, CraftError> {
+        self.inventory.craft(recipe, near)
+    }
+
+    pub fn data(&self) -> PlayerData {
+        PlayerData {
+            position: self.position,
+            yaw: self.yaw,
+            pitch: self.pitch,
+            game_mode: self.game_mode,
+            inventory: self.inventory.clone(),
+            stashed_inventory: self.stashed_inventory.clone(),
+            cell_in_hotbar: self.cell_in_hotbar,
+        }
+    }
+
+    pub fn restore(&mut self, data: PlayerData) {
+        self.position = data.position;
+        self.yaw = data.yaw;
+        self.pitch = data.pitch.clamp(
+            Self::DEFAULT_MAX_DOWN_ROTATION,
+            Self::DEFAULT_MAX_UP_ROTATION,
+        );
+        self.update_target();
+
+        self.game_mode = data.game_mode;
+        self.inventory = data.inventory;
+        self.stashed_inventory = data.stashed_inventory;
+        self.select_hotbar_cell(data.cell_in_hotbar);
     }
 
     pub fn select_hotbar_cell(&mut self, cell_position: usize) {
@@ -0,0 +1,197 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::Path;
+
+use nalgebra_glm::{vec3, Vec3};
+
+use super::inventory::Inventory;
+use super::GameMode;
+
+/// Everything about the player which is saved with the world
+#[derive(Clone, PartialEq, Debug)]
+pub struct PlayerData {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub game_mode: GameMode,
+    pub inventory: Inventory,
+    /// Inventory of the other game mode, see `Player::set_game_mode`
+    pub stashed_inventory: Option<Inventory>,
+    pub cell_in_hotbar: usize,
+}
+
+impl PlayerData {
+    const MAGIC: [u8; 4] = *b"T3DP";
+    const VERSION: u16 = 2;
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        if let Some(directory) = Path::new(path).parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load(path: &str) -> std::io::Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(&Self::MAGIC)?;
+        writer.write_all(&Self::VERSION.to_be_bytes())?;
+
+        for value in [self.position.x, self.position.y, self.position.z] {
+            writer.write_all(&value.to_be_bytes())?;
+        }
+        writer.write_all(&self.yaw.to_be_bytes())?;
+        writer.write_all(&self.pitch.to_be_bytes())?;
+
+        let game_mode = match self.game_mode {
+            GameMode::Survival => 0u8,
+            GameMode::Creative => 1u8,
+        };
+        writer.write_all(&[game_mode, self.cell_in_hotbar as u8])?;
+
+        self.inventory.write_to(writer)?;
+        match &self.stashed_inventory {
+            Some(inventory) => {
+                writer.write_all(&[1])?;
+                inventory.write_to(writer)
+            }
+            None => writer.write_all(&[0]),
+        }
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> std::io::Result<Self> {
+        if read_bytes::<4>(reader)? != Self::MAGIC {
+            return Err(invalid_data("not a player data file"));
+        }
+        let version = u16::from_be_bytes(read_bytes(reader)?);
+        if version != Self::VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported player data version {}",
+                version
+            )));
+        }
+
+        let position = vec3(read_f32(reader)?, read_f32(reader)?, read_f32(reader)?);
+        let yaw = read_f32(reader)?;
+        let pitch = read_f32(reader)?;
+
+        let [game_mode, cell_in_hotbar] = read_bytes(reader)?;
+        let game_mode = match game_mode {
+            0 => GameMode::Survival,
+            1 => GameMode::Creative,
+            _ => return Err(invalid_data("unknown game mode")),
+        };
+
+        let inventory = Inventory::read_from(reader)?;
+        let stashed_inventory = match read_bytes::<1>(reader)? {
+            [0] => None,
+            _ => Some(Inventory::read_from(reader)?),
+        };
+
+        Ok(Self {
+            position,
+            yaw,
+            pitch,
+            game_mode,
+            inventory,
+            stashed_inventory,
+            cell_in_hotbar: cell_in_hotbar as usize,
+        })
+    }
+}
+
+pub(super) fn read_bytes<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_f32(reader: &mut impl Read) -> std::io::Result<f32> {
+    Ok(f32::from_be_bytes(read_bytes(reader)?))
+}
+
+pub(super) fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::player::inventory::{Count, Item, Slot, Tier, ToolKind};
+    use crate::game::world::block_state::BlockState;
+    use crate::render::block::BlockType;
+
+    #[test]
+    fn player_data_survives_round_trip() {
+        let mut inventory = Inventory::new();
+        inventory.put(
+            Slot::Hotbar(2),
+            Item::from_block(BlockState::from(BlockType::LOG), Count::Finite(17)),
+        );
+        inventory.put(Slot::Hotbar(3), Item::tool(ToolKind::SHOVEL, Tier::IRON));
+        inventory.put(
+            Slot::Backpack(5),
+            Item::from_block(BlockState::from(BlockType::LAMP), Count::Finite(1)),
+        );
+        let data = PlayerData {
+            position: vec3(-12.5, 104.0, 3.25),
+            yaw: 135.0,
+            pitch: -20.0,
+            game_mode: GameMode::Creative,
+            inventory: Inventory::creative(),
+            stashed_inventory: Some(inventory),
+            cell_in_hotbar: 2,
+        };
+
+        let mut bytes = vec![];
+        data.write_to(&mut bytes).unwrap();
+        let restored = PlayerData::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(restored, data);
+    }
+
+    #[test]
+    fn empty_and_oversized_stacks_are_rejected() {
+        let log = Item::from_block(BlockState::from(BlockType::LOG), Count::Finite(17));
+        let mut inventory = Inventory::new();
+        inventory.put(Slot::Hotbar(0), log);
+        let data = PlayerData {
+            position: vec3(0.0, 80.0, 0.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            game_mode: GameMode::Survival,
+            inventory,
+            stashed_inventory: None,
+            cell_in_hotbar: 0,
+        };
+        let mut bytes = vec![];
+        data.write_to(&mut bytes).unwrap();
+
+        // Id and type of the log are followed by its count
+        let mut stack = (BlockType::LOG as u16).to_be_bytes().to_vec();
+        stack.extend([0, 0, 0]);
+        stack.extend(17u64.to_be_bytes());
+        let count = bytes
+            .windows(stack.len())
+            .position(|window| window == stack)
+            .unwrap()
+            + stack.len()
+            - 8;
+        for wrong in [0, log.max_stack_size() as u64 + 1] {
+            let mut wrong_bytes = bytes.clone();
+            wrong_bytes[count..count + 8].copy_from_slice(&wrong.to_be_bytes());
+            let error = PlayerData::read_from(&mut wrong_bytes.as_slice()).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn foreign_file_is_rejected() {
+        let bytes = b"not a player at all".to_vec();
+        let error = PlayerData::read_from(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
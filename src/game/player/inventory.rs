@@ -1,85 +1,937 @@
 #![allow(dead_code)]
 
+use std::io::{Read, Write};
+
+use serde::Deserialize;
+
+use super::data::{invalid_data, read_bytes};
+use crate::game::crafting::{Recipe, Station};
+use crate::game::world::block_state::BlockState;
+use crate::render::block::{Block, BLOCK_COUNT};
+
 pub const HOTBAR_SIZE: usize = 10;
+pub const BACKPACK_SIZE: usize = 32;
+#[derive(Clone, PartialEq, Debug)]
 pub struct Inventory {
     hotbar: [Option<Item>; HOTBAR_SIZE],
-    backpack: [Option<Item>; 32],
+    backpack: [Option<Item>; BACKPACK_SIZE],
 }
 
 impl Inventory {
     pub fn new() -> Self {
         Self {
             hotbar: [Option::None; HOTBAR_SIZE],
-            backpack: [Option::None; 32],
+            backpack: [Option::None; BACKPACK_SIZE],
+        }
+    }
+
+    /// Inventory of the creative mode, it has an infinite stack of every block
+    pub fn creative() -> Self {
+        let mut inventory = Self::new();
+        for id in 1..BLOCK_COUNT {
+            inventory.pick_item(Item::from_block(
+                BlockState::new(id as u16),
+                Count::Infinite,
+            ));
         }
+        inventory
     }
 
-    pub fn pick_item(&mut self, item: Item) -> bool {
-        for cell in &mut self.hotbar {
+    /// Puts the item into the inventory. It's merged into stacks of the same item first, the rest
+    /// goes to empty cells of the hotbar and then of the backpack. Returns what didn't fit.
+    pub fn pick_item(&mut self, item: Item) -> Option<Item> {
+        let mut rest = Some(item);
+        for slot in Self::slots() {
+            let item = rest?;
+            if let Some(current) = self.cell_mut(slot) {
+                if current.stacks_with(&item) {
+                    rest = current.merge(item);
+                }
+            }
+        }
+        for slot in Self::slots() {
+            let item = rest?;
+            let cell = self.cell_mut(slot);
             if cell.is_none() {
-                *cell = Some(item);
-                return true;
+                let (stack, left) = item.split_stack();
+                *cell = Some(stack);
+                rest = left;
             }
         }
+        rest
+    }
 
-        for cell in &mut self.backpack {
-            if cell.is_none() {
-                *cell = Some(item);
-                return true;
+    /// Whether `pick_item` would take the whole item
+    pub fn has_room_for(&self, item: Item) -> bool {
+        self.clone().pick_item(item).is_none()
+    }
+
+    pub fn item(&self, slot: Slot) -> Option<&Item> {
+        self.cell(slot).as_ref()
+    }
+
+    /// Takes the whole stack out of the slot
+    pub fn take(&mut self, slot: Slot) -> Option<Item> {
+        self.cell_mut(slot).take()
+    }
+
+    /// Takes the bigger half of the stack out of the slot. Infinite stacks give a full stack and
+    /// stay as they are.
+    pub fn take_half(&mut self, slot: Slot) -> Option<Item> {
+        let cell = self.cell_mut(slot);
+        let item = (*cell)?;
+        match item.count {
+            Count::Infinite => Some(item.with_count(Count::Finite(item.max_stack_size()))),
+            Count::Finite(count) => {
+                let taken = count.div_ceil(2);
+                *cell = (count > taken).then(|| item.with_count(Count::Finite(count - taken)));
+                Some(item.with_count(Count::Finite(taken)))
+            }
+        }
+    }
+
+    /// Puts the item into the slot. It's merged with the same item or swapped with a different
+    /// one, returns what is left after that.
+    pub fn put(&mut self, slot: Slot, item: Item) -> Option<Item> {
+        let cell = self.cell_mut(slot);
+        match cell {
+            None => {
+                let (stack, left) = item.split_stack();
+                *cell = Some(stack);
+                left
             }
+            Some(current) if current.stacks_with(&item) => current.merge(item),
+            Some(current) => Some(std::mem::replace(current, item)),
+        }
+    }
+
+    /// Moves the stack to another slot, merges it with the same item there or swaps them
+    pub fn move_item(&mut self, from: Slot, to: Slot) {
+        if from == to {
+            return;
+        }
+        if let Some(item) = self.take(from) {
+            *self.cell_mut(from) = self.put(to, item);
+        }
+    }
+
+    pub fn swap(&mut self, first: Slot, second: Slot) {
+        let first_item = self.take(first);
+        let second_item = self.take(second);
+        *self.cell_mut(first) = second_item;
+        *self.cell_mut(second) = first_item;
+    }
+
+    /// Moves the bigger half of the stack to the empty slot, returns false if it's not possible
+    pub fn split(&mut self, from: Slot, to: Slot) -> bool {
+        if from == to || self.cell(to).is_some() {
+            return false;
+        }
+        match self.take_half(from) {
+            Some(half) => {
+                *self.cell_mut(to) = Some(half);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// How many blocks with the id the inventory has
+    pub fn total(&self, id: u16) -> Count {
+        let mut total = 0;
+        for slot in Self::slots() {
+            match self.cell(slot) {
+                Some(item) if item.type_ == ItemType::BLOCK && item.id == id => match item.count {
+                    Count::Infinite => return Count::Infinite,
+                    Count::Finite(count) => total += count,
+                },
+                _ => (),
+            }
+        }
+        Count::Finite(total)
+    }
+
+    pub fn has_ingredients(&self, recipe: &Recipe) -> bool {
+        recipe
+            .ingredients()
+            .into_iter()
+            .all(|(id, needed)| match self.total(id) {
+                Count::Infinite => true,
+                Count::Finite(count) => count >= needed,
+            })
+    }
+
+    /// Uses up ingredients of the recipe and puts its result into the inventory. Nothing changes
+    /// if the recipe cannot be crafted.
+    pub fn craft(&mut self, recipe: &Recipe, near: &[Station]) -> Result<Item, CraftError> {
+        if !recipe.station().is_unlocked(near) {
+            return Err(CraftError::MissingStation(recipe.station()));
+        }
+        if !self.has_ingredients(recipe) {
+            return Err(CraftError::MissingIngredients);
+        }
+
+        let mut crafted = self.clone();
+        for (id, count) in recipe.ingredients() {
+            crafted.remove_blocks(id, count);
+        }
+        let result = recipe.result();
+        if crafted.pick_item(result).is_some() {
+            return Err(CraftError::NoRoom);
+        }
+        *self = crafted;
+        Ok(result)
+    }
+
+    /// Removes blocks starting from the end of the backpack, so the hotbar is used last
+    fn remove_blocks(&mut self, id: u16, mut count: usize) {
+        let slots = Self::slots().collect::<Vec<Slot>>();
+        for slot in slots.into_iter().rev() {
+            if count == 0 {
+                return;
+            }
+            let cell = self.cell_mut(slot);
+            let Some(item) = *cell else {
+                continue;
+            };
+            if item.type_ != ItemType::BLOCK || item.id != id {
+                continue;
+            }
+            match item.count {
+                Count::Infinite => return,
+                Count::Finite(remains) if remains <= count => {
+                    *cell = None;
+                    count -= remains;
+                }
+                Count::Finite(remains) => {
+                    *cell = Some(item.with_count(Count::Finite(remains - count)));
+                    count = 0;
+                }
+            }
+        }
+    }
+
+    /// Writes every cell, the hotbar first
+    pub fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        for slot in Self::slots() {
+            match self.cell(slot) {
+                Some(item) => {
+                    writer.write_all(&[1])?;
+                    item.write_to(writer)?;
+                }
+                None => writer.write_all(&[0])?,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> std::io::Result<Self> {
+        let mut inventory = Self::new();
+        for slot in Self::slots() {
+            if read_bytes::<1>(reader)? != [0] {
+                *inventory.cell_mut(slot) = Some(Item::read_from(reader)?);
+            }
+        }
+        Ok(inventory)
+    }
+
+    /// All slots in order of filling: the hotbar first, then the backpack
+    fn slots() -> impl Iterator<Item = Slot> {
+        (0..HOTBAR_SIZE)
+            .map(Slot::Hotbar)
+            .chain((0..BACKPACK_SIZE).map(Slot::Backpack))
+    }
+
+    fn cell(&self, slot: Slot) -> &Option<Item> {
+        match slot {
+            Slot::Hotbar(position) => &self.hotbar[position],
+            Slot::Backpack(position) => &self.backpack[position],
         }
-        false
     }
 
+    fn cell_mut(&mut self, slot: Slot) -> &mut Option<Item> {
+        match slot {
+            Slot::Hotbar(position) => &mut self.hotbar[position],
+            Slot::Backpack(position) => &mut self.backpack[position],
+        }
+    }
+
+    /// Item in the hotbar cell, the item stays in the inventory
+    pub fn hotbar_item(&self, position: usize) -> Option<&Item> {
+        assert!(position < self.hotbar.len());
+        self.hotbar[position].as_ref()
+    }
+
+    /// Wears the tool or the weapon in the hotbar cell, returns it if it's broken
+    pub fn wear_hotbar_item(&mut self, position: usize, uses: u16) -> Option<Item> {
+        assert!(position < self.hotbar.len());
+        let cell = &mut self.hotbar[position];
+        let item = (*cell)?;
+        *cell = item.wear(uses);
+        cell.is_none().then_some(item)
+    }
+
+    /// Takes one item from the hotbar cell, the cell is emptied when its last item is taken
     pub fn get_item_from_hotbar(&mut self, position: usize) -> Option<Item> {
         assert!(position < self.hotbar.len());
-        match &mut self.hotbar[position] {
-            Some(item) => {
-                // match &mut item.count {
-                //     Count::Infinite => (),
-                //     Count::Finite(remains) => {
-                //         *remains -= 1;
-                //         if *remains == 0 {
-                //             self.hotbar[position] = None;
-                //         }
-                //     }
-                // }
-                Some(*item)
-            }
-            None => None,
+        let cell = &mut self.hotbar[position];
+        let item = (*cell)?;
+        match item.count {
+            Count::Infinite => (),
+            Count::Finite(remains) if remains <= 1 => *cell = None,
+            Count::Finite(remains) => *cell = Some(item.with_count(Count::Finite(remains - 1))),
+        }
+        Some(item.with_count(Count::Finite(1)))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CraftError {
+    /// The recipe needs a station which isn't near the player
+    MissingStation(Station),
+    MissingIngredients,
+    /// The result doesn't fit into the inventory
+    NoRoom,
+}
+
+impl std::fmt::Display for CraftError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CraftError::MissingStation(station) => write!(f, "{} is not near", station),
+            CraftError::MissingIngredients => write!(f, "not enough ingredients"),
+            CraftError::NoRoom => write!(f, "inventory is full"),
         }
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Slot {
+    Hotbar(usize),
+    Backpack(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Item {
-    id: u64,
+    id: u16,
     type_: ItemType,
     count: Count,
+    /// Uses which are left until a tool or a weapon breaks
+    durability: Option<u16>,
 }
 
 impl Item {
-    pub fn from_block(block: u64, total: Count) -> Self {
+    const MAX_STACK_SIZE: usize = 64;
+    const HAND_DAMAGE: f32 = 1.0;
+
+    pub fn from_block(block: BlockState, total: Count) -> Self {
         Item {
-            id: block,
+            id: block.id(),
             type_: ItemType::BLOCK,
             count: total,
+            durability: None,
         }
     }
 
-    pub fn id(&self) -> u64 {
+    pub fn tool(kind: ToolKind, tier: Tier) -> Self {
+        Item {
+            id: 0,
+            type_: ItemType::TOOL(kind, tier),
+            count: Count::Finite(1),
+            durability: Some(tier.durability()),
+        }
+    }
+
+    pub fn weapon(kind: WeaponKind, tier: Tier) -> Self {
+        Item {
+            id: 0,
+            type_: ItemType::WEAPON(kind, tier),
+            count: Count::Finite(1),
+            durability: Some(tier.durability()),
+        }
+    }
+
+    pub fn id(&self) -> u16 {
         self.id
     }
+
+    pub fn item_type(&self) -> ItemType {
+        self.type_
+    }
+
+    pub fn count(&self) -> Count {
+        self.count
+    }
+
+    pub fn durability(&self) -> Option<u16> {
+        self.durability
+    }
+
+    pub fn with_count(&self, count: Count) -> Self {
+        Item { count, ..*self }
+    }
+
+    /// Block which is placed with the item, tools and weapons can't be placed
+    pub fn block(&self) -> Option<BlockState> {
+        match self.type_ {
+            ItemType::BLOCK | ItemType::DECORATION => Some(BlockState::new(self.id)),
+            ItemType::TOOL(..) | ItemType::WEAPON(..) => None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self.type_ {
+            ItemType::BLOCK | ItemType::DECORATION => {
+                format!("{:?}", BlockState::new(self.id).info().name())
+            }
+            ItemType::TOOL(kind, tier) => format!("{:?} {:?}", tier, kind),
+            ItemType::WEAPON(kind, tier) => format!("{:?} {:?}", tier, kind),
+        }
+    }
+
+    pub fn max_stack_size(&self) -> usize {
+        match self.type_ {
+            ItemType::BLOCK | ItemType::DECORATION => Self::MAX_STACK_SIZE,
+            ItemType::TOOL(..) | ItemType::WEAPON(..) => 1,
+        }
+    }
+
+    /// Items can be in the same stack if they differ only in count
+    pub fn stacks_with(&self, other: &Item) -> bool {
+        self.id == other.id
+            && self.type_ == other.type_
+            && self.durability == other.durability
+            && self.max_stack_size() > 1
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        let type_ = match self.type_ {
+            ItemType::BLOCK => [0, 0, 0],
+            ItemType::DECORATION => [1, 0, 0],
+            ItemType::TOOL(kind, tier) => [2, kind as u8, tier as u8],
+            ItemType::WEAPON(kind, tier) => [3, kind as u8, tier as u8],
+        };
+        let count = match self.count {
+            Count::Infinite => u64::MAX,
+            Count::Finite(count) => count as u64,
+        };
+        writer.write_all(&self.id.to_be_bytes())?;
+        writer.write_all(&type_)?;
+        writer.write_all(&count.to_be_bytes())?;
+        writer.write_all(&self.durability.unwrap_or(u16::MAX).to_be_bytes())
+    }
+
+    fn read_from(reader: &mut impl Read) -> std::io::Result<Self> {
+        let id = u16::from_be_bytes(read_bytes(reader)?);
+        let type_ = match read_bytes(reader)? {
+            [0, _, _] => Some(ItemType::BLOCK),
+            [1, _, _] => Some(ItemType::DECORATION),
+            [2, kind, tier] => ToolKind::from_byte(kind)
+                .zip(Tier::from_byte(tier))
+                .map(|(kind, tier)| ItemType::TOOL(kind, tier)),
+            [3, kind, tier] => WeaponKind::from_byte(kind)
+                .zip(Tier::from_byte(tier))
+                .map(|(kind, tier)| ItemType::WEAPON(kind, tier)),
+            _ => None,
+        }
+        .ok_or_else(|| invalid_data("unknown item type"))?;
+        let is_block = matches!(type_, ItemType::BLOCK | ItemType::DECORATION);
+        if is_block && id as usize >= BLOCK_COUNT {
+            return Err(invalid_data("unknown block id"));
+        }
+        let count = match u64::from_be_bytes(read_bytes(reader)?) {
+            u64::MAX => Count::Infinite,
+            count => Count::Finite(count as usize),
+        };
+        let durability = match u16::from_be_bytes(read_bytes(reader)?) {
+            u16::MAX => None,
+            durability => Some(durability),
+        };
+        let item = Self {
+            id,
+            type_,
+            count,
+            durability,
+        };
+        if let Count::Finite(count) = count {
+            if count == 0 || count > item.max_stack_size() {
+                return Err(invalid_data("wrong item count"));
+            }
+        }
+        Ok(item)
+    }
+
+    /// Adds the other item to the stack as much as it fits, returns the rest
+    fn merge(&mut self, other: Item) -> Option<Item> {
+        match (self.count, other.count) {
+            (Count::Infinite, _) => None,
+            (_, Count::Infinite) => {
+                self.count = Count::Infinite;
+                None
+            }
+            (Count::Finite(count), Count::Finite(added)) => {
+                let moved = added.min(self.max_stack_size().saturating_sub(count));
+                self.count = Count::Finite(count + moved);
+                (added > moved).then(|| other.with_count(Count::Finite(added - moved)))
+            }
+        }
+    }
+
+    /// Splits the item into a stack which fits into one cell and the rest
+    fn split_stack(self) -> (Item, Option<Item>) {
+        let max = self.max_stack_size();
+        match self.count {
+            Count::Finite(count) if count > max => (
+                self.with_count(Count::Finite(max)),
+                Some(self.with_count(Count::Finite(count - max))),
+            ),
+            _ => (self, None),
+        }
+    }
+
+    /// Uses the item up, returns `None` when it breaks. Items without durability don't change.
+    fn wear(self, uses: u16) -> Option<Item> {
+        match self.durability {
+            None => Some(self),
+            Some(durability) if durability <= uses => None,
+            Some(durability) => Some(Item {
+                durability: Some(durability - uses),
+                ..self
+            }),
+        }
+    }
+
+    /// How much durability breaking a block takes, weapons aren't made for it and wear faster
+    pub fn mining_wear(&self) -> u16 {
+        match self.type_ {
+            ItemType::BLOCK | ItemType::DECORATION => 0,
+            ItemType::TOOL(..) => 1,
+            ItemType::WEAPON(..) => 2,
+        }
+    }
+
+    /// How many times faster the item breaks the block than a bare hand. Tools are fast only
+    /// with blocks of their category, see `Block::tool`.
+    pub fn mining_speed(&self, block: &Block) -> f32 {
+        match (self.type_, block.tool()) {
+            (ItemType::TOOL(kind, tier), Some(category)) if kind == category => tier.mining_speed(),
+            _ => 1.0,
+        }
+    }
+
+    pub fn attack_damage(&self) -> f32 {
+        match self.type_ {
+            ItemType::WEAPON(_, tier) => tier.attack_damage(),
+            ItemType::TOOL(_, tier) => tier.attack_damage() / 2.0,
+            ItemType::BLOCK | ItemType::DECORATION => Self::HAND_DAMAGE,
+        }
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ItemType {
     BLOCK,
     DECORATION,
+    TOOL(ToolKind, Tier),
+    WEAPON(WeaponKind, Tier),
+}
+
+/// Every breakable block belongs to the category of one tool kind, see `Block::tool`
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ToolKind {
+    PICKAXE,
+    AXE,
+    SHOVEL,
+}
+
+impl ToolKind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        [ToolKind::PICKAXE, ToolKind::AXE, ToolKind::SHOVEL]
+            .get(byte as usize)
+            .copied()
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WeaponKind {
+    SWORD,
+}
+
+impl WeaponKind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        [WeaponKind::SWORD].get(byte as usize).copied()
+    }
 }
 
-#[derive(Clone, Copy)]
+/// Material of tools and weapons, better ones are faster, hit harder and last longer
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tier {
+    WOOD,
+    STONE,
+    IRON,
+}
+
+impl Tier {
+    fn from_byte(byte: u8) -> Option<Self> {
+        [Tier::WOOD, Tier::STONE, Tier::IRON]
+            .get(byte as usize)
+            .copied()
+    }
+
+    pub fn mining_speed(self) -> f32 {
+        match self {
+            Tier::WOOD => 2.0,
+            Tier::STONE => 4.0,
+            Tier::IRON => 6.0,
+        }
+    }
+
+    pub fn durability(self) -> u16 {
+        match self {
+            Tier::WOOD => 60,
+            Tier::STONE => 132,
+            Tier::IRON => 251,
+        }
+    }
+
+    pub fn attack_damage(self) -> f32 {
+        match self {
+            Tier::WOOD => 4.0,
+            Tier::STONE => 5.0,
+            Tier::IRON => 6.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Count {
     Infinite,
     Finite(usize),
 }
+
+impl std::fmt::Display for Count {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Count::Infinite => write!(f, "infinite"),
+            Count::Finite(count) => write!(f, "{}", count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::block::BlockType;
+
+    fn stone(count: Count) -> Item {
+        Item::from_block(BlockState::from(BlockType::STONE), count)
+    }
+
+    fn dirt(count: Count) -> Item {
+        Item::from_block(BlockState::from(BlockType::DIRT), count)
+    }
+
+    #[test]
+    fn taking_from_finite_stack_decrements_it() {
+        let mut inventory = Inventory::new();
+        inventory.pick_item(stone(Count::Finite(3)));
+
+        assert_eq!(
+            inventory.get_item_from_hotbar(0),
+            Some(stone(Count::Finite(1)))
+        );
+        assert_eq!(inventory.hotbar_item(0), Some(&stone(Count::Finite(2))));
+    }
+
+    #[test]
+    fn last_item_empties_the_cell() {
+        let mut inventory = Inventory::new();
+        inventory.pick_item(stone(Count::Finite(1)));
+
+        assert!(inventory.get_item_from_hotbar(0).is_some());
+        assert_eq!(inventory.hotbar_item(0), None);
+        assert_eq!(inventory.get_item_from_hotbar(0), None);
+    }
+
+    #[test]
+    fn infinite_stack_is_never_used_up() {
+        let mut inventory = Inventory::new();
+        inventory.pick_item(stone(Count::Infinite));
+
+        for _ in 0..100 {
+            assert_eq!(
+                inventory.get_item_from_hotbar(0),
+                Some(stone(Count::Finite(1)))
+            );
+        }
+        assert_eq!(inventory.hotbar_item(0), Some(&stone(Count::Infinite)));
+    }
+
+    #[test]
+    fn creative_inventory_has_infinite_stack_of_every_block() {
+        let inventory = Inventory::creative();
+
+        for position in 0..HOTBAR_SIZE {
+            let item = inventory.hotbar_item(position).unwrap();
+            assert_eq!(item.id(), position as u16 + 1);
+            assert_eq!(item.count(), Count::Infinite);
+        }
+    }
+
+    #[test]
+    fn picked_item_merges_into_existing_stack() {
+        let mut inventory = Inventory::new();
+        inventory.pick_item(dirt(Count::Finite(1)));
+        inventory.pick_item(stone(Count::Finite(10)));
+
+        assert_eq!(inventory.pick_item(stone(Count::Finite(5))), None);
+        assert_eq!(
+            inventory.item(Slot::Hotbar(1)),
+            Some(&stone(Count::Finite(15)))
+        );
+        assert_eq!(inventory.item(Slot::Hotbar(2)), None);
+    }
+
+    #[test]
+    fn picked_item_respects_max_stack_size() {
+        let mut inventory = Inventory::new();
+        inventory.pick_item(stone(Count::Finite(60)));
+
+        assert_eq!(inventory.pick_item(stone(Count::Finite(80))), None);
+        assert_eq!(
+            inventory.item(Slot::Hotbar(0)),
+            Some(&stone(Count::Finite(64)))
+        );
+        assert_eq!(
+            inventory.item(Slot::Hotbar(1)),
+            Some(&stone(Count::Finite(64)))
+        );
+        assert_eq!(
+            inventory.item(Slot::Hotbar(2)),
+            Some(&stone(Count::Finite(12)))
+        );
+    }
+
+    #[test]
+    fn picked_item_overflows_into_backpack() {
+        let mut inventory = Inventory::new();
+        for _ in 0..HOTBAR_SIZE {
+            inventory.pick_item(dirt(Count::Finite(64)));
+        }
+
+        assert_eq!(inventory.pick_item(stone(Count::Finite(3))), None);
+        assert_eq!(
+            inventory.item(Slot::Backpack(0)),
+            Some(&stone(Count::Finite(3)))
+        );
+    }
+
+    #[test]
+    fn full_inventory_returns_the_rest() {
+        let mut inventory = Inventory::new();
+        for _ in 0..HOTBAR_SIZE + BACKPACK_SIZE - 1 {
+            inventory.pick_item(dirt(Count::Finite(64)));
+        }
+        inventory.pick_item(stone(Count::Finite(60)));
+
+        assert_eq!(
+            inventory.pick_item(stone(Count::Finite(10))),
+            Some(stone(Count::Finite(6)))
+        );
+        assert_eq!(
+            inventory.pick_item(dirt(Count::Finite(1))),
+            Some(dirt(Count::Finite(1)))
+        );
+    }
+
+    #[test]
+    fn room_is_checked_without_picking_the_item() {
+        let mut inventory = Inventory::new();
+        for _ in 0..HOTBAR_SIZE + BACKPACK_SIZE - 1 {
+            inventory.pick_item(dirt(Count::Finite(64)));
+        }
+        inventory.pick_item(stone(Count::Finite(60)));
+        let full = inventory.clone();
+
+        assert!(inventory.has_room_for(stone(Count::Finite(4))));
+        assert!(!inventory.has_room_for(stone(Count::Finite(5))));
+        assert!(!inventory.has_room_for(dirt(Count::Finite(1))));
+        assert_eq!(inventory, full);
+    }
+
+    #[test]
+    fn moving_into_empty_slot_moves_the_stack() {
+        let mut inventory = Inventory::new();
+        inventory.pick_item(stone(Count::Finite(5)));
+
+        inventory.move_item(Slot::Hotbar(0), Slot::Backpack(3));
+        assert_eq!(inventory.item(Slot::Hotbar(0)), None);
+        assert_eq!(
+            inventory.item(Slot::Backpack(3)),
+            Some(&stone(Count::Finite(5)))
+        );
+    }
+
+    #[test]
+    fn moving_onto_same_item_merges_and_keeps_the_rest() {
+        let mut inventory = Inventory::new();
+        inventory.put(Slot::Hotbar(0), stone(Count::Finite(40)));
+        inventory.put(Slot::Hotbar(1), stone(Count::Finite(30)));
+
+        inventory.move_item(Slot::Hotbar(0), Slot::Hotbar(1));
+        assert_eq!(
+            inventory.item(Slot::Hotbar(0)),
+            Some(&stone(Count::Finite(6)))
+        );
+        assert_eq!(
+            inventory.item(Slot::Hotbar(1)),
+            Some(&stone(Count::Finite(64)))
+        );
+    }
+
+    #[test]
+    fn moving_onto_different_item_swaps_them() {
+        let mut inventory = Inventory::new();
+        inventory.put(Slot::Hotbar(0), stone(Count::Finite(2)));
+        inventory.put(Slot::Backpack(0), dirt(Count::Finite(7)));
+
+        inventory.move_item(Slot::Hotbar(0), Slot::Backpack(0));
+        assert_eq!(
+            inventory.item(Slot::Hotbar(0)),
+            Some(&dirt(Count::Finite(7)))
+        );
+        assert_eq!(
+            inventory.item(Slot::Backpack(0)),
+            Some(&stone(Count::Finite(2)))
+        );
+
+        inventory.swap(Slot::Hotbar(0), Slot::Backpack(0));
+        assert_eq!(
+            inventory.item(Slot::Hotbar(0)),
+            Some(&stone(Count::Finite(2)))
+        );
+        assert_eq!(
+            inventory.item(Slot::Backpack(0)),
+            Some(&dirt(Count::Finite(7)))
+        );
+    }
+
+    #[test]
+    fn split_moves_bigger_half_to_empty_slot() {
+        let mut inventory = Inventory::new();
+        inventory.put(Slot::Hotbar(0), stone(Count::Finite(7)));
+        inventory.put(Slot::Hotbar(2), dirt(Count::Finite(1)));
+
+        assert!(inventory.split(Slot::Hotbar(0), Slot::Hotbar(1)));
+        assert_eq!(
+            inventory.item(Slot::Hotbar(0)),
+            Some(&stone(Count::Finite(3)))
+        );
+        assert_eq!(
+            inventory.item(Slot::Hotbar(1)),
+            Some(&stone(Count::Finite(4)))
+        );
+
+        assert!(!inventory.split(Slot::Hotbar(0), Slot::Hotbar(2)));
+        assert!(!inventory.split(Slot::Hotbar(5), Slot::Hotbar(6)));
+    }
+
+    #[test]
+    fn put_into_occupied_slot_returns_the_rest() {
+        let mut inventory = Inventory::new();
+        inventory.put(Slot::Hotbar(0), stone(Count::Finite(50)));
+
+        assert_eq!(
+            inventory.put(Slot::Hotbar(0), stone(Count::Finite(20))),
+            Some(stone(Count::Finite(6)))
+        );
+        assert_eq!(
+            inventory.put(Slot::Hotbar(0), dirt(Count::Finite(1))),
+            Some(stone(Count::Finite(64)))
+        );
+    }
+
+    #[test]
+    fn crafting_uses_up_ingredients() {
+        let book = crate::game::crafting::RecipeBook::parse(
+            r#"
+            [[recipe]]
+            name = "furnace"
+            station = "workbench"
+            pattern = ["SSS", "S S", "SSS"]
+            key = { S = "STONE" }
+            result = { block = "FURNACE" }
+            "#,
+        )
+        .unwrap();
+        let recipe = book.get("furnace").unwrap();
+        let mut inventory = Inventory::new();
+        inventory.put(Slot::Hotbar(0), stone(Count::Finite(5)));
+        inventory.put(Slot::Backpack(3), stone(Count::Finite(5)));
+
+        assert_eq!(
+            inventory.craft(recipe, &[]),
+            Err(CraftError::MissingStation(Station::Workbench))
+        );
+        let furnace = Item::from_block(BlockState::from(BlockType::FURNACE), Count::Finite(1));
+        assert_eq!(inventory.craft(recipe, &[Station::Workbench]), Ok(furnace));
+        assert_eq!(
+            inventory.item(Slot::Hotbar(0)),
+            Some(&stone(Count::Finite(2)))
+        );
+        assert_eq!(inventory.item(Slot::Backpack(3)), None);
+        assert_eq!(inventory.total(furnace.id()), Count::Finite(1));
+
+        let before = inventory.clone();
+        assert_eq!(
+            inventory.craft(recipe, &[Station::Workbench]),
+            Err(CraftError::MissingIngredients)
+        );
+        assert_eq!(inventory, before);
+    }
+
+    #[test]
+    fn tool_is_fast_only_in_its_category() {
+        let pickaxe = Item::tool(ToolKind::PICKAXE, Tier::STONE);
+        let stone = BlockState::from(BlockType::STONE).info();
+        let log = BlockState::from(BlockType::LOG).info();
+
+        assert_eq!(pickaxe.mining_speed(&stone), Tier::STONE.mining_speed());
+        assert_eq!(pickaxe.mining_speed(&log), 1.0);
+        assert_eq!(pickaxe.block(), None);
+        assert!(!pickaxe.stacks_with(&pickaxe));
+    }
+
+    #[test]
+    fn worn_out_tool_breaks() {
+        let mut inventory = Inventory::new();
+        inventory.pick_item(Item::tool(ToolKind::AXE, Tier::WOOD));
+        inventory.pick_item(Item::weapon(WeaponKind::SWORD, Tier::WOOD));
+
+        assert_eq!(inventory.wear_hotbar_item(0, 1), None);
+        assert_eq!(
+            inventory.hotbar_item(0).unwrap().durability(),
+            Some(Tier::WOOD.durability() - 1)
+        );
+
+        let sword = *inventory.hotbar_item(1).unwrap();
+        assert_eq!(
+            inventory.wear_hotbar_item(1, Tier::WOOD.durability()),
+            Some(sword)
+        );
+        assert_eq!(inventory.hotbar_item(1), None);
+    }
+
+    #[test]
+    fn unknown_block_item_is_rejected() {
+        let mut bytes = vec![];
+        stone(Count::Finite(3)).write_to(&mut bytes).unwrap();
+        assert_eq!(
+            Item::read_from(&mut bytes.as_slice()).unwrap(),
+            stone(Count::Finite(3))
+        );
+
+        bytes[..2].copy_from_slice(&u16::MAX.to_be_bytes());
+        let error = Item::read_from(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
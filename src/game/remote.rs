@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use nalgebra_glm::{vec3, Vec3};
+
+use super::player::Player;
+use crate::net::client::NetClient;
+use crate::net::protocol::Message;
+
+/// Name of the player on a server when none is given
+pub const DEFAULT_NAME: &str = "Player";
+
+/// Another player on the server
+pub struct RemotePlayer {
+    pub name: String,
+    /// Position of the eyes, see `Player::position`. It's unknown until the player moves.
+    pub position: Option<Vec3>,
+}
+
+/// Connection with the server which the game plays on and the other players there
+pub struct Remote {
+    client: NetClient,
+    players: HashMap<u32, RemotePlayer>,
+    /// When the position of the player was sent and what it was
+    last_move: Option<(Instant, Message)>,
+}
+
+impl Remote {
+    /// Moves are sent at most this often, like the ticks of the server
+    const MOVE_INTERVAL: Duration = Duration::from_millis(50);
+
+    pub fn new(client: NetClient) -> Self {
+        Self {
+            client,
+            players: HashMap::new(),
+            last_move: None,
+        }
+    }
+
+    /// Messages of the server which have come since the last call, the other players are
+    /// updated from them
+    pub fn poll(&mut self) -> Vec<Message> {
+        let mut messages = vec![];
+        while let Some(message) = self.client.poll() {
+            match &message {
+                Message::PlayerJoined { player_id, name } => {
+                    println!("{} has joined", name);
+                    let player = RemotePlayer {
+                        name: name.clone(),
+                        position: None,
+                    };
+                    self.players.insert(*player_id, player);
+                }
+                Message::PlayerMove {
+                    player_id,
+                    position,
+                    ..
+                } => {
+                    if let Some(player) = self.players.get_mut(player_id) {
+                        player.position = Some(vec3(position[0], position[1], position[2]));
+                    }
+                }
+                Message::PlayerLeft { player_id } => {
+                    if let Some(player) = self.players.remove(player_id) {
+                        println!("{} has left", player.name);
+                    }
+                }
+                Message::Chat { sender, text } => println!("<{}> {}", sender, text),
+                _ => (),
+            }
+            messages.push(message);
+        }
+        messages
+    }
+
+    pub fn send(&mut self, message: &Message) {
+        if let Err(error) = self.client.send(message) {
+            eprintln!("Message cannot be sent to the server: {}", error);
+        }
+    }
+
+    /// Sends the position of the player if it has changed, at most every `MOVE_INTERVAL`
+    pub fn send_move(&mut self, player: &Player) {
+        let position = player.position();
+        let moved = Message::PlayerMove {
+            player_id: self.client.player_id().unwrap_or(0),
+            position: [position.x, position.y, position.z],
+            yaw: player.yaw(),
+            pitch: player.pitch(),
+        };
+        if let Some((time, last_move)) = &self.last_move {
+            if time.elapsed() < Self::MOVE_INTERVAL || *last_move == moved {
+                return;
+            }
+        }
+        self.send(&moved);
+        self.last_move = Some((Instant::now(), moved));
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &RemotePlayer> {
+        self.players.values()
+    }
+
+    pub fn server_address(&self) -> String {
+        self.client.server_address().to_string()
+    }
+
+    /// Says goodbye to the server
+    pub fn disconnect(self, reason: &str) {
+        self.client.disconnect(reason);
+    }
+}
@@ -9,6 +9,10 @@ use std::sync::Arc;
 
 pub static STORAGE: Lazy<Mutex<Storage>> = Lazy::new(|| Mutex::new(Storage::init()));
 
+/// Taken by tests which load chunks, so they don't clear the chunks of each other
+#[cfg(test)]
+pub static TEST_LOCK: Mutex<()> = Mutex::new(());
+
 pub struct Storage {
     chunk_meshs: HashMap<(isize, isize), Arc<ChunkMesh>>,
     chunks: HashMap<(isize, isize), Arc<Mutex<Chunk>>>,
@@ -50,6 +54,12 @@ impl Storage {
     pub fn all_mesh(&self) -> &HashMap<(isize, isize), Arc<ChunkMesh>> {
         &self.chunk_meshs
     }
+
+    /// Removes all chunks and their meshes
+    pub fn clear(&mut self) {
+        self.chunk_meshs.clear();
+        self.chunks.clear();
+    }
 }
 
 #[macro_export]
@@ -0,0 +1,421 @@
+use nalgebra_glm::{vec4, Vec4};
+
+use super::player::inventory::{
+    Count, Inventory, Item, ItemType, Slot, ToolKind, WeaponKind, BACKPACK_SIZE, HOTBAR_SIZE,
+};
+use crate::render::hud::{BitmapFont, Hud, HudTexture, Rect};
+
+/// Atlas tile of the first tool icon, icons go by kinds and then by tiers
+const TOOL_ICONS: usize = 32;
+const TIERS_COUNT: usize = 3;
+
+const CELL_SIZE: f32 = 40.0;
+const CELL_GAP: f32 = 4.0;
+const ICON_MARGIN: f32 = 6.0;
+const COUNT_SIZE: f32 = 12.0;
+const NOTICE_SIZE: f32 = 16.0;
+/// Seconds for which a notice stays above the hotbar
+const NOTICE_TIME: f32 = 3.0;
+/// Distance from the bottom of the screen to the hotbar
+const HOTBAR_MARGIN: f32 = 12.0;
+const BACKPACK_COLUMNS: usize = 8;
+const PANEL_PADDING: f32 = 16.0;
+
+const CELL_COLOR: Vec4 = Vec4::new(0.15, 0.15, 0.15, 0.7);
+const FRAME_COLOR: Vec4 = Vec4::new(0.05, 0.05, 0.05, 0.9);
+const SELECTED_COLOR: Vec4 = Vec4::new(1.0, 1.0, 1.0, 1.0);
+const HOVERED_COLOR: Vec4 = Vec4::new(0.35, 0.35, 0.35, 0.8);
+const PANEL_COLOR: Vec4 = Vec4::new(0.3, 0.3, 0.32, 0.9);
+const DIM_COLOR: Vec4 = Vec4::new(0.0, 0.0, 0.0, 0.4);
+const TEXT_COLOR: Vec4 = Vec4::new(1.0, 1.0, 1.0, 1.0);
+
+/// Stack which is carried by the mouse, it goes back to its slot if it's dropped nowhere
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Dragged {
+    item: Item,
+    from: Slot,
+}
+
+/// The hotbar at the bottom of the screen and the inventory screen with all slots. Items are
+/// moved between slots by dragging them with the mouse while the screen is open.
+pub struct InventoryUi {
+    open: bool,
+    cursor: (f32, f32),
+    dragged: Option<Dragged>,
+    /// Message above the hotbar and seconds left to show it
+    notice: Option<(String, f32)>,
+}
+
+impl InventoryUi {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            cursor: (0.0, 0.0),
+            dragged: None,
+            notice: None,
+        }
+    }
+
+    /// Shows the message above the hotbar for a few seconds
+    pub fn show_notice(&mut self, text: String) {
+        self.notice = Some((text, NOTICE_TIME));
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        if let Some((_, time_left)) = &mut self.notice {
+            *time_left -= delta_time;
+            if *time_left <= 0.0 {
+                self.notice = None;
+            }
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Opens or closes the inventory screen, the dragged stack goes back when it's closed. The
+    /// screen stays open while the stack fits nowhere.
+    pub fn toggle(&mut self, inventory: &mut Inventory) {
+        if self.open {
+            self.cancel_drag(inventory);
+            if self.dragged.is_some() {
+                return;
+            }
+        }
+        self.open = !self.open;
+    }
+
+    pub fn move_cursor(&mut self, x: f32, y: f32) {
+        self.cursor = (x, y);
+    }
+
+    /// Starts dragging the stack under the cursor, `half` takes only the bigger half of it
+    pub fn start_drag(&mut self, inventory: &mut Inventory, screen: (f32, f32), half: bool) {
+        if !self.open || self.dragged.is_some() {
+            return;
+        }
+        let Some(from) = Self::slot_at(screen, self.cursor) else {
+            return;
+        };
+        let item = if half {
+            inventory.take_half(from)
+        } else {
+            inventory.take(from)
+        };
+        self.dragged = item.map(|item| Dragged { item, from });
+    }
+
+    /// Drops the dragged stack into the slot under the cursor. What doesn't fit there, or the
+    /// stack which was in the slot, goes to the slot where dragging started. A part of a stack
+    /// isn't swapped with another item, it stays on the cursor.
+    pub fn drop_dragged(&mut self, inventory: &mut Inventory, screen: (f32, f32)) {
+        let Some(dragged) = self.dragged.take() else {
+            return;
+        };
+        let Dragged { item, from } = dragged;
+        let Some(to) = Self::slot_at(screen, self.cursor) else {
+            self.dragged = Self::put_back(inventory, from, item).map(|item| Dragged { item, from });
+            return;
+        };
+        let swaps = matches!(inventory.item(to), Some(current) if !current.stacks_with(&item));
+        if swaps && inventory.item(from).is_some() {
+            self.dragged = Some(dragged);
+            return;
+        }
+        if let Some(rest) = inventory.put(to, item) {
+            self.dragged = Self::put_back(inventory, from, rest).map(|item| Dragged { item, from });
+        }
+    }
+
+    fn cancel_drag(&mut self, inventory: &mut Inventory) {
+        if let Some(Dragged { item, from }) = self.dragged.take() {
+            self.dragged = Self::put_back(inventory, from, item).map(|item| Dragged { item, from });
+        }
+    }
+
+    /// Puts the stack into the slot where dragging started or anywhere else if another item is
+    /// there, returns what fits nowhere
+    fn put_back(inventory: &mut Inventory, slot: Slot, item: Item) -> Option<Item> {
+        let rest = match inventory.item(slot) {
+            Some(current) if !current.stacks_with(&item) => Some(item),
+            _ => inventory.put(slot, item),
+        };
+        rest.and_then(|rest| inventory.pick_item(rest))
+    }
+
+    fn hotbar_rect(screen: (f32, f32)) -> Rect {
+        let width = HOTBAR_SIZE as f32 * (CELL_SIZE + CELL_GAP) - CELL_GAP;
+        Rect::new(
+            (screen.0 - width) / 2.0,
+            screen.1 - HOTBAR_MARGIN - CELL_SIZE,
+            width,
+            CELL_SIZE,
+        )
+    }
+
+    /// Panel of the inventory screen in the middle of the screen: backpack rows and the hotbar
+    /// row below them
+    fn panel_rect(screen: (f32, f32)) -> Rect {
+        let rows = BACKPACK_SIZE.div_ceil(BACKPACK_COLUMNS) + 1;
+        let width = HOTBAR_SIZE as f32 * (CELL_SIZE + CELL_GAP) - CELL_GAP + 2.0 * PANEL_PADDING;
+        let height = rows as f32 * (CELL_SIZE + CELL_GAP) + CELL_GAP + 2.0 * PANEL_PADDING;
+        Rect::new(
+            (screen.0 - width) / 2.0,
+            (screen.1 - height) / 2.0,
+            width,
+            height,
+        )
+    }
+
+    /// Cell of the slot on the inventory screen
+    fn slot_rect(screen: (f32, f32), slot: Slot) -> Rect {
+        let panel = Self::panel_rect(screen).inset(PANEL_PADDING);
+        let step = CELL_SIZE + CELL_GAP;
+        match slot {
+            Slot::Backpack(position) => {
+                let backpack_width = BACKPACK_COLUMNS as f32 * step - CELL_GAP;
+                let left = panel.x + (panel.width - backpack_width) / 2.0;
+                let (row, column) = (position / BACKPACK_COLUMNS, position % BACKPACK_COLUMNS);
+                Rect::new(
+                    left + column as f32 * step,
+                    panel.y + row as f32 * step,
+                    CELL_SIZE,
+                    CELL_SIZE,
+                )
+            }
+            Slot::Hotbar(position) => {
+                let rows = BACKPACK_SIZE.div_ceil(BACKPACK_COLUMNS);
+                Rect::new(
+                    panel.x + position as f32 * step,
+                    panel.y + rows as f32 * step + CELL_GAP,
+                    CELL_SIZE,
+                    CELL_SIZE,
+                )
+            }
+        }
+    }
+
+    fn slots() -> impl Iterator<Item = Slot> {
+        (0..HOTBAR_SIZE)
+            .map(Slot::Hotbar)
+            .chain((0..BACKPACK_SIZE).map(Slot::Backpack))
+    }
+
+    /// Slot of the inventory screen under the point
+    fn slot_at(screen: (f32, f32), point: (f32, f32)) -> Option<Slot> {
+        Self::slots().find(|&slot| Self::slot_rect(screen, slot).contains(point.0, point.1))
+    }
+
+    pub fn render(&self, hud: &mut Hud, inventory: &Inventory, selected: usize) {
+        let screen = (hud.width(), hud.height());
+        let hotbar = Self::hotbar_rect(screen);
+        for position in 0..HOTBAR_SIZE {
+            let cell = Rect::new(
+                hotbar.x + position as f32 * (CELL_SIZE + CELL_GAP),
+                hotbar.y,
+                CELL_SIZE,
+                CELL_SIZE,
+            );
+            Self::draw_cell(hud, cell, inventory.hotbar_item(position), CELL_COLOR);
+            if position == selected {
+                hud.stroke_rect(cell.inset(-2.0), 3.0, SELECTED_COLOR);
+            }
+        }
+        if let Some((text, _)) = &self.notice {
+            let (width, _) = BitmapFont::measure(text, NOTICE_SIZE);
+            hud.draw_text_with_shadow(
+                (screen.0 - width) / 2.0,
+                hotbar.y - CELL_GAP - 2.0 * NOTICE_SIZE,
+                NOTICE_SIZE,
+                text,
+                TEXT_COLOR,
+            );
+        }
+
+        if !self.open {
+            return;
+        }
+        hud.fill_rect(Rect::new(0.0, 0.0, screen.0, screen.1), DIM_COLOR);
+        let panel = Self::panel_rect(screen);
+        hud.fill_rect(panel, PANEL_COLOR);
+        hud.stroke_rect(panel, 2.0, FRAME_COLOR);
+
+        let hovered = Self::slot_at(screen, self.cursor);
+        for slot in Self::slots() {
+            let color = if hovered == Some(slot) {
+                HOVERED_COLOR
+            } else {
+                CELL_COLOR
+            };
+            Self::draw_cell(
+                hud,
+                Self::slot_rect(screen, slot),
+                inventory.item(slot),
+                color,
+            );
+        }
+
+        if let Some(Dragged { item, .. }) = self.dragged {
+            let (x, y) = self.cursor;
+            let cell = Rect::new(
+                x - CELL_SIZE / 2.0,
+                y - CELL_SIZE / 2.0,
+                CELL_SIZE,
+                CELL_SIZE,
+            );
+            Self::draw_item(hud, cell, &item);
+        }
+    }
+
+    fn draw_cell(hud: &mut Hud, cell: Rect, item: Option<&Item>, color: Vec4) {
+        hud.fill_rect(cell, color);
+        hud.stroke_rect(cell, 1.0, FRAME_COLOR);
+        if let Some(item) = item {
+            Self::draw_item(hud, cell, item);
+        }
+    }
+
+    fn draw_item(hud: &mut Hud, cell: Rect, item: &Item) {
+        hud.draw_image(
+            cell.inset(ICON_MARGIN),
+            HudTexture::Blocks,
+            Self::icon(item),
+            vec4(1.0, 1.0, 1.0, 1.0),
+        );
+
+        if let Count::Finite(count) = item.count() {
+            if count > 1 {
+                let text = count.to_string();
+                let (width, _) = BitmapFont::measure(&text, COUNT_SIZE);
+                hud.draw_text_with_shadow(
+                    cell.x + cell.width - width - 2.0,
+                    cell.y + cell.height - COUNT_SIZE - 2.0,
+                    COUNT_SIZE,
+                    &text,
+                    TEXT_COLOR,
+                );
+            }
+        }
+
+        if let (Some(durability), ItemType::TOOL(_, tier) | ItemType::WEAPON(_, tier)) =
+            (item.durability(), item.item_type())
+        {
+            let part = durability as f32 / tier.durability() as f32;
+            let bar = Rect::new(
+                cell.x + 4.0,
+                cell.y + cell.height - 6.0,
+                cell.width - 8.0,
+                3.0,
+            );
+            hud.fill_rect(bar, vec4(0.0, 0.0, 0.0, 0.8));
+            hud.fill_rect(
+                Rect::new(bar.x, bar.y, bar.width * part, bar.height),
+                vec4(1.0 - part, part, 0.0, 1.0),
+            );
+        }
+    }
+
+    /// Tile of the block atlas which shows the item
+    fn icon(item: &Item) -> usize {
+        let kind = match item.item_type() {
+            ItemType::BLOCK | ItemType::DECORATION => {
+                return item.block().unwrap().info().zoffset_texure(0);
+            }
+            ItemType::TOOL(ToolKind::PICKAXE, tier) => (0, tier),
+            ItemType::TOOL(ToolKind::AXE, tier) => (1, tier),
+            ItemType::TOOL(ToolKind::SHOVEL, tier) => (2, tier),
+            ItemType::WEAPON(WeaponKind::SWORD, tier) => (3, tier),
+        };
+        TOOL_ICONS + kind.0 * TIERS_COUNT + kind.1 as usize
+    }
+}
+
+impl Default for InventoryUi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::world::block_state::BlockState;
+    use crate::render::block::BlockType;
+
+    const SCREEN: (f32, f32) = (1280.0, 720.0);
+
+    fn center(slot: Slot) -> (f32, f32) {
+        let rect = InventoryUi::slot_rect(SCREEN, slot);
+        (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0)
+    }
+
+    #[test]
+    fn every_slot_is_found_under_its_cell() {
+        for slot in InventoryUi::slots() {
+            assert_eq!(InventoryUi::slot_at(SCREEN, center(slot)), Some(slot));
+        }
+        assert_eq!(InventoryUi::slot_at(SCREEN, (0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn dragged_stack_swaps_with_target_and_returns_when_dropped_nowhere() {
+        let stone = Item::from_block(BlockState::from(BlockType::STONE), Count::Finite(5));
+        let dirt = Item::from_block(BlockState::from(BlockType::DIRT), Count::Finite(3));
+        let mut inventory = Inventory::new();
+        inventory.put(Slot::Hotbar(0), stone);
+        inventory.put(Slot::Backpack(7), dirt);
+
+        let mut ui = InventoryUi::new();
+        ui.toggle(&mut inventory);
+        ui.move_cursor(center(Slot::Hotbar(0)).0, center(Slot::Hotbar(0)).1);
+        ui.start_drag(&mut inventory, SCREEN, false);
+        assert_eq!(inventory.item(Slot::Hotbar(0)), None);
+        ui.move_cursor(center(Slot::Backpack(7)).0, center(Slot::Backpack(7)).1);
+        ui.drop_dragged(&mut inventory, SCREEN);
+        assert_eq!(inventory.item(Slot::Backpack(7)), Some(&stone));
+        assert_eq!(inventory.item(Slot::Hotbar(0)), Some(&dirt));
+
+        ui.start_drag(&mut inventory, SCREEN, false);
+        ui.move_cursor(0.0, 0.0);
+        ui.drop_dragged(&mut inventory, SCREEN);
+        assert_eq!(inventory.item(Slot::Backpack(7)), Some(&stone));
+    }
+
+    #[test]
+    fn notice_disappears_after_a_while() {
+        let mut ui = InventoryUi::new();
+        ui.show_notice(String::from("Inventory is full"));
+        ui.update(NOTICE_TIME - 0.5);
+        assert!(ui.notice.is_some());
+        ui.update(1.0);
+        assert_eq!(ui.notice, None);
+    }
+
+    #[test]
+    fn half_of_a_stack_is_not_swapped_with_another_item() {
+        let stone = Item::from_block(BlockState::from(BlockType::STONE), Count::Finite(6));
+        let dirt = Item::from_block(BlockState::from(BlockType::DIRT), Count::Finite(3));
+        let mut inventory = Inventory::new();
+        inventory.put(Slot::Hotbar(0), stone);
+        inventory.put(Slot::Backpack(7), dirt);
+
+        let mut ui = InventoryUi::new();
+        ui.toggle(&mut inventory);
+        ui.move_cursor(center(Slot::Hotbar(0)).0, center(Slot::Hotbar(0)).1);
+        ui.start_drag(&mut inventory, SCREEN, true);
+        ui.move_cursor(center(Slot::Backpack(7)).0, center(Slot::Backpack(7)).1);
+        ui.drop_dragged(&mut inventory, SCREEN);
+        assert_eq!(inventory.item(Slot::Backpack(7)), Some(&dirt));
+        assert_eq!(
+            inventory.item(Slot::Hotbar(0)),
+            Some(&stone.with_count(Count::Finite(3)))
+        );
+
+        // The half stays on the cursor until it's dropped where it fits
+        ui.move_cursor(0.0, 0.0);
+        ui.drop_dragged(&mut inventory, SCREEN);
+        assert_eq!(inventory.item(Slot::Hotbar(0)), Some(&stone));
+        assert_eq!(inventory.item(Slot::Backpack(7)), Some(&dirt));
+    }
+}
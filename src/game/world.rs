@@ -1,26 +1,41 @@
 #![allow(dead_code)]
 
+pub mod block_state;
+use block_state::{BlockProperty, BlockState};
+
 pub mod chunk;
-use chunk::Chunk;
+use chunk::{Chunk, ChunkWriter};
+
+pub mod clock;
+use clock::WorldClock;
+
+pub mod info;
+use info::WorldInfo;
 
-use super::player::Player;
+pub mod light;
+use light::{LightPropagator, FULL_SKYLIGHT};
+
+use super::crafting::Station;
+use super::player::{
+    data::PlayerData,
+    inventory::{Count, Item},
+    GameMode, Player,
+};
 
 use crate::render::block::Block;
-use crate::render::mesh::ChunkMesh;
-use crate::render::{Program, Shader, TextureAtlas, TextureAtlasConfiguration};
+use crate::render::mesh::{ChunkMesh, RenderPosition, MESH_OPTIONS};
+use crate::settings::Settings;
 
 use nalgebra_glm::{vec3, Vec3};
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::thread::JoinHandle;
 
 use super::storage::STORAGE;
 use crate::lock;
 
-const DATA_FILE: &str = "data/test-world.dat";
-
 macro_rules! foreach_in_radius {
     (($x:ident, $z:ident; $xcenter:expr, $zcenter:expr; $radius:ident) $body:expr) => {
         for $x in ($xcenter - $radius)..=($xcenter + $radius) {
@@ -30,7 +45,9 @@ macro_rules! foreach_in_radius {
         }
     };
     (($x:ident, $y:ident, $z:ident; $xcenter:expr, $ycenter:expr, $zcenter:expr; $radius:ident) $body:expr) => {
-        for $y in ($ycenter - $radius).max(0)..=($ycenter + $radius).min(crate::game::world::chunk::Chunk::HEIGHT as isize) {
+        // Only heights inside chunks, a player near the build limit or below the world looks
+        // around blocks which don't exist
+        for $y in ($ycenter - $radius).max(0)..($ycenter + $radius + 1).min(crate::game::world::chunk::Chunk::HEIGHT as isize) {
             foreach_in_radius!(($x, $z; $xcenter, $zcenter; $radius) {$body});
         }
     };
@@ -71,85 +88,195 @@ macro_rules! get_block_position {
 }
 
 pub struct World {
-    seed: u32,
+    info: WorldInfo,
     blocksize: f32,
 
     render_center: (isize, isize),
     render_radius_in_chunks: usize,
+    /// Vertical field of view in degrees
+    fov: f32,
 
     player: Rc<RefCell<Player>>,
     player_distance_to_block: usize,
 
+    clock: WorldClock,
+    /// Block which the player looks at, updated every frame
+    target: Option<CoordinateInSpace>,
+    attacking: bool,
+    breaking: Option<BlockBreaking>,
+    /// Message for the player, such as a full inventory, until the game takes it
+    notice: Option<String>,
+
+    /// Chunks read from the world file which aren't loaded yet
+    saved_chunks: HashMap<(isize, isize), Chunk>,
+    /// Loaded chunks which differ from generated ones, only they and `saved_chunks` are saved
+    modified_chunks: HashSet<(isize, isize)>,
+    /// Blocks changed by the player since `take_player_changes`, they are sent to the server
+    player_changes: Vec<((isize, isize, isize), BlockState)>,
+
+    /// Whether meshes of the chunks are built, a headless world only keeps the blocks
+    meshing: bool,
     threads: Vec<JoinHandle<()>>,
-    texture_atlas: TextureAtlas,
-    shader_program: Program,
 }
 
 impl World {
     //TODO:
     //This generation is temporary, need in future change to normal generation!
-    pub fn new(seed: u32, blocksize: f32, player: Rc<RefCell<Player>>) -> Self {
-        #[allow(unused_assignments)]
-        let mut render_radius_in_chunks = 8;
-        if cfg!(target_os = "macos") {
-            render_radius_in_chunks = 4;
-        }
-        let world = Self {
-            seed,
+    pub fn new(
+        info: WorldInfo,
+        blocksize: f32,
+        player: Rc<RefCell<Player>>,
+        settings: &Settings,
+    ) -> Self {
+        let saved_chunks = Self::read_saved_chunks(&info, blocksize);
+        Self::create(info, blocksize, player, settings, true, saved_chunks)
+    }
+
+    /// World without chunk meshes, it's simulated on the server where nothing is rendered
+    pub fn headless(
+        info: WorldInfo,
+        blocksize: f32,
+        player: Rc<RefCell<Player>>,
+        settings: &Settings,
+    ) -> Self {
+        let saved_chunks = Self::read_saved_chunks(&info, blocksize);
+        Self::create(info, blocksize, player, settings, false, saved_chunks)
+    }
+
+    /// World of a server, it's generated from the seed of the server and the chunks which
+    /// differ come from the server. Nothing is read from or saved to the disk.
+    pub fn remote(
+        info: WorldInfo,
+        blocksize: f32,
+        player: Rc<RefCell<Player>>,
+        settings: &Settings,
+    ) -> Self {
+        Self::create(info, blocksize, player, settings, true, HashMap::new())
+    }
+
+    fn create(
+        info: WorldInfo,
+        blocksize: f32,
+        player: Rc<RefCell<Player>>,
+        settings: &Settings,
+        meshing: bool,
+        saved_chunks: HashMap<(isize, isize), Chunk>,
+    ) -> Self {
+        let seed = info.seed();
+        let mut world = Self {
+            info,
             blocksize,
 
             render_center: (0, 0),
-            render_radius_in_chunks,
+            render_radius_in_chunks: settings.render_distance,
+            fov: settings.fov,
 
             player,
             player_distance_to_block: 4,
 
-            threads: vec![],
+            clock: WorldClock::new(),
+            target: None,
+            attacking: false,
+            breaking: None,
+            notice: None,
 
-            texture_atlas: TextureAtlas::from(TextureAtlasConfiguration {
-                image_path: String::from("res/images/block-texture-atlas.png"),
-                square_size: 16,
-            }),
-            shader_program: Program::from([
-                Shader::from_vertex(String::from("res/shaders/block-vert.glsl")).unwrap(),
-                Shader::from_fragment(String::from("res/shaders/block-frag.glsl")).unwrap(),
-            ]),
+            saved_chunks,
+            modified_chunks: HashSet::new(),
+            player_changes: vec![],
+
+            meshing,
+            threads: vec![],
         };
 
         Chunk::set_seed(seed);
 
         let offset = blocksize * Chunk::WIDTH as f32;
-        Self::init_world(&world, offset);
+        Self::init_world(&mut world, offset);
         Self::init_player_position(&world);
         Self::update_player_vision(&world);
         world
     }
 
-    fn init_world(world: &World, offset: f32) {
+    fn read_saved_chunks(info: &WorldInfo, blocksize: f32) -> HashMap<(isize, isize), Chunk> {
+        match chunk::read_from_file(&info.chunks_file(), blocksize) {
+            Ok(chunks) => chunks,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => {
+                eprintln!("Chunks cannot be read, they are generated again: {}", error);
+                HashMap::new()
+            }
+        }
+    }
+
+    fn init_world(world: &mut World, offset: f32) {
         let radius = world.render_radius_in_chunks as isize;
         foreach_in_radius! {
             (x, z; 0, 0; radius) {
-                let chunk = Chunk::create(x as f32 * offset, z as f32 * offset);
+                let chunk = match world.take_saved_chunk(x, z) {
+                    Some(chunk) => chunk,
+                    None => Chunk::create(x as f32 * offset, z as f32 * offset),
+                };
 
                 STORAGE.lock().store_chunk(x, z, chunk);
             }
         }
 
-        for (_, chunk) in STORAGE.lock().all_chunks() {
-            let chunk = chunk.lock();
-            let x = (chunk.xoffset() / offset) as isize;
-            let z = (chunk.zoffset() / offset) as isize;
-            lock!(STORAGE).update_mesh(
-                x,
-                z,
-                ChunkMesh::new(
-                    chunk.blocks(),
-                    chunk.xoffset(),
-                    chunk.zoffset(),
-                    world.blocksize,
-                ),
-            );
+        let mut propagator = LightPropagator::new();
+        foreach_in_radius! {
+            (x, z; 0, 0; radius) {
+                propagator.stitch_chunk(x, z);
+            }
+        }
+
+        if !world.meshing {
+            return;
+        }
+        foreach_in_radius! {
+            (x, z; 0, 0; radius) {
+                Self::rebuild_mesh(x, z, world.blocksize);
+            }
+        }
+    }
+
+    /// Saved chunk which is going to be loaded, it stays modified so it's saved again
+    fn take_saved_chunk(&mut self, xoffset: isize, zoffset: isize) -> Option<Chunk> {
+        let chunk = self.saved_chunks.remove(&(xoffset, zoffset))?;
+        self.modified_chunks.insert((xoffset, zoffset));
+        Some(chunk)
+    }
+
+    /// Generates the chunks within the radius of the spawn chunk and saves them with the world,
+    /// chunks which are saved already are kept. It doesn't need a window, so it can run in CI.
+    /// Returns how many chunks are in the world file.
+    pub fn generate(info: &WorldInfo, blocksize: f32, radius: usize) -> std::io::Result<usize> {
+        let mut saved_chunks = match chunk::read_from_file(&info.chunks_file(), blocksize) {
+            Ok(chunks) => chunks,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(error),
+        };
+        Chunk::set_seed(info.seed());
+
+        let offset = blocksize * Chunk::WIDTH as f32;
+        let radius = radius as isize;
+        let mut writer = ChunkWriter::create(&info.chunks_file())?;
+        let mut count = 0;
+        foreach_in_radius! {
+            (x, z; 0, 0; radius) {
+                let chunk = match saved_chunks.remove(&(x, z)) {
+                    Some(chunk) => chunk,
+                    None => Chunk::create(x as f32 * offset, z as f32 * offset),
+                };
+                writer.write(x, z, &chunk)?;
+                count += 1;
+            }
         }
+        for ((x, z), chunk) in saved_chunks {
+            writer.write(x, z, &chunk)?;
+            count += 1;
+        }
+        writer.finish()?;
+        info.save()?;
+        Ok(count)
     }
 
     fn init_player_position(world: &World) {
@@ -160,8 +287,8 @@ impl World {
         macro_rules! stop_at_block_and_set_new_player_pos {
             (for $y:ident with $blocks:ident, $world:ident) => {
                 if $y != Chunk::HEIGHT
-                    && Block::is_air($blocks[$y][0][0])
-                    && Block::is_air($blocks[$y + 1][0][0])
+                    && $blocks[$y][0][0].is_air()
+                    && $blocks[$y + 1][0][0].is_air()
                 {
                     let new_position = vec3(
                         $world.blocksize / 2.0,
@@ -173,7 +300,7 @@ impl World {
                 }
             };
         }
-        if Block::is_air(blocks[anticipated_surface][0][0]) {
+        if blocks[anticipated_surface][0][0].is_air() {
             for y in (0..=anticipated_surface).rev() {
                 stop_at_block_and_set_new_player_pos!(for y with blocks, world);
             }
@@ -187,47 +314,127 @@ impl World {
     pub fn update_player_vision(world: &World) {
         let mut player = world.player.borrow_mut();
         player.update_vision(
-            45.0,
+            world.fov.to_radians(),
             0.01,
             (world.render_radius_in_chunks * Chunk::WIDTH - 1) as f32,
         )
     }
 
-    pub fn player_destroy_block_if_possible(&mut self) {
-        let player_position = self.player.borrow().position();
-        let view_ray = self.player.borrow().view_ray();
-        if let Some(CoordinateInSpace {
+    /// Starts breaking the target block, it breaks after its hardness time while attacking lasts
+    pub fn player_start_breaking(&mut self) {
+        self.attacking = true;
+    }
+
+    pub fn player_stop_breaking(&mut self) {
+        self.attacking = false;
+        self.breaking = None;
+    }
+
+    /// Every block breaks in the same short time, so holding the button doesn't break them all
+    const CREATIVE_BREAKING_TIME: f32 = 0.15;
+
+    fn update_breaking(&mut self, delta_time: f32) {
+        let target = match (self.attacking, self.target) {
+            (true, Some(target)) => target,
+            _ => {
+                self.breaking = None;
+                return;
+            }
+        };
+
+        let chunk = STORAGE.lock().chunk(target.xoffset, target.zoffset);
+        let Some(chunk) = chunk else {
+            return;
+        };
+        let block_state = chunk.lock().block_at(target.x, target.z, target.y);
+        let block = block_state.info();
+        let time = {
+            let player = self.player.borrow();
+            match player.game_mode() {
+                GameMode::Survival => block.hardness() / player.mining_speed(&block),
+                GameMode::Creative => Self::CREATIVE_BREAKING_TIME,
+            }
+        };
+
+        let mut breaking = match self.breaking {
+            Some(breaking) if breaking.target.same_block(&target) => breaking,
+            _ => BlockBreaking {
+                target,
+                progress: 0.0,
+            },
+        };
+        breaking.progress += if time > 0.0 { delta_time / time } else { 1.0 };
+
+        if breaking.progress >= 1.0 {
+            self.breaking = None;
+            // The block stays if its drop would be lost
+            if !self.player_has_room_for_drop(block_state) {
+                self.notice = Some(String::from("Inventory is full"));
+                return;
+            }
+            self.destroy_block(target);
+            if let Some(broken) = self.player.borrow_mut().wear_held_item() {
+                println!("{} is broken", broken.name());
+            }
+        } else {
+            self.breaking = Some(breaking);
+        }
+    }
+
+    fn destroy_block(&mut self, target: CoordinateInSpace) {
+        let CoordinateInSpace {
             x,
             y,
             z,
             xoffset,
             zoffset,
             ..
-        }) = self.find_nearest_block_in_ray(&player_position, &view_ray)
-        {
-            if let Some(chunk) = STORAGE.lock().chunk(xoffset, zoffset) {
+        } = target;
+        let chunk = STORAGE.lock().chunk(xoffset, zoffset);
+        if let Some(chunk) = chunk {
+            let block = {
                 let mut chunk = chunk.lock();
-                let block = chunk.mut_block_at(x, z, y);
-
-                *block = 0;
-                lock!(STORAGE).update_mesh(
-                    xoffset,
-                    zoffset,
-                    ChunkMesh::new(
-                        chunk.blocks(),
-                        chunk.xoffset(),
-                        chunk.zoffset(),
-                        self.blocksize,
-                    ),
-                );
-                self.rerender_neighbors(x, y, z, xoffset, zoffset);
-            }
+                let block = chunk.block_at(x, z, y);
+                chunk.set_block_at(x, z, y, BlockState::AIR);
+                block
+            };
+            self.on_block_changed(x, y, z, xoffset, zoffset, BlockState::AIR);
+            self.player_changes.push((
+                Self::world_position(x, y, z, xoffset, zoffset),
+                BlockState::AIR,
+            ));
+            self.player_pick_drop(block);
+        }
+    }
+
+    fn player_has_room_for_drop(&self, broken: BlockState) -> bool {
+        let player = self.player.borrow();
+        match Block::drop_of(broken) {
+            Some((block, count)) if player.game_mode() == GameMode::Survival => player
+                .inventory()
+                .has_room_for(Item::from_block(block, Count::Finite(count))),
+            _ => true,
+        }
+    }
+
+    /// Room for the drop is checked before the block is broken, see `player_has_room_for_drop`
+    fn player_pick_drop(&mut self, broken: BlockState) {
+        if self.player.borrow().game_mode() == GameMode::Creative {
+            return;
+        }
+        if let Some((block, count)) = Block::drop_of(broken) {
+            self.player
+                .borrow_mut()
+                .pick_block(block, Count::Finite(count));
         }
     }
 
     pub fn player_place_block_if_possible(&mut self) {
-        let block_to_update = match self.player.borrow_mut().get_block_in_hand() {
-            Some(item) => item.id(),
+        let block_to_update = match self.player.borrow().held_item() {
+            Some(item) => match item.block() {
+                Some(block) => block,
+                None => return,
+            },
             None => return,
         };
 
@@ -242,12 +449,13 @@ impl World {
             intersection_point,
         }) = self.find_nearest_block_in_ray(&player_position, &view_ray)
         {
-            let block_pos = vec3(
-                x as f32 + (xoffset * Chunk::WIDTH_ISIZE) as f32,
-                y as f32,
-                z as f32 + (zoffset * Chunk::WIDTH_ISIZE) as f32,
+            let diff =
+                intersection_point - CoordinateInSpace::block_position(x, y, z, xoffset, zoffset);
+            let block_to_update = Self::orient_placed_block(
+                block_to_update,
+                hit_face(&diff),
+                self.player.borrow().horizontal_facing(),
             );
-            let diff = intersection_point - block_pos;
             macro_rules! shift_to_need_axis {
                 ($diff:ident, $axis:ident, $axisoffset:ident, $max:expr) => {
                     if $diff.$axis == 1.0 {
@@ -297,30 +505,71 @@ impl World {
                 }
             }
 
-            if let Some(chunk) = STORAGE.lock().chunk(xoffset, zoffset) {
-                let mut chunk = chunk.lock();
-                let block = chunk.mut_block_at(x, z, y);
+            let chunk = STORAGE.lock().chunk(xoffset, zoffset);
+            if let Some(chunk) = chunk {
+                {
+                    let mut chunk = chunk.lock();
+                    let block = chunk.mut_block_at(x, z, y);
 
-                if !Block::is_air(*block) {
-                    return;
-                }
+                    if !block.is_air() {
+                        return;
+                    }
 
-                *block = block_to_update;
-                lock!(STORAGE).update_mesh(
-                    xoffset,
-                    zoffset,
-                    ChunkMesh::new(
-                        chunk.blocks(),
-                        chunk.xoffset(),
-                        chunk.zoffset(),
-                        self.blocksize,
-                    ),
-                );
-                self.rerender_neighbors(x, y, z, xoffset, zoffset);
+                    *block = block_to_update;
+                }
+                self.player.borrow_mut().consume_block_in_hand();
+                self.on_block_changed(x, y, z, xoffset, zoffset, block_to_update);
+                self.player_changes.push((
+                    Self::world_position(x, y, z, xoffset, zoffset),
+                    block_to_update,
+                ));
             }
         }
     }
 
+    /// Logs are placed along the axis of the hit face, blocks with facing turn front to the player.
+    fn orient_placed_block(
+        block: BlockState,
+        hit_face: Option<RenderPosition>,
+        player_facing: RenderPosition,
+    ) -> BlockState {
+        let mut block = block;
+        if let (true, Some(face)) = (block.has(BlockProperty::Axis), hit_face) {
+            block = block.with_axis(face.axis());
+        }
+        if block.has(BlockProperty::Facing) {
+            block = block.with_facing(player_facing.opposite());
+        }
+        block
+    }
+
+    /// The block which the player looks at
+    pub fn target_block(&self) -> Option<BlockState> {
+        let target = self.target?;
+        let chunk = STORAGE.lock().chunk(target.xoffset, target.zoffset)?;
+        let block = chunk.lock().block_at(target.x, target.z, target.y);
+        Some(block)
+    }
+
+    /// World position of the block which the player looks at
+    pub fn target_block_position(&self) -> Option<Vec3> {
+        self.target.as_ref().map(|coord| {
+            CoordinateInSpace::block_position(
+                coord.x,
+                coord.y,
+                coord.z,
+                coord.xoffset,
+                coord.zoffset,
+            )
+        })
+    }
+
+    fn update_target(&mut self) {
+        let player_position = self.player.borrow().position();
+        let view_ray = self.player.borrow().view_ray();
+        self.target = self.find_nearest_block_in_ray(&player_position, &view_ray);
+    }
+
     fn find_nearest_block_in_ray(
         &self,
         player_position: &Vec3,
@@ -345,7 +594,7 @@ impl World {
                 if let Some(chunk) = STORAGE.lock().chunk(xoffset, zoffset) {
                     let chunk = chunk.lock();
                     let block = chunk.block_at(xblock, zblock, yblock);
-                    if Block::is_air(block) {
+                    if block.is_air() {
                         continue;
                     }
 
@@ -387,61 +636,363 @@ impl World {
         (tmin, tmax)
     }
 
-    fn rerender_neighbors(&mut self, x: usize, y: usize, z: usize, xoffset: isize, zoffset: isize) {
-        macro_rules! update_mesh {
-            ((($xoffset:expr, $zoffset:expr), $chunk:ident, $blocksize:ident) => $storage:ident) => {
-                lock!($storage).update_mesh(
-                    $xoffset,
-                    $zoffset,
-                    ChunkMesh::new(
-                        $chunk.blocks(),
-                        $chunk.xoffset(),
-                        $chunk.zoffset(),
-                        $blocksize,
-                    ),
-                );
-            };
+    /// Updates light around the changed block and rebuilds meshes of every chunk which it affects
+    fn on_block_changed(
+        &mut self,
+        x: usize,
+        y: usize,
+        z: usize,
+        xoffset: isize,
+        zoffset: isize,
+        new_block: BlockState,
+    ) {
+        self.modified_chunks.insert((xoffset, zoffset));
+        let mut propagator = LightPropagator::new();
+        propagator.block_changed(Self::world_position(x, y, z, xoffset, zoffset), new_block);
+        if !self.meshing {
+            return;
         }
-        let blocksize = self.blocksize;
-        if x == 0 {
-            if let Some(chunk) = lock!(STORAGE).chunk(xoffset - 1, zoffset) {
-                let chunk = lock!(chunk);
-                let block = chunk.blocks()[y][Chunk::WIDTH - 1][z as usize];
-                if !Block::is_air(block) {
-                    update_mesh! (((xoffset - 1, zoffset), chunk, blocksize) =>  STORAGE);
-                }
+
+        let mut chunks = Self::neighbors_to_rerender(x, y, z, xoffset, zoffset);
+        chunks.push((xoffset, zoffset));
+        for chunk in propagator.touched_chunks() {
+            if !chunks.contains(chunk) && STORAGE.lock().get_mesh(chunk.0, chunk.1).is_some() {
+                chunks.push(*chunk);
             }
-        } else if x == Chunk::WIDTH - 1 {
-            if let Some(chunk) = lock!(STORAGE).chunk(xoffset + 1, zoffset) {
-                let chunk = lock!(chunk);
-                let block = chunk.blocks()[y][0][z as usize];
-                if !Block::is_air(block) {
-                    update_mesh! (((xoffset + 1, zoffset), chunk, blocksize) =>  STORAGE);
+        }
+        for (xoffset, zoffset) in chunks {
+            Self::rebuild_mesh(xoffset, zoffset, self.blocksize);
+        }
+    }
+
+    fn world_position(
+        x: usize,
+        y: usize,
+        z: usize,
+        xoffset: isize,
+        zoffset: isize,
+    ) -> (isize, isize, isize) {
+        (
+            x as isize + xoffset * Chunk::WIDTH_ISIZE,
+            y as isize,
+            z as isize + zoffset * Chunk::WIDTH_ISIZE,
+        )
+    }
+
+    /// Neighbour chunks which have a block next to the given one on the chunk border
+    fn neighbors_to_rerender(
+        x: usize,
+        y: usize,
+        z: usize,
+        xoffset: isize,
+        zoffset: isize,
+    ) -> Vec<(isize, isize)> {
+        let mut neighbors = vec![];
+        let mut check_neighbor = |(xoffset, zoffset): (isize, isize), (x, z): (usize, usize)| {
+            let chunk = STORAGE.lock().chunk(xoffset, zoffset);
+            if let Some(chunk) = chunk {
+                if !chunk.lock().block_at(x, z, y).is_air() {
+                    neighbors.push((xoffset, zoffset));
                 }
             }
+        };
+
+        if x == 0 {
+            check_neighbor((xoffset - 1, zoffset), (Chunk::WIDTH - 1, z));
+        } else if x == Chunk::WIDTH - 1 {
+            check_neighbor((xoffset + 1, zoffset), (0, z));
         }
 
         if z == 0 {
-            if let Some(chunk) = lock!(STORAGE).chunk(xoffset, zoffset - 1) {
-                let chunk = lock!(chunk);
-                let block = chunk.blocks()[y][x as usize][Chunk::WIDTH - 1];
-                if !Block::is_air(block) {
-                    update_mesh! (((xoffset, zoffset - 1), chunk, blocksize) =>  STORAGE);
+            check_neighbor((xoffset, zoffset - 1), (x, Chunk::WIDTH - 1));
+        } else if z == Chunk::WIDTH - 1 {
+            check_neighbor((xoffset, zoffset + 1), (x, 0));
+        }
+        neighbors
+    }
+
+    pub fn toggle_ambient_occlusion(&mut self) {
+        let mut options = MESH_OPTIONS.lock();
+        options.ambient_occlusion = !options.ambient_occlusion;
+        drop(options);
+        self.rebuild_all_meshes();
+    }
+
+    pub fn toggle_smooth_lighting(&mut self) {
+        let mut options = MESH_OPTIONS.lock();
+        options.smooth_lighting = !options.smooth_lighting;
+        drop(options);
+        self.rebuild_all_meshes();
+    }
+
+    fn rebuild_all_meshes(&self) {
+        let rendered = STORAGE
+            .lock()
+            .all_mesh()
+            .keys()
+            .copied()
+            .collect::<Vec<(isize, isize)>>();
+        for (xoffset, zoffset) in rendered {
+            Self::rebuild_mesh(xoffset, zoffset, self.blocksize);
+        }
+    }
+
+    /// Spreads light between the stored chunk and its neighbours and meshes the chunks which
+    /// got new light
+    fn stitch_new_chunk(xoffset: isize, zoffset: isize, blocksize: f32, meshing: bool) {
+        let mut propagator = LightPropagator::new();
+        propagator.stitch_chunk(xoffset, zoffset);
+        if !meshing {
+            return;
+        }
+        Self::rebuild_mesh(xoffset, zoffset, blocksize);
+        for (xtouched, ztouched) in propagator.touched_chunks() {
+            let is_rendered = STORAGE.lock().get_mesh(*xtouched, *ztouched).is_some();
+            if (*xtouched, *ztouched) != (xoffset, zoffset) && is_rendered {
+                Self::rebuild_mesh(*xtouched, *ztouched, blocksize);
+            }
+        }
+    }
+
+    fn rebuild_mesh(xoffset: isize, zoffset: isize, blocksize: f32) {
+        let chunk = STORAGE.lock().chunk(xoffset, zoffset);
+        if let Some(chunk) = chunk {
+            let mesh = ChunkMesh::new(&chunk.lock(), blocksize);
+            STORAGE.lock().update_mesh(xoffset, zoffset, mesh);
+        }
+    }
+
+    /// Crafting stations within the reach of the player
+    pub fn stations_near_player(&self) -> Vec<Station> {
+        let position = self.player.borrow().position() / self.blocksize;
+        let xcenter = position.x.floor() as isize;
+        let ycenter = position.y.floor() as isize;
+        let zcenter = position.z.floor() as isize;
+        let radius = self.player_distance_to_block as isize;
+
+        let mut stations = vec![];
+        foreach_in_radius! {
+            (x, y, z; xcenter, ycenter, zcenter; radius) {
+                let mut block_position = vec3(x as f32, y as f32, z as f32);
+                get_block_position!((xblock, yblock, zblock, xoffset, zoffset) <= block_position);
+                let chunk = STORAGE.lock().chunk(xoffset, zoffset);
+                if let Some(chunk) = chunk {
+                    let block = chunk.lock().block_at(xblock, zblock, yblock);
+                    if let Some(station) = Station::of_block(block.info().name()) {
+                        if !stations.contains(&station) {
+                            stations.push(station);
+                        }
+                    }
                 }
             }
-        } else if z == Chunk::WIDTH - 1 {
-            if let Some(chunk) = lock!(STORAGE).chunk(xoffset, zoffset + 1) {
-                let chunk = lock!(chunk);
-                let block = chunk.blocks()[y][x as usize][0];
-                if !Block::is_air(block) {
-                    update_mesh! (((xoffset, zoffset + 1), chunk, blocksize) =>  STORAGE);
+        }
+        stations
+    }
+
+    /// Message for the player since the last call
+    pub fn take_notice(&mut self) -> Option<String> {
+        self.notice.take()
+    }
+
+    /// Blocks changed by the player since the last call, as positions in blocks
+    pub fn take_player_changes(&mut self) -> Vec<((isize, isize, isize), BlockState)> {
+        std::mem::take(&mut self.player_changes)
+    }
+
+    /// Changes the block at the position in blocks, e.g. when another player has changed it.
+    /// A chunk which isn't loaded is generated and kept with the change until it's loaded.
+    pub fn set_block(&mut self, position: (isize, isize, isize), block: BlockState) {
+        let Some((xoffset, zoffset, x, y, z)) = Self::split_position(position) else {
+            return;
+        };
+        let chunk = STORAGE.lock().chunk(xoffset, zoffset);
+        if let Some(chunk) = chunk {
+            {
+                let mut chunk = chunk.lock();
+                if chunk.block_at(x, z, y) == block {
+                    return;
                 }
+                chunk.set_block_at(x, z, y, block);
             }
+            self.on_block_changed(x, y, z, xoffset, zoffset, block);
+            return;
         }
+        let offset = self.blocksize * Chunk::WIDTH as f32;
+        self.saved_chunks
+            .entry((xoffset, zoffset))
+            .or_insert_with(|| Chunk::create(xoffset as f32 * offset, zoffset as f32 * offset))
+            .set_block_at(x, z, y, block);
+    }
+
+    /// Block at the position in blocks. A chunk which isn't loaded is read from the saved
+    /// chunks or generated without keeping it.
+    pub fn block_at(&self, position: (isize, isize, isize)) -> BlockState {
+        let Some((xoffset, zoffset, x, y, z)) = Self::split_position(position) else {
+            return BlockState::AIR;
+        };
+        let chunk = STORAGE.lock().chunk(xoffset, zoffset);
+        if let Some(chunk) = chunk {
+            return chunk.lock().block_at(x, z, y);
+        }
+        if let Some(chunk) = self.saved_chunks.get(&(xoffset, zoffset)) {
+            return chunk.block_at(x, z, y);
+        }
+        let offset = self.blocksize * Chunk::WIDTH as f32;
+        Chunk::create(xoffset as f32 * offset, zoffset as f32 * offset).block_at(x, z, y)
+    }
+
+    /// Chunk of the position in blocks and the position inside it, `None` above or below
+    /// the world
+    fn split_position(
+        position: (isize, isize, isize),
+    ) -> Option<(isize, isize, usize, usize, usize)> {
+        let (x, y, z) = position;
+        if !(0..Chunk::HEIGHT as isize).contains(&y) {
+            return None;
+        }
+        Some((
+            x.div_euclid(Chunk::WIDTH_ISIZE),
+            z.div_euclid(Chunk::WIDTH_ISIZE),
+            x.rem_euclid(Chunk::WIDTH_ISIZE) as usize,
+            y as usize,
+            z.rem_euclid(Chunk::WIDTH_ISIZE) as usize,
+        ))
+    }
+
+    /// Whether the chunk with the position in blocks is loaded
+    pub fn is_loaded(&self, position: (isize, isize, isize)) -> bool {
+        let (x, _, z) = position;
+        let (xoffset, zoffset) = (
+            x.div_euclid(Chunk::WIDTH_ISIZE),
+            z.div_euclid(Chunk::WIDTH_ISIZE),
+        );
+        STORAGE.lock().chunk(xoffset, zoffset).is_some()
+    }
+
+    /// Replaces the chunk with one which came from the server, a chunk which isn't loaded is
+    /// kept until it's loaded
+    pub fn replace_chunk(&mut self, xoffset: isize, zoffset: isize, chunk: Chunk) {
+        if STORAGE.lock().chunk(xoffset, zoffset).is_none() {
+            self.saved_chunks.insert((xoffset, zoffset), chunk);
+            return;
+        }
+        STORAGE.lock().store_chunk(xoffset, zoffset, chunk);
+        self.modified_chunks.insert((xoffset, zoffset));
+        Self::stitch_new_chunk(xoffset, zoffset, self.blocksize, self.meshing);
+    }
+
+    /// Packed blocks of every chunk which differs from the generated one, see
+    /// `Chunk::encode_blocks`. They're sent to players who join the server.
+    pub fn encode_changed_chunks(&self) -> Vec<((isize, isize), Vec<u8>)> {
+        let mut chunks = vec![];
+        for (x, z) in &self.modified_chunks {
+            let chunk = STORAGE.lock().chunk(*x, *z);
+            if let Some(chunk) = chunk {
+                chunks.push(((*x, *z), chunk.lock().encode_blocks()));
+            }
+        }
+        for (position, chunk) in &self.saved_chunks {
+            chunks.push((*position, chunk.encode_blocks()));
+        }
+        chunks
+    }
+
+    /// Saves the changed chunks and the player with the world
+    pub fn save(&self) -> std::io::Result<()> {
+        self.save_chunks()?;
+        self.player
+            .borrow()
+            .data()
+            .save(&self.info.player_file().to_string_lossy())
+    }
+
+    /// Saves the changed chunks without the player, the server has no player of its own
+    pub fn save_chunks(&self) -> std::io::Result<()> {
+        self.info.save()?;
+        let mut writer = ChunkWriter::create(&self.info.chunks_file())?;
+        for (x, z) in &self.modified_chunks {
+            let chunk = STORAGE.lock().chunk(*x, *z);
+            if let Some(chunk) = chunk {
+                writer.write(*x, *z, &chunk.lock())?;
+            }
+        }
+        for ((x, z), chunk) in &self.saved_chunks {
+            writer.write(*x, *z, chunk)?;
+        }
+        writer.finish()
+    }
+
+    /// Restores the player saved with the world, the new player stays if there is no save yet
+    pub fn restore_player(&mut self) -> std::io::Result<()> {
+        match PlayerData::load(&self.info.player_file().to_string_lossy()) {
+            Ok(data) => {
+                self.player.borrow_mut().restore(data);
+                Ok(())
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Waits for the worker threads and unloads all chunks, so another world can be loaded
+    pub fn close(self) {
+        for thread in self.threads {
+            let _ = thread.join();
+        }
+        STORAGE.lock().clear();
+    }
+
+    pub fn info(&self) -> &WorldInfo {
+        &self.info
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.info.seed()
+    }
+
+    pub fn blocksize(&self) -> f32 {
+        self.blocksize
+    }
+
+    /// Chunks which are still generated or meshed by worker threads
+    pub fn pending_jobs(&self) -> usize {
+        self.threads
+            .iter()
+            .filter(|thread| !thread.is_finished())
+            .count()
+    }
+
+    /// Radius of the loaded area in chunks
+    pub fn render_distance(&self) -> usize {
+        self.render_radius_in_chunks
+    }
+
+    /// Position of the block which the player is breaking and how much of it is broken
+    pub fn breaking_block(&self) -> Option<(Vec3, f32)> {
+        let BlockBreaking { target, progress } = self.breaking?;
+        let position = CoordinateInSpace::block_position(
+            target.x,
+            target.y,
+            target.z,
+            target.xoffset,
+            target.zoffset,
+        );
+        Some((position, progress))
+    }
+
+    pub fn clock(&self) -> &WorldClock {
+        &self.clock
+    }
+
+    pub fn clock_mut(&mut self) -> &mut WorldClock {
+        &mut self.clock
     }
 
     pub fn update(&mut self, delta_time: f32) {
+        self.clock.advance(delta_time);
         self.update_player_position(delta_time);
+        self.update_target();
+        self.update_breaking(delta_time);
         self.update_mesh_if_needed();
     }
 
@@ -462,7 +1013,7 @@ impl World {
 
                 let binding_chunk = STORAGE.lock().chunk(xoffset, zoffset).unwrap();
                 let chunk = binding_chunk.lock();
-                if Block::is_air(chunk.block_at(x, z, y)) {
+                if chunk.block_at(x, z, y).is_air() {
                     continue;
                 }
 
@@ -528,48 +1079,56 @@ impl World {
             return;
         }
 
+        self.load_chunks_around(xplayer_pos, zplayer_pos);
+
+        let radius = self.render_radius_in_chunks as isize;
+        let (xcenter, zcenter) = self.render_center;
+
+        foreach_in_radius! {
+            (x, z; xcenter, zcenter; radius) {
+                if (x - xplayer_pos).abs() > radius || (z - zplayer_pos).abs() > radius {
+                    STORAGE.lock().destroy_mesh(x, z);
+                }
+            }
+        };
+        self.render_center = (xplayer_pos, zplayer_pos);
+    }
+
+    /// Generates missing chunks in the render radius around the chunk and meshes unmeshed ones
+    pub fn load_chunks_around(&mut self, xcenter: isize, zcenter: isize) {
         let radius = self.render_radius_in_chunks as isize;
         let offset = self.blocksize * Chunk::WIDTH as f32;
         let blocksize = self.blocksize;
+        let meshing = self.meshing;
 
         foreach_in_radius! {
-            (x, z; xplayer_pos, zplayer_pos; radius) {
+            (x, z; xcenter, zcenter; radius) {
 
                 if let Some(chunk) = lock!(STORAGE).chunk(x, z) {
-                    if lock!(STORAGE).get_mesh(x, z).is_some() {
+                    if !meshing || lock!(STORAGE).get_mesh(x, z).is_some() {
                         continue;
                     }
 
                     let thread = new_thread! {
                         (size = size!(8, MiB)) {
                             move || {
-                                let chunk = chunk.lock();
-                                lock!(STORAGE).update_mesh(
-                                    x,
-                                    z,
-                                    ChunkMesh::new(
-                                        chunk.blocks(),
-                                        chunk.xoffset(),
-                                        chunk.zoffset(),
-                                        blocksize,
-                                    ),
-                                );
+                                let mesh = ChunkMesh::new(&chunk.lock(), blocksize);
+                                lock!(STORAGE).update_mesh(x, z, mesh);
                             }
                         }
                     };
                     self.threads.push(thread);
                 } else  {
+                    let saved = self.take_saved_chunk(x, z);
                     let thread = new_thread!{
                         (size = size!(8, MiB)) {
                             move || {
                                 let xoffset = x as f32 * offset;
                                 let zoffset = z as f32 * offset;
-                                let chunk = Chunk::create(xoffset, zoffset);
-                                let mesh =
-                                    ChunkMesh::new(chunk.blocks(), xoffset, zoffset, blocksize);
-                                let mut storage = STORAGE.lock();
-                                storage.store_chunk(x, z, chunk);
-                                storage.update_mesh(x, z, mesh);
+                                let chunk =
+                                    saved.unwrap_or_else(|| Chunk::create(xoffset, zoffset));
+                                STORAGE.lock().store_chunk(x, z, chunk);
+                                Self::stitch_new_chunk(x, z, blocksize, meshing);
                             }
                         }
                     };
@@ -577,17 +1136,28 @@ impl World {
                 }
             }
         };
+    }
 
+    /// Loads chunks which get in the radius and unloads meshes which get out of it
+    pub fn set_render_distance(&mut self, radius_in_chunks: usize) {
+        let old_radius = self.render_radius_in_chunks as isize;
+        let radius = radius_in_chunks as isize;
         let (xcenter, zcenter) = self.render_center;
-
         foreach_in_radius! {
-            (x, z; xcenter, zcenter; radius) {
-                if (x - xplayer_pos).abs() > radius || (z - zplayer_pos).abs() > radius {
+            (x, z; xcenter, zcenter; old_radius) {
+                if (x - xcenter).abs() > radius || (z - zcenter).abs() > radius {
                     STORAGE.lock().destroy_mesh(x, z);
                 }
             }
         };
-        self.render_center = (xplayer_pos, zplayer_pos);
+        self.render_radius_in_chunks = radius_in_chunks;
+        self.load_chunks_around(xcenter, zcenter);
+        Self::update_player_vision(self);
+    }
+
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov;
+        Self::update_player_vision(self);
     }
 
     const MAX_ACCEPTS: usize = 5;
@@ -606,41 +1176,6 @@ impl World {
             self.threads.remove(*i);
         }
     }
-
-    pub fn render(&self) {
-        self.texture_atlas.set_used();
-        self.shader_program.set_used();
-
-        let player = self.player.borrow();
-        self.shader_program.insert_mat4(
-            &std::ffi::CString::new("projection").unwrap(),
-            player.projection(),
-        );
-        self.shader_program
-            .insert_mat4(&std::ffi::CString::new("view").unwrap(), &player.look_at());
-
-        self.shader_program.insert_vec3(
-            &std::ffi::CString::new("camera_position").unwrap(),
-            &player.position(),
-        );
-        self.shader_program.insert_vec3(
-            &std::ffi::CString::new("fog_color").unwrap(),
-            &vec3(0.3, 0.3, 0.5),
-        );
-
-        self.shader_program.insert_float(
-            &std::ffi::CString::new("fog_min_dist").unwrap(),
-            (self.render_radius_in_chunks * Chunk::WIDTH - 2) as f32,
-        );
-        self.shader_program.insert_float(
-            &std::ffi::CString::new("fog_max_dist").unwrap(),
-            (self.render_radius_in_chunks * Chunk::WIDTH) as f32,
-        );
-
-        for mesh in STORAGE.lock().all_mesh().clone().values() {
-            mesh.render(&self.shader_program);
-        }
-    }
 }
 
 pub struct WorldHelper {
@@ -649,7 +1184,18 @@ pub struct WorldHelper {
 }
 
 impl WorldHelper {
-    pub fn get_block_at(xyz: &Vec3, blocksize: f32) -> u64 {
+    pub fn get_light_at(xyz: &Vec3, blocksize: f32) -> u8 {
+        let mut xyz_normalized = xyz / blocksize;
+        get_block_position!((x, y, z, xoffset, zoffset) <= xyz_normalized);
+
+        if let Some(chunk) = lock!(STORAGE).chunk(xoffset, zoffset) {
+            lock!(chunk).light_at(x, z, y)
+        } else {
+            FULL_SKYLIGHT
+        }
+    }
+
+    pub fn get_block_at(xyz: &Vec3, blocksize: f32) -> BlockState {
         let mut xyz_normalized = xyz / blocksize;
         get_block_position!((x, y, z, xoffset, zoffset) <= xyz_normalized);
 
@@ -662,6 +1208,7 @@ impl WorldHelper {
     }
 }
 
+#[derive(Clone, Copy)]
 struct CoordinateInSpace {
     x: usize,
     y: usize,
@@ -671,6 +1218,29 @@ struct CoordinateInSpace {
     intersection_point: Vec3,
 }
 
+impl CoordinateInSpace {
+    fn same_block(&self, other: &CoordinateInSpace) -> bool {
+        (self.x, self.y, self.z, self.xoffset, self.zoffset)
+            == (other.x, other.y, other.z, other.xoffset, other.zoffset)
+    }
+
+    fn block_position(x: usize, y: usize, z: usize, xoffset: isize, zoffset: isize) -> Vec3 {
+        vec3(
+            x as f32 + (xoffset * Chunk::WIDTH_ISIZE) as f32,
+            y as f32,
+            z as f32 + (zoffset * Chunk::WIDTH_ISIZE) as f32,
+        )
+    }
+}
+
+/// Progress of breaking the block which the player attacks
+#[derive(Clone, Copy)]
+struct BlockBreaking {
+    target: CoordinateInSpace,
+    /// From 0 to 1, the block breaks when it's reached 1
+    progress: f32,
+}
+
 fn shift_negative_block_coord(mut coord: isize) -> usize {
     if coord < 0 {
         coord += Chunk::WIDTH_ISIZE;
@@ -684,3 +1254,73 @@ fn shift_negative_coord(mut coord: f32) -> f32 {
     }
     coord
 }
+
+/// Face of the block which contains the point, `diff` is the point relative to the block origin.
+fn hit_face(diff: &Vec3) -> Option<RenderPosition> {
+    if diff.x == 0.0 {
+        Some(RenderPosition::WEST)
+    } else if diff.x == 1.0 {
+        Some(RenderPosition::EAST)
+    } else if diff.y == 0.0 {
+        Some(RenderPosition::BOTTOM)
+    } else if diff.y == 1.0 {
+        Some(RenderPosition::TOP)
+    } else if diff.z == 0.0 {
+        Some(RenderPosition::NORTH)
+    } else if diff.z == 1.0 {
+        Some(RenderPosition::SOUTH)
+    } else {
+        None
+    }
+}
+
+/// Runs a test on a thread with a stack as big as the main thread of the game has, chunks are
+/// built on the stack
+pub fn run_with_big_stack(test: fn()) {
+    new_thread! {
+        (size = size!(32, MiB)) {
+            test
+        }
+    }
+    .join()
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::storage::TEST_LOCK;
+    use crate::render::block::BlockType;
+
+    fn open(info: &WorldInfo) -> World {
+        let settings = Settings {
+            render_distance: 2,
+            ..Settings::default()
+        };
+        let player = Player::new(1.0, settings.fov.to_radians(), 0.1, 200.0);
+        World::headless(info.clone(), 1.0, Rc::new(RefCell::new(player)), &settings)
+    }
+
+    #[test]
+    fn changed_blocks_are_saved_with_the_world() {
+        run_with_big_stack(|| {
+            let _storage = TEST_LOCK.lock();
+            let directory = std::env::temp_dir().join(format!("t3d-world-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&directory);
+            let info = WorldInfo::create_at(&directory, "Saved", 7).unwrap();
+            let glass = BlockState::from(BlockType::GLASS);
+
+            let mut world = open(&info);
+            world.set_block((3, 200, -5), glass);
+            world.save().unwrap();
+            world.close();
+
+            let world = open(&info);
+            let chunk = STORAGE.lock().chunk(0, -1).unwrap();
+            let block = chunk.lock().block_at(3, 11, 200);
+            world.close();
+            std::fs::remove_dir_all(&directory).unwrap();
+            assert_eq!(block, glass);
+        });
+    }
+}
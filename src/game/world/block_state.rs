@@ -0,0 +1,235 @@
+use crate::render::block::{Block, BlockType, BLOCK_COUNT};
+use crate::render::mesh::RenderPosition;
+
+/// Named property which can be stored in the 48 metadata bits of a block.
+///
+/// Every block declares its own schema (see `Block::properties`): an ordered list of properties
+/// which are packed one after another right above the 16 bits of block id.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockProperty {
+    /// One of six directions, stored as `RenderPosition`
+    Facing,
+    /// One of three axes, stored as `Axis`
+    Axis,
+    /// 0 is a source block, 7 is the thinnest flow
+    FluidLevel,
+    /// 0 is just planted, 7 is fully grown
+    GrowthStage,
+    /// Redstone-like boolean flag
+    Powered,
+}
+
+impl BlockProperty {
+    pub const fn bit_width(&self) -> u32 {
+        match self {
+            BlockProperty::Facing => 3,
+            BlockProperty::Axis => 2,
+            BlockProperty::FluidLevel => 3,
+            BlockProperty::GrowthStage => 3,
+            BlockProperty::Powered => 1,
+        }
+    }
+
+    pub const fn max_value(&self) -> u8 {
+        match self {
+            BlockProperty::Facing => 5,
+            BlockProperty::Axis => 2,
+            BlockProperty::FluidLevel => 7,
+            BlockProperty::GrowthStage => 7,
+            BlockProperty::Powered => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Axis {
+    X = 0,
+    Y = 1,
+    Z = 2,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+}
+
+/// Block as it is stored in a chunk. The u64 looks like:
+/// 48 bits - metatdata information, packed by schema of the block
+/// 16 bits - block id
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Hash)]
+pub struct BlockState(u64);
+
+impl BlockState {
+    pub const AIR: BlockState = BlockState(0);
+
+    pub const ID_BITS: u32 = 16;
+    pub const METADATA_BITS: u32 = 48;
+    const ID_MASK: u64 = (1 << Self::ID_BITS) - 1;
+
+    pub const fn new(id: u16) -> Self {
+        Self(id as u64)
+    }
+
+    pub const fn from_raw(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    pub const fn raw(self) -> u64 {
+        self.0
+    }
+
+    pub const fn id(self) -> u16 {
+        (self.0 & Self::ID_MASK) as u16
+    }
+
+    pub const fn metadata(self) -> u64 {
+        self.0 >> Self::ID_BITS
+    }
+
+    pub const fn is_air(self) -> bool {
+        self.id() == 0
+    }
+
+    /// Whether the id is a block of the game, states read from files or the network may not be
+    pub const fn is_known(self) -> bool {
+        (self.id() as usize) < BLOCK_COUNT
+    }
+
+    pub fn info(self) -> Block {
+        Block::from(self.id() as usize)
+    }
+
+    pub fn has(self, property: BlockProperty) -> bool {
+        self.property_offset(property).is_some()
+    }
+
+    /// Returns `None` if the block has no such property in its schema.
+    pub fn get(self, property: BlockProperty) -> Option<u8> {
+        let offset = self.property_offset(property)?;
+        let mask = (1u64 << property.bit_width()) - 1;
+        Some(((self.0 >> offset) & mask) as u8)
+    }
+
+    /// Panics if the block has no such property or the value does not fit into it.
+    pub fn with(self, property: BlockProperty, value: u8) -> Self {
+        assert!(value <= property.max_value());
+        let offset = self
+            .property_offset(property)
+            .expect("Block does not have such property in its schema!");
+        let mask = ((1u64 << property.bit_width()) - 1) << offset;
+        Self((self.0 & !mask) | ((value as u64) << offset))
+    }
+
+    pub fn facing(self) -> Option<RenderPosition> {
+        self.get(BlockProperty::Facing)
+            .map(|value| RenderPosition::ALL[value as usize])
+    }
+
+    pub fn with_facing(self, facing: RenderPosition) -> Self {
+        self.with(BlockProperty::Facing, facing as u8)
+    }
+
+    pub fn axis(self) -> Option<Axis> {
+        self.get(BlockProperty::Axis)
+            .map(|value| Axis::ALL[value as usize])
+    }
+
+    pub fn with_axis(self, axis: Axis) -> Self {
+        self.with(BlockProperty::Axis, axis as u8)
+    }
+
+    pub fn fluid_level(self) -> Option<u8> {
+        self.get(BlockProperty::FluidLevel)
+    }
+
+    pub fn with_fluid_level(self, level: u8) -> Self {
+        self.with(BlockProperty::FluidLevel, level)
+    }
+
+    pub fn growth_stage(self) -> Option<u8> {
+        self.get(BlockProperty::GrowthStage)
+    }
+
+    pub fn with_growth_stage(self, stage: u8) -> Self {
+        self.with(BlockProperty::GrowthStage, stage)
+    }
+
+    pub fn powered(self) -> Option<bool> {
+        self.get(BlockProperty::Powered).map(|value| value == 1)
+    }
+
+    pub fn with_powered(self, powered: bool) -> Self {
+        self.with(BlockProperty::Powered, powered as u8)
+    }
+
+    fn property_offset(self, property: BlockProperty) -> Option<u32> {
+        let mut offset = Self::ID_BITS;
+        for schema_property in self.info().properties() {
+            if *schema_property == property {
+                return Some(offset);
+            }
+            offset += schema_property.bit_width();
+        }
+        None
+    }
+}
+
+impl From<BlockType> for BlockState {
+    fn from(block_type: BlockType) -> Self {
+        Self::new(block_type as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_PROPERTIES: [BlockProperty; 5] = [
+        BlockProperty::Facing,
+        BlockProperty::Axis,
+        BlockProperty::FluidLevel,
+        BlockProperty::GrowthStage,
+        BlockProperty::Powered,
+    ];
+
+    #[test]
+    fn every_property_of_every_block_round_trips() {
+        for id in 0..BLOCK_COUNT as u16 {
+            let block = BlockState::new(id);
+            let schema = block.info().properties();
+            for property in ALL_PROPERTIES {
+                assert_eq!(block.has(property), schema.contains(&property));
+            }
+
+            // Every property gets its maximum, then each one is changed alone
+            let full = schema.iter().fold(block, |block, property| {
+                block.with(*property, property.max_value())
+            });
+            for property in schema {
+                for value in 0..=property.max_value() {
+                    let changed = full.with(*property, value);
+                    assert_eq!(changed.get(*property), Some(value));
+                    assert_eq!(changed.id(), id);
+                    for other in schema.iter().filter(|other| *other != property) {
+                        assert_eq!(changed.get(*other), Some(other.max_value()));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn metadata_does_not_change_the_id() {
+        let metadata = (1u64 << BlockState::METADATA_BITS) - 1;
+        let block = BlockState::from_raw((metadata << BlockState::ID_BITS) | 2);
+        assert_eq!(block.id(), 2);
+        assert_eq!(block.metadata(), metadata);
+        assert_eq!(BlockState::new(u16::MAX).metadata(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn setting_a_property_outside_the_schema_panics() {
+        BlockState::from(BlockType::STONE).with(BlockProperty::Powered, 1);
+    }
+}
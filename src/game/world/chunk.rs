@@ -1,18 +1,21 @@
-/// Block data stores as 3D array by yxz (height, x and z offset) and their u64 looks like:
-/// 48 bits - metatdata information
-/// 16 bits - block id
-pub type ChunkBlocks = [[[u64; Chunk::WIDTH]; Chunk::WIDTH]; Chunk::HEIGHT];
+/// Block data stores as 3D array by yxz (height, x and z offset), see `BlockState` for the layout
+/// of every block.
+pub type ChunkBlocks = [[[BlockState; Chunk::WIDTH]; Chunk::WIDTH]; Chunk::HEIGHT];
 
 pub struct Chunk {
     xoffset: f32,
     zoffset: f32,
 
-    /// Block data stores as 3D array by yxz (height, x and z offset) and their u64 looks like:
-    /// 48 bits - metatdata information
-    /// 16 bits - block id
+    /// Block data stores as 3D array by yxz (height, x and z offset), see `BlockState` for the
+    /// layout of every block.
     blocks: ChunkBlocks,
+    light: ChunkLight,
 }
 
+use super::block_state::BlockState;
+use super::light::{compute_chunk_light, ChunkLight};
+use crate::render::block::BlockType;
+
 use noise::*;
 use parking_lot::Mutex;
 
@@ -33,7 +36,7 @@ impl Chunk {
     //TODO:
     //This generation is temporary, need in future change to normal generation!
     pub fn create(xoffset: f32, zoffset: f32) -> Self {
-        let mut blocks = [[[0; Self::WIDTH]; Self::WIDTH]; Self::HEIGHT];
+        let mut blocks = [[[BlockState::AIR; Self::WIDTH]; Self::WIDTH]; Self::HEIGHT];
 
         let perlin = noise::Perlin::new(*SEED.lock());
         let xoffset_f64 = xoffset as f64;
@@ -49,7 +52,11 @@ impl Chunk {
 
                 for y in 0..Self::HEIGHT {
                     let floor = &mut blocks[y];
-                    floor[x][z] = if y < surface_height { 1 } else { 0 }
+                    floor[x][z] = if y < surface_height {
+                        BlockState::from(BlockType::STONE)
+                    } else {
+                        BlockState::AIR
+                    }
                 }
             }
         }
@@ -57,6 +64,7 @@ impl Chunk {
         Chunk {
             xoffset,
             zoffset,
+            light: compute_chunk_light(&blocks),
             blocks,
         }
     }
@@ -73,19 +81,37 @@ impl Chunk {
         &self.blocks
     }
 
-    pub fn mut_block_at(&mut self, x: usize, z: usize, y: usize) -> &mut u64 {
+    pub fn mut_block_at(&mut self, x: usize, z: usize, y: usize) -> &mut BlockState {
         &mut self.blocks[y][x][z]
     }
 
-    pub fn block_at(&self, x: usize, z: usize, y: usize) -> u64 {
+    pub fn block_at(&self, x: usize, z: usize, y: usize) -> BlockState {
         self.blocks[y][x][z]
     }
 
-    pub fn set_block_at(&mut self, x: usize, z: usize, y: usize, new_block: u64) {
+    pub fn set_block_at(&mut self, x: usize, z: usize, y: usize, new_block: BlockState) {
         self.blocks[y][x][z] = new_block;
     }
 
-    pub fn anticipated_block_at(x: usize, z: usize, y: usize, xoffset: f32, zoffset: f32) -> u64 {
+    pub fn light(&self) -> &ChunkLight {
+        &self.light
+    }
+
+    pub fn light_at(&self, x: usize, z: usize, y: usize) -> u8 {
+        self.light[y][x][z]
+    }
+
+    pub fn set_light_at(&mut self, x: usize, z: usize, y: usize, new_light: u8) {
+        self.light[y][x][z] = new_light;
+    }
+
+    pub fn anticipated_block_at(
+        x: usize,
+        z: usize,
+        y: usize,
+        xoffset: f32,
+        zoffset: f32,
+    ) -> BlockState {
         let perlin = noise::Perlin::new(*SEED.lock());
         let xoffset_f64 = xoffset as f64;
         let zoffset_f64 = zoffset as f64;
@@ -96,9 +122,9 @@ impl Chunk {
         let s_val = Self::SURFACE_LINE as f64 + (20.0 * val);
         let surface_height = s_val as usize;
         if y < surface_height {
-            1
+            BlockState::from(BlockType::STONE)
         } else {
-            0
+            BlockState::AIR
         }
     }
 }
@@ -109,6 +135,7 @@ impl Clone for Chunk {
             xoffset: self.xoffset,
             zoffset: self.zoffset,
             blocks: self.blocks.clone(),
+            light: self.light,
         }
     }
 }
@@ -134,77 +161,231 @@ macro_rules! foreach_block {
 
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufReader, Read, Write};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+const FILE_MAGIC: [u8; 4] = *b"T3DC";
+const FILE_VERSION: u16 = 1;
 
-fn read_from_file(
-    filepath: String,
+/// Reads all chunks of a file written by `ChunkWriter`, their light is computed again
+pub fn read_from_file(
+    filepath: &Path,
     blocksize: f32,
 ) -> std::io::Result<HashMap<(isize, isize), Chunk>> {
-    let file = File::open(filepath)?;
-    let mut reader = BufReader::new(file);
+    let mut reader = BufReader::new(File::open(filepath)?);
+    let mut header = [0; 6];
+    reader.read_exact(&mut header)?;
+    if header[..4] != FILE_MAGIC {
+        return Err(Error::new(ErrorKind::InvalidData, "not a chunk file"));
+    }
+    let version = u16::from_be_bytes([header[4], header[5]]);
+    if version != FILE_VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("unsupported chunk file version {}", version),
+        ));
+    }
+
     let mut chunks = HashMap::new();
-    let mut i_b = [0; std::mem::size_of::<isize>()];
-    while let Ok(n) = reader.read(&mut i_b) {
-        if n == 0 {
-            break;
+    let mut i_b = [0; std::mem::size_of::<i64>()];
+    loop {
+        match reader.read_exact(&mut i_b) {
+            Ok(()) => (),
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => break,
+            Err(error) => return Err(error),
         }
-        let mut j_b = [0; std::mem::size_of::<isize>()];
-        reader.read(&mut j_b)?;
+        let mut j_b = [0; std::mem::size_of::<i64>()];
+        reader.read_exact(&mut j_b)?;
 
-        let mut blocks = [[[0; Chunk::WIDTH]; Chunk::WIDTH]; Chunk::HEIGHT];
+        let mut blocks = [[[BlockState::AIR; Chunk::WIDTH]; Chunk::WIDTH]; Chunk::HEIGHT];
         let mut block = [0; std::mem::size_of::<u64>()];
 
-        for y in 0..Chunk::HEIGHT {
-            let floor = &mut blocks[y];
-
-            for x in 0..Chunk::WIDTH {
-                let zarray = &mut floor[x];
-
-                for z in 0..Chunk::WIDTH {
-                    reader.read(&mut block)?;
-                    zarray[z] = u64::from_be_bytes(block);
+        for floor in blocks.iter_mut() {
+            for zarray in floor.iter_mut() {
+                for cell in zarray.iter_mut() {
+                    reader.read_exact(&mut block)?;
+                    *cell = BlockState::from_raw(u64::from_be_bytes(block));
+                    if !cell.is_known() {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("unknown block id {}", cell.id()),
+                        ));
+                    }
                 }
             }
         }
 
-        let i = isize::from_be_bytes(i_b);
-        let j = isize::from_be_bytes(j_b);
-        let chunk = Chunk {
+        let i = i64::from_be_bytes(i_b) as isize;
+        let j = i64::from_be_bytes(j_b) as isize;
+        chunks.insert((i, j), Chunk::from_blocks(i, j, blocksize, blocks));
+    }
+    Ok(chunks)
+}
+
+impl Chunk {
+    /// Size of a run of equal blocks in `encode_blocks`: u32 count and u64 block
+    const RUN_SIZE: usize = 12;
+
+    /// Chunk with the given blocks at the position in the grid of chunks, its light is computed
+    fn from_blocks(i: isize, j: isize, blocksize: f32, blocks: ChunkBlocks) -> Self {
+        Self {
             xoffset: i as f32 * blocksize * Chunk::WIDTH as f32,
             zoffset: j as f32 * blocksize * Chunk::WIDTH as f32,
+            light: compute_chunk_light(&blocks),
             blocks,
-            // mesh: None,
-        };
-        chunks.insert((i, j), chunk);
+        }
     }
-    Ok(chunks)
-}
 
-fn write_to_file(
-    filepath: String,
-    // worldname: String,
-    chunks: &HashMap<(isize, isize), Chunk>,
-) -> std::io::Result<()> {
-    let mut file = File::create(filepath)?;
-    // file.write(worldname.as_bytes())?;
+    /// Blocks packed to be sent over the network, runs of equal blocks are stored once, so a
+    /// chunk takes a few kilobytes instead of the 442 KiB in the chunk file
+    pub fn encode_blocks(&self) -> Vec<u8> {
+        let mut data = vec![];
+        let mut push_run = |block: BlockState, count: u32| {
+            data.extend_from_slice(&count.to_be_bytes());
+            data.extend_from_slice(&block.raw().to_be_bytes());
+        };
+        let mut run: Option<(BlockState, u32)> = None;
+        for block in self.blocks.iter().flatten().flatten() {
+            run = match run {
+                Some((current, count)) if current == *block => Some((current, count + 1)),
+                Some((current, count)) => {
+                    push_run(current, count);
+                    Some((*block, 1))
+                }
+                None => Some((*block, 1)),
+            };
+        }
+        if let Some((block, count)) = run {
+            push_run(block, count);
+        }
+        data
+    }
 
-    for ((i, j), chunk) in chunks {
-        file.write(&i.to_be_bytes())?;
-        file.write(&j.to_be_bytes())?;
+    /// Reads blocks packed by `encode_blocks`, the light of the chunk is computed again
+    pub fn decode_blocks(i: isize, j: isize, blocksize: f32, data: &[u8]) -> std::io::Result<Self> {
+        let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message.to_string());
+        if !data.len().is_multiple_of(Self::RUN_SIZE) {
+            return Err(invalid("truncated chunk blocks"));
+        }
+        let mut blocks = [[[BlockState::AIR; Chunk::WIDTH]; Chunk::WIDTH]; Chunk::HEIGHT];
+        let mut cells = blocks.iter_mut().flatten().flatten();
+        for run in data.chunks_exact(Self::RUN_SIZE) {
+            let count = u32::from_be_bytes([run[0], run[1], run[2], run[3]]);
+            let mut raw = [0; std::mem::size_of::<u64>()];
+            raw.copy_from_slice(&run[4..]);
+            let block = BlockState::from_raw(u64::from_be_bytes(raw));
+            if !block.is_known() {
+                return Err(invalid("unknown block id in chunk blocks"));
+            }
+            for _ in 0..count {
+                let cell = cells
+                    .next()
+                    .ok_or_else(|| invalid("too many chunk blocks"))?;
+                *cell = block;
+            }
+        }
+        if cells.next().is_some() {
+            return Err(invalid("too few chunk blocks"));
+        }
+        Ok(Self::from_blocks(i, j, blocksize, blocks))
+    }
+}
 
-        let blocks = chunk.blocks();
+/// Writes chunks one by one, so they don't have to be in memory at once. The file is written
+/// next to the old one and replaces it in `finish`, an interrupted save keeps the old file.
+pub struct ChunkWriter {
+    writer: BufWriter<File>,
+    filepath: PathBuf,
+    temporary: PathBuf,
+}
 
-        for y in 0..Chunk::HEIGHT {
-            let floor = &blocks[y];
+impl ChunkWriter {
+    pub fn create(filepath: &Path) -> std::io::Result<Self> {
+        if let Some(directory) = filepath.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let temporary = filepath.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        writer.write_all(&FILE_MAGIC)?;
+        writer.write_all(&FILE_VERSION.to_be_bytes())?;
+        Ok(Self {
+            writer,
+            filepath: filepath.to_path_buf(),
+            temporary,
+        })
+    }
 
-            for x in 0..Chunk::WIDTH {
-                let zarray = &floor[x];
+    pub fn write(&mut self, i: isize, j: isize, chunk: &Chunk) -> std::io::Result<()> {
+        self.writer.write_all(&(i as i64).to_be_bytes())?;
+        self.writer.write_all(&(j as i64).to_be_bytes())?;
 
-                for z in 0..Chunk::WIDTH {
-                    file.write(&zarray[z].to_be_bytes())?;
+        for floor in chunk.blocks() {
+            for zarray in floor {
+                for block in zarray {
+                    self.writer.write_all(&block.raw().to_be_bytes())?;
                 }
             }
         }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> std::io::Result<()> {
+        self.writer.flush()?;
+        drop(self.writer);
+        std::fs::rename(&self.temporary, &self.filepath)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::world::run_with_big_stack;
+
+    #[test]
+    fn chunks_are_read_as_written() {
+        run_with_big_stack(|| {
+            let path = std::env::temp_dir().join(format!("t3d-chunks-{}.dat", std::process::id()));
+            let mut chunk = Chunk::create(16.0, -32.0);
+            chunk.set_block_at(3, 4, 150, BlockState::from(BlockType::GLASS));
+
+            let mut writer = ChunkWriter::create(&path).unwrap();
+            writer.write(1, -2, &chunk).unwrap();
+            writer.finish().unwrap();
+
+            let chunks = read_from_file(&path, 1.0).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(chunks.len(), 1);
+            let read = &chunks[&(1, -2)];
+            assert_eq!((read.xoffset(), read.zoffset()), (16.0, -32.0));
+            assert!(read.blocks() == chunk.blocks());
+
+            // A chunk file of another game or a newer version has blocks which this one doesn't know
+            chunk.set_block_at(0, 0, 0, BlockState::new(u16::MAX));
+            let mut writer = ChunkWriter::create(&path).unwrap();
+            writer.write(0, 0, &chunk).unwrap();
+            writer.finish().unwrap();
+            let read = read_from_file(&path, 1.0);
+            std::fs::remove_file(&path).unwrap();
+            assert!(matches!(read, Err(error) if error.kind() == ErrorKind::InvalidData));
+        });
+    }
+
+    #[test]
+    fn packed_blocks_are_unpacked_as_they_were() {
+        run_with_big_stack(|| {
+            let mut chunk = Chunk::create(-16.0, 0.0);
+            chunk.set_block_at(0, 15, 200, BlockState::from(BlockType::GLASS));
+            let data = chunk.encode_blocks();
+            assert!(data.len() < 64 * 1024);
+
+            let unpacked = Chunk::decode_blocks(-1, 0, 1.0, &data).unwrap();
+            assert_eq!((unpacked.xoffset(), unpacked.zoffset()), (-16.0, 0.0));
+            assert!(unpacked.blocks() == chunk.blocks());
+            assert!(Chunk::decode_blocks(0, 0, 1.0, &data[..data.len() - 12]).is_err());
+            assert!(Chunk::decode_blocks(0, 0, 1.0, &data[1..]).is_err());
+            let mut unknown = data.clone();
+            unknown[4..12].copy_from_slice(&u64::from(u16::MAX).to_be_bytes());
+            assert!(Chunk::decode_blocks(0, 0, 1.0, &unknown).is_err());
+        });
     }
-    Ok(())
 }
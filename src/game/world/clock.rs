@@ -0,0 +1,166 @@
+use nalgebra_glm::{mix, vec3, Vec3};
+
+/// Time of the day in the world, measured as a part of the full day:
+/// 0.0  - sunrise
+/// 0.25 - noon
+/// 0.5  - sunset
+/// 0.75 - midnight
+pub struct WorldClock {
+    time_of_day: f32,
+    day_length: f32,
+    frozen: bool,
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WorldClock {
+    pub const SUNRISE: f32 = 0.0;
+    pub const NOON: f32 = 0.25;
+    pub const SUNSET: f32 = 0.5;
+    pub const MIDNIGHT: f32 = 0.75;
+
+    /// Day length in seconds
+    pub const DEFAULT_DAY_LENGTH: f32 = 1200.0;
+    const DEFAULT_TIME: f32 = 0.05;
+
+    const DAY_SKY: [f32; 3] = [0.45, 0.65, 0.95];
+    const NIGHT_SKY: [f32; 3] = [0.02, 0.02, 0.06];
+    const DAWN_SKY: [f32; 3] = [0.85, 0.5, 0.3];
+    const DAY_ZENITH: [f32; 3] = [0.2, 0.4, 0.85];
+    const NIGHT_ZENITH: [f32; 3] = [0.0, 0.0, 0.02];
+    const DAY_CLOUDS: [f32; 3] = [1.0, 1.0, 1.0];
+    const NIGHT_CLOUDS: [f32; 3] = [0.08, 0.08, 0.12];
+
+    const MIN_SKYLIGHT_INTENSITY: f32 = 0.15;
+
+    pub fn new() -> Self {
+        Self {
+            time_of_day: Self::DEFAULT_TIME,
+            day_length: Self::DEFAULT_DAY_LENGTH,
+            frozen: false,
+        }
+    }
+
+    pub fn advance(&mut self, delta_time: f32) {
+        if self.frozen {
+            return;
+        }
+        self.time_of_day = (self.time_of_day + delta_time / self.day_length).rem_euclid(1.0);
+    }
+
+    pub fn time_of_day(&self) -> f32 {
+        self.time_of_day
+    }
+
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        self.time_of_day = time_of_day.rem_euclid(1.0);
+    }
+
+    pub fn day_length(&self) -> f32 {
+        self.day_length
+    }
+
+    pub fn set_day_length(&mut self, seconds: f32) {
+        assert!(seconds > 0.0);
+        self.day_length = seconds;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    /// Angle of the sun over the eastern horizon in radians
+    pub fn sun_angle(&self) -> f32 {
+        self.time_of_day * std::f32::consts::TAU
+    }
+
+    /// Direction from the world to the sun, the sun rises in the east and goes over the south
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = self.sun_angle();
+        vec3(angle.cos(), angle.sin(), 0.2).normalize()
+    }
+
+    pub fn moon_direction(&self) -> Vec3 {
+        -self.sun_direction()
+    }
+
+    /// Part of the daylight from 0 at night to 1 at day, it changes while the sun is low
+    fn daylight(&self) -> f32 {
+        (0.5 + self.sun_angle().sin() * 2.0).clamp(0.0, 1.0)
+    }
+
+    /// How much skylight is visible, from `MIN_SKYLIGHT_INTENSITY` at night to 1 at day
+    pub fn skylight_intensity(&self) -> f32 {
+        self.daylight().max(Self::MIN_SKYLIGHT_INTENSITY)
+    }
+
+    /// Color of the sky near the horizon
+    pub fn sky_color(&self) -> Vec3 {
+        let height = self.sun_angle().sin();
+        let color = mix(
+            &Vec3::from(Self::NIGHT_SKY),
+            &Vec3::from(Self::DAY_SKY),
+            self.daylight(),
+        );
+        // Sunrise and sunset paint the sky while the sun is close to the horizon
+        let glow = (1.0 - height.abs() * 4.0).clamp(0.0, 1.0) * 0.6;
+        mix(&color, &Vec3::from(Self::DAWN_SKY), glow)
+    }
+
+    /// Color of the sky right above the player
+    pub fn zenith_color(&self) -> Vec3 {
+        mix(
+            &Vec3::from(Self::NIGHT_ZENITH),
+            &Vec3::from(Self::DAY_ZENITH),
+            self.daylight(),
+        )
+    }
+
+    pub fn cloud_color(&self) -> Vec3 {
+        mix(
+            &Vec3::from(Self::NIGHT_CLOUDS),
+            &Vec3::from(Self::DAY_CLOUDS),
+            self.daylight(),
+        )
+    }
+
+    /// Fog blends chunks into the sky, so it has the same color
+    pub fn fog_color(&self) -> Vec3 {
+        self.sky_color()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_advances_wraps_and_freezes() {
+        let mut clock = WorldClock::new();
+        clock.set_day_length(100.0);
+        clock.set_time_of_day(WorldClock::SUNRISE);
+
+        clock.advance(25.0);
+        assert_eq!(clock.time_of_day(), WorldClock::NOON);
+        clock.advance(80.0);
+        assert!((clock.time_of_day() - 0.05).abs() < 1e-5);
+
+        clock.set_frozen(true);
+        clock.advance(30.0);
+        assert!((clock.time_of_day() - 0.05).abs() < 1e-5);
+        clock.set_frozen(false);
+        clock.advance(20.0);
+        assert!((clock.time_of_day() - WorldClock::NOON).abs() < 1e-5);
+
+        clock.set_time_of_day(-0.25);
+        assert_eq!(clock.time_of_day(), WorldClock::MIDNIGHT);
+    }
+}
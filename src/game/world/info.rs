@@ -0,0 +1,187 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Directory where every world has its own subdirectory
+pub const WORLDS_DIRECTORY: &str = "data/worlds";
+
+/// Name and seed of a saved world, stored as `world.toml` in the directory of the world
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct WorldInfo {
+    name: String,
+    seed: u32,
+    #[serde(skip)]
+    directory: PathBuf,
+}
+
+impl WorldInfo {
+    const INFO_FILE: &'static str = "world.toml";
+    const PLAYER_FILE: &'static str = "player.dat";
+    const CHUNKS_FILE: &'static str = "chunks.dat";
+
+    /// Creates the directory of a new world in `worlds`, the directory is named after the world
+    /// and gets a number if a world with a similar name already exists
+    pub fn create(worlds: &Path, name: &str, seed: u32) -> std::io::Result<Self> {
+        let base = Self::directory_name(name);
+        let mut directory = worlds.join(&base);
+        let mut copy = 1;
+        while directory.exists() {
+            copy += 1;
+            directory = worlds.join(format!("{}-{}", base, copy));
+        }
+        Self::create_at(&directory, name, seed)
+    }
+
+    /// Creates a new world in exactly this directory
+    pub fn create_at(directory: &Path, name: &str, seed: u32) -> std::io::Result<Self> {
+        std::fs::create_dir_all(directory)?;
+        let info = Self {
+            name: String::from(name.trim()),
+            seed,
+            directory: directory.to_path_buf(),
+        };
+        info.save()?;
+        Ok(info)
+    }
+
+    /// World of a server, it has no directory because the server keeps it
+    pub fn remote(name: &str, seed: u32) -> Self {
+        Self {
+            name: String::from(name),
+            seed,
+            directory: PathBuf::new(),
+        }
+    }
+
+    pub fn load(directory: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(directory.join(Self::INFO_FILE))?;
+        let mut info: Self = toml::from_str(&text)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+        info.directory = directory.to_path_buf();
+        Ok(info)
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let text = toml::to_string(self)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+        std::fs::write(self.directory.join(Self::INFO_FILE), text)
+    }
+
+    /// Worlds saved in `worlds` sorted by name, directories without a valid info are skipped
+    pub fn list(worlds: &Path) -> Vec<Self> {
+        let Ok(entries) = std::fs::read_dir(worlds) else {
+            return vec![];
+        };
+        let mut infos = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| Self::load(&entry.path()).ok())
+            .collect::<Vec<Self>>();
+        infos.sort_by_key(|info| (info.name.to_lowercase(), info.directory.clone()));
+        infos
+    }
+
+    fn directory_name(name: &str) -> String {
+        let name = name
+            .trim()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        if name.is_empty() {
+            String::from("world")
+        } else {
+            name
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn player_file(&self) -> PathBuf {
+        self.directory.join(Self::PLAYER_FILE)
+    }
+
+    pub fn chunks_file(&self) -> PathBuf {
+        self.directory.join(Self::CHUNKS_FILE)
+    }
+}
+
+/// Seed typed by the player: numbers are used as they are, other text is hashed
+pub fn parse_seed(text: &str) -> u32 {
+    let text = text.trim();
+    if let Ok(seed) = text.parse::<u32>() {
+        return seed;
+    }
+    if let Ok(seed) = text.parse::<i64>() {
+        return seed as u32;
+    }
+    // FNV-1a, so the same text gives the same world on every platform and build
+    text.bytes().fold(0x811c9dc5, |hash: u32, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+/// Seed for a world created without one
+pub fn random_seed() -> u32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.subsec_nanos() ^ time.as_secs() as u32)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_are_numbers_or_hashed_text() {
+        assert_eq!(parse_seed("42"), 42);
+        assert_eq!(parse_seed(" 42 "), 42);
+        assert_eq!(parse_seed("-1"), u32::MAX);
+        assert_eq!(parse_seed("terraria"), parse_seed("terraria"));
+        assert_ne!(parse_seed("terraria"), parse_seed("Terraria"));
+    }
+
+    #[test]
+    fn created_worlds_are_listed_by_name() {
+        let worlds = std::env::temp_dir().join(format!("t3d-worlds-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&worlds);
+
+        let second = WorldInfo::create(&worlds, "Zeta", 2).unwrap();
+        let first = WorldInfo::create(&worlds, "alpha world", 1).unwrap();
+        let copy = WorldInfo::create(&worlds, "alpha world", 3).unwrap();
+        assert_ne!(first.directory(), copy.directory());
+        assert!(first.directory().ends_with("alpha_world"));
+
+        let names = WorldInfo::list(&worlds)
+            .iter()
+            .map(|info| (info.name().to_string(), info.seed()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                (String::from("alpha world"), 1),
+                (String::from("alpha world"), 3),
+                (String::from("Zeta"), 2)
+            ]
+        );
+        assert_eq!(WorldInfo::load(second.directory()).unwrap(), second);
+
+        std::fs::remove_dir_all(&worlds).unwrap();
+    }
+}
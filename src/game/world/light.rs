@@ -0,0 +1,428 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use super::block_state::BlockState;
+use super::chunk::{Chunk, ChunkBlocks};
+use crate::foreach_block;
+use crate::game::storage::STORAGE;
+use crate::render::block::Transparency;
+use crate::render::mesh::RenderPosition;
+
+pub const MAX_LIGHT: u8 = 15;
+
+/// Light data stores as 3D array by yxz like blocks, and their u8 looks like:
+/// 4 bits - skylight level
+/// 4 bits - block light level
+pub type ChunkLight = [[[u8; Chunk::WIDTH]; Chunk::WIDTH]; Chunk::HEIGHT];
+
+/// Light value of the cell which is open to the sky and has no light sources around
+pub const FULL_SKYLIGHT: u8 = MAX_LIGHT << 4;
+
+/// Position of the block in the world, measured in blocks
+pub type LightPosition = (isize, isize, isize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LightKind {
+    Sky,
+    Block,
+}
+
+impl LightKind {
+    pub const ALL: [LightKind; 2] = [LightKind::Sky, LightKind::Block];
+
+    pub fn get(self, light: u8) -> u8 {
+        match self {
+            LightKind::Sky => light >> 4,
+            LightKind::Block => light & 0x0F,
+        }
+    }
+
+    pub fn set(self, light: u8, level: u8) -> u8 {
+        match self {
+            LightKind::Sky => (light & 0x0F) | (level << 4),
+            LightKind::Block => (light & 0xF0) | level,
+        }
+    }
+
+    /// Level of light after one step in the direction. Skylight goes straight down without fading.
+    fn next_level(self, level: u8, direction: RenderPosition) -> u8 {
+        if self == LightKind::Sky && direction == RenderPosition::BOTTOM && level == MAX_LIGHT {
+            MAX_LIGHT
+        } else {
+            level.saturating_sub(1)
+        }
+    }
+}
+
+pub fn passes_light(block: BlockState) -> bool {
+    block.info().transparency() != Transparency::Opaque
+}
+
+/// Computes light of the chunk as if it has no neighbours, it's enough to be rendered right after
+/// generation. Light is connected with neighbours by `LightPropagator::stitch_chunk`.
+pub fn compute_chunk_light(blocks: &ChunkBlocks) -> ChunkLight {
+    let mut light = [[[0; Chunk::WIDTH]; Chunk::WIDTH]; Chunk::HEIGHT];
+    let mut queue = VecDeque::new();
+
+    for x in 0..Chunk::WIDTH {
+        for z in 0..Chunk::WIDTH {
+            for y in (0..Chunk::HEIGHT).rev() {
+                if !passes_light(blocks[y][x][z]) {
+                    break;
+                }
+                light[y][x][z] = FULL_SKYLIGHT;
+                queue.push_back((LightKind::Sky, x, y, z));
+            }
+        }
+    }
+
+    foreach_block! {
+        (y; x; z) {
+            let emission = blocks[y][x][z].info().light_emission();
+            if emission > 0 {
+                light[y][x][z] = LightKind::Block.set(light[y][x][z], emission);
+                queue.push_back((LightKind::Block, x, y, z));
+            }
+        }
+    };
+
+    while let Some((kind, x, y, z)) = queue.pop_front() {
+        let level = kind.get(light[y][x][z]);
+        for direction in RenderPosition::ALL {
+            let (dx, dy, dz) = direction.offset();
+            let (nx, ny, nz) = (x as isize + dx, y as isize + dy, z as isize + dz);
+            if !(0..Chunk::WIDTH_ISIZE).contains(&nx)
+                || !(0..Chunk::HEIGHT as isize).contains(&ny)
+                || !(0..Chunk::WIDTH_ISIZE).contains(&nz)
+            {
+                continue;
+            }
+            let (nx, ny, nz) = (nx as usize, ny as usize, nz as usize);
+            if !passes_light(blocks[ny][nx][nz]) {
+                continue;
+            }
+
+            let new_level = kind.next_level(level, direction);
+            if kind.get(light[ny][nx][nz]) < new_level {
+                light[ny][nx][nz] = kind.set(light[ny][nx][nz], new_level);
+                queue.push_back((kind, nx, ny, nz));
+            }
+        }
+    }
+    light
+}
+
+/// Chunk, its offsets in the storage and coordinates of the cell inside of it
+type LocatedCell = (Arc<Mutex<Chunk>>, (isize, isize), (usize, usize, usize));
+
+/// Spreads and removes light through all loaded chunks of the storage. Every chunk is locked only
+/// while a single cell is read or written, so the caller must not hold any chunk lock or the
+/// storage lock.
+#[derive(Default)]
+pub struct LightPropagator {
+    chunks: HashMap<(isize, isize), Option<Arc<Mutex<Chunk>>>>,
+    touched: HashSet<(isize, isize)>,
+}
+
+impl LightPropagator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Chunks which light was changed, their meshes must be rebuilt
+    pub fn touched_chunks(&self) -> &HashSet<(isize, isize)> {
+        &self.touched
+    }
+
+    /// Updates light after the block at the position was replaced by the new one.
+    pub fn block_changed(&mut self, position: LightPosition, new_block: BlockState) {
+        for kind in LightKind::ALL {
+            let mut queue = VecDeque::new();
+            let level = self.level(kind, position).unwrap_or(0);
+            self.remove(kind, position, level, &mut queue);
+
+            let emission = new_block.info().light_emission();
+            if kind == LightKind::Block && emission > 0 {
+                self.set_level(kind, position, emission);
+                queue.push_back(position);
+            }
+            if passes_light(new_block) {
+                for direction in RenderPosition::ALL {
+                    queue.push_back(Self::neighbour(position, direction));
+                }
+            }
+            self.propagate(kind, queue);
+        }
+    }
+
+    /// Lets light flow over the borders of the chunk in both directions. Only neighbours which
+    /// are already loaded take part.
+    pub fn stitch_chunk(&mut self, xoffset: isize, zoffset: isize) {
+        let Some(chunk) = self.chunk(xoffset, zoffset) else {
+            return;
+        };
+        let xstart = xoffset * Chunk::WIDTH_ISIZE;
+        let zstart = zoffset * Chunk::WIDTH_ISIZE;
+        let last = Chunk::WIDTH_ISIZE - 1;
+
+        // Inside and outside cells of every border, relative to the chunk
+        let borders = [
+            (RenderPosition::WEST, (0, 0), (0, 1)),
+            (RenderPosition::EAST, (last, 0), (0, 1)),
+            (RenderPosition::NORTH, (0, 0), (1, 0)),
+            (RenderPosition::SOUTH, (0, last), (1, 0)),
+        ];
+        for (direction, (xfirst, zfirst), (xstep, zstep)) in borders {
+            let (dx, _, dz) = direction.offset();
+            let Some(neighbour) = self.chunk(xoffset + dx, zoffset + dz) else {
+                continue;
+            };
+
+            let cells = (0..Chunk::WIDTH_ISIZE)
+                .map(|i| (xfirst + i * xstep, zfirst + i * zstep))
+                .collect::<Vec<(isize, isize)>>();
+            let inside = Self::copy_light(&chunk, &cells, (0, 0));
+            let outside = Self::copy_light(&neighbour, &cells, (-dx * last, -dz * last));
+
+            for kind in LightKind::ALL {
+                let mut queue = VecDeque::new();
+                for (i, (x, z)) in cells.iter().enumerate() {
+                    for y in 0..Chunk::HEIGHT {
+                        let inside_level = kind.get(inside[i][y]);
+                        let outside_level = kind.get(outside[i][y]);
+                        let position = (xstart + x, y as isize, zstart + z);
+                        if inside_level > outside_level + 1 {
+                            queue.push_back(position);
+                        } else if outside_level > inside_level + 1 {
+                            queue.push_back(Self::neighbour(position, direction));
+                        }
+                    }
+                }
+                self.propagate(kind, queue);
+            }
+        }
+    }
+
+    fn copy_light(
+        chunk: &Arc<Mutex<Chunk>>,
+        cells: &[(isize, isize)],
+        shift: (isize, isize),
+    ) -> Vec<[u8; Chunk::HEIGHT]> {
+        let chunk = chunk.lock();
+        cells
+            .iter()
+            .map(|(x, z)| {
+                let (x, z) = ((x + shift.0) as usize, (z + shift.1) as usize);
+                let mut column = [0; Chunk::HEIGHT];
+                for (y, light) in column.iter_mut().enumerate() {
+                    *light = chunk.light_at(x, z, y);
+                }
+                column
+            })
+            .collect()
+    }
+
+    fn remove(
+        &mut self,
+        kind: LightKind,
+        position: LightPosition,
+        level: u8,
+        relight: &mut VecDeque<LightPosition>,
+    ) {
+        if level == 0 {
+            return;
+        }
+        self.set_level(kind, position, 0);
+
+        let mut queue = VecDeque::from([(position, level)]);
+        while let Some((position, level)) = queue.pop_front() {
+            for direction in RenderPosition::ALL {
+                let neighbour = Self::neighbour(position, direction);
+                let Some(neighbour_level) = self.level(kind, neighbour) else {
+                    continue;
+                };
+                if neighbour_level == 0 {
+                    continue;
+                }
+
+                let lit_by_removed = neighbour_level < level
+                    || (kind.next_level(level, direction) == MAX_LIGHT
+                        && neighbour_level == MAX_LIGHT);
+                if !lit_by_removed {
+                    relight.push_back(neighbour);
+                    continue;
+                }
+
+                self.set_level(kind, neighbour, 0);
+                queue.push_back((neighbour, neighbour_level));
+
+                let emission = self
+                    .block(neighbour)
+                    .map_or(0, |block| block.info().light_emission());
+                if kind == LightKind::Block && emission > 0 {
+                    self.set_level(kind, neighbour, emission);
+                    relight.push_back(neighbour);
+                }
+            }
+        }
+    }
+
+    fn propagate(&mut self, kind: LightKind, mut queue: VecDeque<LightPosition>) {
+        while let Some(position) = queue.pop_front() {
+            let level = match self.level(kind, position) {
+                Some(level) if level > 0 => level,
+                _ => continue,
+            };
+            for direction in RenderPosition::ALL {
+                let neighbour = Self::neighbour(position, direction);
+                match self.block(neighbour) {
+                    Some(block) if passes_light(block) => (),
+                    _ => continue,
+                }
+
+                let new_level = kind.next_level(level, direction);
+                if self.level(kind, neighbour).unwrap_or(MAX_LIGHT) < new_level {
+                    self.set_level(kind, neighbour, new_level);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+
+    fn neighbour(position: LightPosition, direction: RenderPosition) -> LightPosition {
+        let (dx, dy, dz) = direction.offset();
+        (position.0 + dx, position.1 + dy, position.2 + dz)
+    }
+
+    fn chunk(&mut self, xoffset: isize, zoffset: isize) -> Option<Arc<Mutex<Chunk>>> {
+        self.chunks
+            .entry((xoffset, zoffset))
+            .or_insert_with(|| STORAGE.lock().chunk(xoffset, zoffset))
+            .clone()
+    }
+
+    /// Finds the chunk and coordinates inside of it, `None` if the chunk is not loaded
+    fn locate(&mut self, position: LightPosition) -> Option<LocatedCell> {
+        let (x, y, z) = position;
+        if !(0..Chunk::HEIGHT as isize).contains(&y) {
+            return None;
+        }
+        let key = (
+            x.div_euclid(Chunk::WIDTH_ISIZE),
+            z.div_euclid(Chunk::WIDTH_ISIZE),
+        );
+        let chunk = self.chunk(key.0, key.1)?;
+        let inner = (
+            x.rem_euclid(Chunk::WIDTH_ISIZE) as usize,
+            y as usize,
+            z.rem_euclid(Chunk::WIDTH_ISIZE) as usize,
+        );
+        Some((chunk, key, inner))
+    }
+
+    fn block(&mut self, position: LightPosition) -> Option<BlockState> {
+        let (chunk, _, (x, y, z)) = self.locate(position)?;
+        let block = chunk.lock().block_at(x, z, y);
+        Some(block)
+    }
+
+    fn level(&mut self, kind: LightKind, position: LightPosition) -> Option<u8> {
+        let (chunk, _, (x, y, z)) = self.locate(position)?;
+        let light = chunk.lock().light_at(x, z, y);
+        Some(kind.get(light))
+    }
+
+    fn set_level(&mut self, kind: LightKind, position: LightPosition, level: u8) {
+        if let Some((chunk, key, (x, y, z))) = self.locate(position) {
+            let mut chunk = chunk.lock();
+            let light = chunk.light_at(x, z, y);
+            chunk.set_light_at(x, z, y, kind.set(light, level));
+            self.touched.insert(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::world::run_with_big_stack;
+    use crate::render::block::BlockType;
+
+    fn empty_blocks() -> ChunkBlocks {
+        [[[BlockState::AIR; Chunk::WIDTH]; Chunk::WIDTH]; Chunk::HEIGHT]
+    }
+
+    #[test]
+    fn skylight_falls_down_until_a_block_stops_it() {
+        run_with_big_stack(|| {
+            // A roof over the whole chunk with a single hole in it
+            let mut blocks = empty_blocks();
+            for row in blocks[100].iter_mut() {
+                row.fill(BlockState::from(BlockType::STONE));
+            }
+            blocks[100][3][3] = BlockState::AIR;
+
+            let light = compute_chunk_light(&blocks);
+            let sky = |x: usize, y: usize, z: usize| LightKind::Sky.get(light[y][x][z]);
+            assert_eq!(sky(12, 150, 12), MAX_LIGHT);
+            assert_eq!(sky(3, 20, 3), MAX_LIGHT);
+            assert_eq!(sky(4, 20, 3), MAX_LIGHT - 1);
+            assert_eq!(sky(3, 20, 6), MAX_LIGHT - 3);
+            assert_eq!(sky(12, 99, 12), 0);
+        });
+    }
+
+    #[test]
+    fn lamp_lights_its_surroundings_with_falloff() {
+        run_with_big_stack(|| {
+            let lamp = BlockState::from(BlockType::LAMP);
+            let emission = lamp.info().light_emission();
+            let mut blocks = empty_blocks();
+            blocks[50][8][8] = lamp;
+
+            let light = compute_chunk_light(&blocks);
+            let block = |x: usize, y: usize, z: usize| LightKind::Block.get(light[y][x][z]);
+            assert_eq!(block(8, 50, 8), emission);
+            assert_eq!(block(8, 51, 8), emission - 1);
+            assert_eq!(block(8, 50, 11), emission - 3);
+            assert_eq!(block(10, 48, 9), emission - 5);
+            assert_eq!(block(8, 50 + emission as usize, 8), 0);
+        });
+    }
+
+    #[test]
+    fn removed_lamp_takes_its_light_away() {
+        run_with_big_stack(|| {
+            let _storage = crate::game::storage::TEST_LOCK.lock();
+            let (xoffset, zoffset) = (1000, -1000);
+            let width = Chunk::WIDTH as f32;
+            let chunk = Chunk::create(xoffset as f32 * width, zoffset as f32 * width);
+            STORAGE.lock().store_chunk(xoffset, zoffset, chunk);
+            let x = xoffset * Chunk::WIDTH_ISIZE + 8;
+            let z = zoffset * Chunk::WIDTH_ISIZE + 8;
+            let set_block = |block: BlockState| {
+                let chunk = STORAGE.lock().chunk(xoffset, zoffset).unwrap();
+                chunk.lock().set_block_at(8, 8, 200, block);
+                LightPropagator::new().block_changed((x, 200, z), block);
+            };
+            let block_light = |dx: isize| {
+                let chunk = STORAGE.lock().chunk(xoffset, zoffset).unwrap();
+                let light = chunk.lock().light_at((8 + dx) as usize, 8, 200);
+                LightKind::Block.get(light)
+            };
+
+            let lamp = BlockState::from(BlockType::LAMP);
+            let emission = lamp.info().light_emission();
+            set_block(lamp);
+            assert_eq!(block_light(0), emission);
+            assert_eq!(block_light(2), emission - 2);
+
+            set_block(BlockState::AIR);
+            let remaining = (-8..8).map(block_light).collect::<Vec<u8>>();
+            STORAGE.lock().clear();
+            assert_eq!(remaining, vec![0; Chunk::WIDTH]);
+        });
+    }
+}
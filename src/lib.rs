@@ -0,0 +1,18 @@
+extern crate gl;
+#[cfg(feature = "client")]
+extern crate sdl2;
+
+#[cfg(feature = "client")]
+pub mod window;
+
+pub mod settings;
+
+pub mod game;
+
+pub mod cli;
+
+pub mod server;
+
+pub mod net;
+
+mod render;
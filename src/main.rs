@@ -1,49 +1,132 @@
-extern crate gl;
-extern crate sdl2;
+use terraria3d_rs::cli::Options;
+use terraria3d_rs::game::remote::DEFAULT_NAME;
+use terraria3d_rs::game::world::info::{random_seed, WorldInfo, WORLDS_DIRECTORY};
+use terraria3d_rs::game::world::World;
+use terraria3d_rs::game::*;
+use terraria3d_rs::server::Server;
+use terraria3d_rs::settings::{Settings, SETTINGS_FILE};
+use terraria3d_rs::window::*;
 
-pub mod window;
-use window::*;
+use sdl2::event::WindowEvent;
 
-pub mod game;
-use game::*;
-
-mod render;
-
-fn run() {
-    let window = Window::from("Terraria 3D", Window::WIDTH, Window::HEIGHT)
+fn run(options: Options) {
+    let settings = Settings::load(SETTINGS_FILE).unwrap_or_else(|error| {
+        eprintln!(
+            "Settings cannot be loaded, the defaults are used: {}",
+            error
+        );
+        Settings::default()
+    });
+    let settings = options.apply(&settings);
+    let mut window = Window::from("Terraria 3D", settings.window_width, settings.window_height)
         .expect("Window cannot be initialized!");
+    if let Err(error) = window.apply_settings(&settings) {
+        eprintln!("Window settings cannot be applied: {}", error);
+    }
     let mut events = window
         .event_pump()
         .expect("Must be initialized sdl for pumping event!");
 
-    let mut game = Game::init(&window).expect("Game cannot be initialized!");
+    let mut game = Game::init(&window, settings).expect("Game cannot be initialized!");
+    match options.world_info() {
+        Ok(Some(info)) => game.open_world(info),
+        Ok(None) => (),
+        Err(error) => eprintln!("World cannot be opened: {}", error),
+    }
+    if let Some(address) = &options.connect {
+        game.join_server(address, options.name.as_deref().unwrap_or(DEFAULT_NAME));
+    }
 
-    'mainloop: loop {
+    'mainloop: while game.is_running() {
         game.new_loop();
 
         while let Some(event) = events.poll_event() {
             match event {
                 sdl2::event::Event::Quit { .. } => break 'mainloop,
+                sdl2::event::Event::Window {
+                    win_event: WindowEvent::Resized(..) | WindowEvent::SizeChanged(..),
+                    ..
+                } => {
+                    window.update_viewport();
+                    game.resize(window.width(), window.height());
+                }
                 other => game.handle(other),
             }
         }
 
         game.update();
+        if let Some(settings) = game.take_window_settings() {
+            if let Err(error) = window.apply_settings(&settings) {
+                eprintln!("Window settings cannot be applied: {}", error);
+            }
+        }
         game.render();
-        println!("Current FPS: {}", game.fps());
+        game.limit_frame_rate();
 
         window.update();
     }
+    game.close();
+}
+
+/// Generates chunks of the world without a window, a new world is created if none is given
+fn generate(options: &Options, radius: usize) -> std::io::Result<()> {
+    let info = match options.world_info()? {
+        Some(info) => info,
+        None => WorldInfo::create(
+            std::path::Path::new(WORLDS_DIRECTORY),
+            "New World",
+            random_seed(),
+        )?,
+    };
+    let count = World::generate(&info, DEFAULT_BLOCK_SIZE, radius)?;
+    println!(
+        "{} chunks of {:?} with seed {} are saved in {}",
+        count,
+        info.name(),
+        info.seed(),
+        info.directory().display()
+    );
+    Ok(())
+}
+
+fn start(options: Options) {
+    match options.generate_only {
+        Some(radius) => {
+            if let Err(error) = generate(&options, radius) {
+                eprintln!("World cannot be generated: {}", error);
+                std::process::exit(1);
+            }
+        }
+        None if options.headless => {
+            if let Err(error) = Server::host(&options) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        }
+        None => run(options),
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, Options::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", Options::USAGE);
+        return Ok(());
+    }
+
     if cfg!(target_os = "macos") {
-        run();
+        start(options);
     } else {
         std::thread::Builder::new()
             .stack_size(32 * 1024 * 1024)
             .name(String::from("Main"))
-            .spawn(run)
+            .spawn(move || start(options))
             .unwrap()
             .join()
             .unwrap();
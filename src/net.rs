@@ -0,0 +1,63 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+pub mod protocol;
+
+pub mod client;
+
+pub mod server;
+
+/// Port of the server when the address has none
+pub const DEFAULT_PORT: u16 = 7777;
+
+/// A peer which doesn't read its messages for this long is disconnected
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the threads which read datagrams check whether their socket is closed
+const UDP_READ_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Address of the server with the default port if the text has no port, e.g. "192.168.1.5"
+pub fn server_address(text: &str) -> String {
+    let text = text.trim();
+    let has_port = match text.rsplit_once(':') {
+        // An IPv6 address has colons too, a port follows its closing bracket
+        Some((host, port)) => {
+            port.parse::<u16>().is_ok() && (!host.contains(':') || host.ends_with(']'))
+        }
+        None => false,
+    };
+    if has_port {
+        String::from(text)
+    } else if text.parse::<Ipv6Addr>().is_ok() {
+        format!("[{}]:{}", text, DEFAULT_PORT)
+    } else {
+        format!("{}:{}", text, DEFAULT_PORT)
+    }
+}
+
+/// Address of the same port on this computer when the socket listens on every interface
+fn loopback(address: SocketAddr) -> SocketAddr {
+    let ip = match address.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ip => ip,
+    };
+    SocketAddr::new(ip, address.port())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_port_is_added_to_addresses_without_one() {
+        assert_eq!(server_address("192.168.1.5"), "192.168.1.5:7777");
+        assert_eq!(server_address("localhost:4000"), "localhost:4000");
+        assert_eq!(server_address("::1"), "[::1]:7777");
+        assert_eq!(server_address("[::1]:4000"), "[::1]:4000");
+        assert_eq!(
+            loopback("0.0.0.0:4000".parse().unwrap()),
+            "127.0.0.1:4000".parse().unwrap()
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::BufReader;
+use std::net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+
+use super::protocol::{
+    decode_datagram, encode_datagram, read_message, write_message, Message, MAX_DATAGRAM_SIZE,
+    PROTOCOL_VERSION,
+};
+use super::{UDP_READ_TIMEOUT, WRITE_TIMEOUT};
+
+/// Client side of the connection with a server. The messages of the server are read by
+/// threads and collected by `poll`.
+pub struct NetClient {
+    stream: TcpStream,
+    udp: UdpSocket,
+    server: SocketAddr,
+    /// Id which the server has given in `Welcome`
+    player_id: Option<u32>,
+    receiver: Receiver<Message>,
+    closed: Arc<AtomicBool>,
+}
+
+impl NetClient {
+    /// Connects to the server and says hello, the answer comes from `poll`
+    pub fn connect(address: impl ToSocketAddrs, name: &str) -> std::io::Result<Self> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let server = stream.peer_addr()?;
+        let udp = UdpSocket::bind(SocketAddr::new(stream.local_addr()?.ip(), 0))?;
+        udp.set_read_timeout(Some(UDP_READ_TIMEOUT))?;
+
+        let hello = Message::Hello {
+            version: PROTOCOL_VERSION,
+            name: String::from(name),
+        };
+        write_message(&mut stream, &hello)?;
+
+        let (sender, receiver) = channel();
+        let closed = Arc::new(AtomicBool::new(false));
+        Self::spawn_reader(stream.try_clone()?, sender.clone());
+        Self::spawn_udp_reader(udp.try_clone()?, server, sender, closed.clone());
+        Ok(Self {
+            stream,
+            udp,
+            server,
+            player_id: None,
+            receiver,
+            closed,
+        })
+    }
+
+    fn spawn_reader(stream: TcpStream, sender: Sender<Message>) {
+        std::thread::Builder::new()
+            .name(String::from("Connection"))
+            .spawn(move || {
+                let mut reader = BufReader::new(stream);
+                loop {
+                    let message =
+                        read_message(&mut reader).unwrap_or_else(|error| Message::Disconnect {
+                            reason: format!("Connection is lost: {}", error),
+                        });
+                    let last = matches!(message, Message::Disconnect { .. });
+                    if sender.send(message).is_err() || last {
+                        break;
+                    }
+                }
+            })
+            .unwrap();
+    }
+
+    fn spawn_udp_reader(
+        udp: UdpSocket,
+        server: SocketAddr,
+        sender: Sender<Message>,
+        closed: Arc<AtomicBool>,
+    ) {
+        std::thread::Builder::new()
+            .name(String::from("Datagrams"))
+            .spawn(move || {
+                let mut buffer = [0; MAX_DATAGRAM_SIZE];
+                while !closed.load(Ordering::Relaxed) {
+                    // Timeouts only let the thread see that the client is closed
+                    let Ok((length, address)) = udp.recv_from(&mut buffer) else {
+                        continue;
+                    };
+                    if address != server {
+                        continue;
+                    }
+                    let Ok((_, message)) = decode_datagram(&buffer[..length]) else {
+                        continue;
+                    };
+                    if message.is_unreliable() && sender.send(message).is_err() {
+                        break;
+                    }
+                }
+            })
+            .unwrap();
+    }
+
+    /// Next message from the server if there is any, a lost connection comes as `Disconnect`
+    pub fn poll(&mut self) -> Option<Message> {
+        let message = self.receiver.try_recv().ok()?;
+        if let Message::Welcome { player_id, .. } = message {
+            self.player_id = Some(player_id);
+        }
+        Some(message)
+    }
+
+    /// Sends the message to the server, unreliable messages go over UDP after `Welcome`
+    pub fn send(&mut self, message: &Message) -> std::io::Result<()> {
+        if let (true, Some(player_id)) = (message.is_unreliable(), self.player_id) {
+            let datagram = encode_datagram(player_id, message)?;
+            self.udp.send_to(&datagram, self.server)?;
+            return Ok(());
+        }
+        write_message(&mut self.stream, message)
+    }
+
+    /// Says goodbye to the server and closes the connection
+    pub fn disconnect(mut self, reason: &str) {
+        let reason = String::from(reason);
+        let _ = self.send(&Message::Disconnect { reason });
+    }
+
+    pub fn player_id(&self) -> Option<u32> {
+        self.player_id
+    }
+
+    pub fn server_address(&self) -> SocketAddr {
+        self.server
+    }
+}
+
+impl Drop for NetClient {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
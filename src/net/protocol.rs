@@ -0,0 +1,425 @@
+use std::io::{Error, ErrorKind, Read, Write};
+
+use crate::game::world::block_state::BlockState;
+
+/// Version of the messages below, it changes whenever their layout does. A server only accepts
+/// clients with the same version.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Largest message which is accepted, a chunk of blocks which are all different still fits
+pub const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Largest UDP datagram which is sent, only small messages go over UDP
+pub const MAX_DATAGRAM_SIZE: usize = 512;
+
+/// Messages between a client and a server. Over TCP every message is prefixed by its length as
+/// u32, over UDP by the id of the sending player as u32. Numbers are big endian, strings are
+/// UTF-8 prefixed by their length as u16.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Message {
+    /// First message of a client, the layout never changes, so any server can read the version
+    Hello {
+        version: u16,
+        name: String,
+    },
+    /// Answer of the server to `Hello`, the client generates the world from the seed
+    Welcome {
+        player_id: u32,
+        seed: u32,
+    },
+    /// Blocks of a chunk which differs from the generated one, see `Chunk::encode_blocks`
+    ChunkData {
+        x: i64,
+        z: i64,
+        blocks: Vec<u8>,
+    },
+    /// Block at the position in blocks has changed, from a client or to every other client
+    BlockChange {
+        x: i64,
+        y: i64,
+        z: i64,
+        block: BlockState,
+    },
+    PlayerJoined {
+        player_id: u32,
+        name: String,
+    },
+    /// Position of the eyes and the rotation of the camera in degrees, it's sent over UDP.
+    /// The server ignores the id which a client sends and uses the id of the connection.
+    PlayerMove {
+        player_id: u32,
+        position: [f32; 3],
+        yaw: f32,
+        pitch: f32,
+    },
+    PlayerLeft {
+        player_id: u32,
+    },
+    /// The server fills the sender of messages which clients send
+    Chat {
+        sender: String,
+        text: String,
+    },
+    /// Last message of a connection from either side
+    Disconnect {
+        reason: String,
+    },
+}
+
+impl Message {
+    const HELLO: u8 = 0;
+    const WELCOME: u8 = 1;
+    const CHUNK_DATA: u8 = 2;
+    const BLOCK_CHANGE: u8 = 3;
+    const PLAYER_JOINED: u8 = 4;
+    const PLAYER_MOVE: u8 = 5;
+    const PLAYER_LEFT: u8 = 6;
+    const CHAT: u8 = 7;
+    const DISCONNECT: u8 = 8;
+
+    /// Messages which can be lost, they're sent over UDP once the client has an id
+    pub fn is_unreliable(&self) -> bool {
+        matches!(self, Message::PlayerMove { .. })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoder = Encoder(vec![]);
+        match self {
+            Message::Hello { version, name } => {
+                encoder.u8(Self::HELLO);
+                encoder.u16(*version);
+                encoder.string(name);
+            }
+            Message::Welcome { player_id, seed } => {
+                encoder.u8(Self::WELCOME);
+                encoder.u32(*player_id);
+                encoder.u32(*seed);
+            }
+            Message::ChunkData { x, z, blocks } => {
+                encoder.u8(Self::CHUNK_DATA);
+                encoder.i64(*x);
+                encoder.i64(*z);
+                encoder.u32(blocks.len() as u32);
+                encoder.0.extend_from_slice(blocks);
+            }
+            Message::BlockChange { x, y, z, block } => {
+                encoder.u8(Self::BLOCK_CHANGE);
+                encoder.i64(*x);
+                encoder.i64(*y);
+                encoder.i64(*z);
+                encoder.u64(block.raw());
+            }
+            Message::PlayerJoined { player_id, name } => {
+                encoder.u8(Self::PLAYER_JOINED);
+                encoder.u32(*player_id);
+                encoder.string(name);
+            }
+            Message::PlayerMove {
+                player_id,
+                position,
+                yaw,
+                pitch,
+            } => {
+                encoder.u8(Self::PLAYER_MOVE);
+                encoder.u32(*player_id);
+                for axis in position {
+                    encoder.f32(*axis);
+                }
+                encoder.f32(*yaw);
+                encoder.f32(*pitch);
+            }
+            Message::PlayerLeft { player_id } => {
+                encoder.u8(Self::PLAYER_LEFT);
+                encoder.u32(*player_id);
+            }
+            Message::Chat { sender, text } => {
+                encoder.u8(Self::CHAT);
+                encoder.string(sender);
+                encoder.string(text);
+            }
+            Message::Disconnect { reason } => {
+                encoder.u8(Self::DISCONNECT);
+                encoder.string(reason);
+            }
+        }
+        encoder.0
+    }
+
+    pub fn decode(bytes: &[u8]) -> std::io::Result<Self> {
+        let mut decoder = Decoder(bytes);
+        let message = match decoder.u8()? {
+            Self::HELLO => Message::Hello {
+                version: decoder.u16()?,
+                name: decoder.string()?,
+            },
+            Self::WELCOME => Message::Welcome {
+                player_id: decoder.u32()?,
+                seed: decoder.u32()?,
+            },
+            Self::CHUNK_DATA => {
+                let x = decoder.i64()?;
+                let z = decoder.i64()?;
+                let length = decoder.u32()? as usize;
+                Message::ChunkData {
+                    x,
+                    z,
+                    blocks: decoder.bytes(length)?.to_vec(),
+                }
+            }
+            Self::BLOCK_CHANGE => Message::BlockChange {
+                x: decoder.i64()?,
+                y: decoder.i64()?,
+                z: decoder.i64()?,
+                block: decoder.block()?,
+            },
+            Self::PLAYER_JOINED => Message::PlayerJoined {
+                player_id: decoder.u32()?,
+                name: decoder.string()?,
+            },
+            Self::PLAYER_MOVE => Message::PlayerMove {
+                player_id: decoder.u32()?,
+                position: [decoder.f32()?, decoder.f32()?, decoder.f32()?],
+                yaw: decoder.f32()?,
+                pitch: decoder.f32()?,
+            },
+            Self::PLAYER_LEFT => Message::PlayerLeft {
+                player_id: decoder.u32()?,
+            },
+            Self::CHAT => Message::Chat {
+                sender: decoder.string()?,
+                text: decoder.string()?,
+            },
+            Self::DISCONNECT => Message::Disconnect {
+                reason: decoder.string()?,
+            },
+            kind => return Err(invalid(format!("unknown message kind {}", kind))),
+        };
+        if !decoder.0.is_empty() {
+            return Err(invalid(String::from("message is longer than expected")));
+        }
+        Ok(message)
+    }
+}
+
+/// Writes the message with its length, so it can be read from a stream
+pub fn write_message(writer: &mut impl Write, message: &Message) -> std::io::Result<()> {
+    let bytes = message.encode();
+    let mut frame = Vec::with_capacity(bytes.len() + 4);
+    frame.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&bytes);
+    writer.write_all(&frame)
+}
+
+/// Reads a message written by `write_message`, it waits until the whole message has come
+pub fn read_message(reader: &mut impl Read) -> std::io::Result<Message> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(invalid(format!("message of {} bytes is too long", length)));
+    }
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes)?;
+    Message::decode(&bytes)
+}
+
+/// UDP datagram with the message and the id of the sending player, the server sends id 0
+pub fn encode_datagram(player_id: u32, message: &Message) -> std::io::Result<Vec<u8>> {
+    let mut datagram = player_id.to_be_bytes().to_vec();
+    datagram.extend_from_slice(&message.encode());
+    if datagram.len() > MAX_DATAGRAM_SIZE {
+        return Err(invalid(String::from("message is too long for a datagram")));
+    }
+    Ok(datagram)
+}
+
+pub fn decode_datagram(datagram: &[u8]) -> std::io::Result<(u32, Message)> {
+    if datagram.len() < 4 {
+        return Err(invalid(String::from("datagram is too short")));
+    }
+    let player_id = u32::from_be_bytes([datagram[0], datagram[1], datagram[2], datagram[3]]);
+    Ok((player_id, Message::decode(&datagram[4..])?))
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn i64(&mut self, value: i64) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Longer strings are cut at a character boundary
+    fn string(&mut self, value: &str) {
+        let mut length = value.len().min(u16::MAX as usize);
+        while !value.is_char_boundary(length) {
+            length -= 1;
+        }
+        self.u16(length as u16);
+        self.0.extend_from_slice(&value.as_bytes()[..length]);
+    }
+}
+
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn bytes(&mut self, length: usize) -> std::io::Result<&'a [u8]> {
+        if self.0.len() < length {
+            return Err(invalid(String::from("message is shorter than expected")));
+        }
+        let (bytes, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> std::io::Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> std::io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> std::io::Result<u16> {
+        self.array().map(u16::from_be_bytes)
+    }
+
+    fn u32(&mut self) -> std::io::Result<u32> {
+        self.array().map(u32::from_be_bytes)
+    }
+
+    fn u64(&mut self) -> std::io::Result<u64> {
+        self.array().map(u64::from_be_bytes)
+    }
+
+    fn i64(&mut self) -> std::io::Result<i64> {
+        self.array().map(i64::from_be_bytes)
+    }
+
+    fn f32(&mut self) -> std::io::Result<f32> {
+        self.array().map(f32::from_be_bytes)
+    }
+
+    fn block(&mut self) -> std::io::Result<BlockState> {
+        let block = BlockState::from_raw(self.u64()?);
+        if !block.is_known() {
+            return Err(invalid(format!("unknown block id {}", block.id())));
+        }
+        Ok(block)
+    }
+
+    fn string(&mut self) -> std::io::Result<String> {
+        let length = self.u16()? as usize;
+        String::from_utf8(self.bytes(length)?.to_vec())
+            .map_err(|_| invalid(String::from("string is not UTF-8")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_are_read_as_written() {
+        let messages = [
+            Message::Hello {
+                version: PROTOCOL_VERSION,
+                name: String::from("Steve"),
+            },
+            Message::Welcome {
+                player_id: 3,
+                seed: 42,
+            },
+            Message::ChunkData {
+                x: -1,
+                z: 7,
+                blocks: vec![0, 1, 2, 3],
+            },
+            Message::BlockChange {
+                x: -20,
+                y: 100,
+                z: 5,
+                block: BlockState::from_raw(0x0001_0000_0007),
+            },
+            Message::PlayerJoined {
+                player_id: 3,
+                name: String::from("Алекс"),
+            },
+            Message::PlayerMove {
+                player_id: 3,
+                position: [0.5, 101.0, -7.25],
+                yaw: -90.0,
+                pitch: 12.5,
+            },
+            Message::PlayerLeft { player_id: 3 },
+            Message::Chat {
+                sender: String::from("Steve"),
+                text: String::from("hi"),
+            },
+            Message::Disconnect {
+                reason: String::from("bye"),
+            },
+        ];
+        let mut stream = vec![];
+        for message in &messages {
+            write_message(&mut stream, message).unwrap();
+        }
+        let mut reader = stream.as_slice();
+        for message in &messages {
+            assert_eq!(&read_message(&mut reader).unwrap(), message);
+        }
+        assert!(read_message(&mut reader).is_err());
+
+        let datagram = encode_datagram(9, &messages[5]).unwrap();
+        assert_eq!(
+            decode_datagram(&datagram).unwrap(),
+            (9, messages[5].clone())
+        );
+    }
+
+    #[test]
+    fn broken_messages_are_rejected() {
+        assert!(Message::decode(&[]).is_err());
+        assert!(Message::decode(&[99]).is_err());
+        let mut bytes = Message::PlayerLeft { player_id: 1 }.encode();
+        bytes.push(0);
+        assert!(Message::decode(&bytes).is_err());
+        assert!(Message::decode(&bytes[..bytes.len() - 2]).is_err());
+        let unknown_block = Message::BlockChange {
+            x: 0,
+            y: 0,
+            z: 0,
+            block: BlockState::new(u16::MAX),
+        };
+        assert!(Message::decode(&unknown_block.encode()).is_err());
+
+        let too_long = (MAX_MESSAGE_SIZE as u32 + 1).to_be_bytes();
+        assert!(read_message(&mut too_long.as_slice()).is_err());
+        assert!(decode_datagram(&[0, 0]).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::protocol::{
+    decode_datagram, encode_datagram, read_message, write_message, Message, MAX_DATAGRAM_SIZE,
+    PROTOCOL_VERSION,
+};
+use super::{loopback, UDP_READ_TIMEOUT, WRITE_TIMEOUT};
+
+/// What has happened on the network since the last `NetServer::poll`
+#[derive(Clone, PartialEq, Debug)]
+pub enum ServerEvent {
+    /// A client has said hello with the version of the server
+    Joined {
+        player_id: u32,
+        name: String,
+    },
+    Received {
+        player_id: u32,
+        message: Message,
+    },
+    /// The client has disconnected or its connection is lost
+    Left {
+        player_id: u32,
+        reason: String,
+    },
+}
+
+/// What the threads of the server send to it
+enum Incoming {
+    Connected {
+        player_id: u32,
+        name: String,
+        stream: TcpStream,
+    },
+    Received {
+        player_id: u32,
+        message: Message,
+    },
+    Closed {
+        player_id: u32,
+        reason: String,
+    },
+    Datagram {
+        address: SocketAddr,
+        bytes: Vec<u8>,
+    },
+}
+
+struct Connection {
+    name: String,
+    stream: TcpStream,
+    /// Where the datagrams of the player come from, they're sent back there
+    udp_address: Option<SocketAddr>,
+}
+
+/// Server side of the connections with clients. Every connection is read by its own thread,
+/// the messages are collected by `poll`.
+pub struct NetServer {
+    address: SocketAddr,
+    udp: UdpSocket,
+    connections: HashMap<u32, Connection>,
+    receiver: Receiver<Incoming>,
+    closed: Arc<AtomicBool>,
+}
+
+impl NetServer {
+    /// Time which a client has to say hello after connecting
+    const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Listens for TCP connections and UDP datagrams on the same port, port 0 picks a free one
+    pub fn bind(address: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let udp = UdpSocket::bind(address)?;
+        udp.set_read_timeout(Some(UDP_READ_TIMEOUT))?;
+
+        let (sender, receiver) = channel();
+        let closed = Arc::new(AtomicBool::new(false));
+        Self::spawn_listener(listener, sender.clone(), closed.clone());
+        Self::spawn_udp_reader(udp.try_clone()?, sender, closed.clone());
+        Ok(Self {
+            address,
+            udp,
+            connections: HashMap::new(),
+            receiver,
+            closed,
+        })
+    }
+
+    fn spawn_listener(listener: TcpListener, sender: Sender<Incoming>, closed: Arc<AtomicBool>) {
+        let next_id = Arc::new(AtomicU32::new(1));
+        std::thread::Builder::new()
+            .name(String::from("Listener"))
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if closed.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    let sender = sender.clone();
+                    let next_id = next_id.clone();
+                    let _ = std::thread::Builder::new()
+                        .name(String::from("Connection"))
+                        .spawn(move || Self::serve(stream, sender, &next_id));
+                }
+            })
+            .unwrap();
+    }
+
+    /// Waits for the hello of the client, then reads its messages until the connection closes
+    fn serve(mut stream: TcpStream, sender: Sender<Incoming>, next_id: &AtomicU32) {
+        let _ = stream.set_read_timeout(Some(Self::HELLO_TIMEOUT));
+        let name = match read_message(&mut stream) {
+            Ok(Message::Hello { version, name }) if version == PROTOCOL_VERSION => name,
+            Ok(Message::Hello { version, .. }) => {
+                let reason = format!(
+                    "The server uses protocol version {}, the client uses {}",
+                    PROTOCOL_VERSION, version
+                );
+                let _ = write_message(&mut stream, &Message::Disconnect { reason });
+                return;
+            }
+            // Anything else is not a client of the game
+            _ => return,
+        };
+        let _ = stream.set_read_timeout(None);
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+        let _ = stream.set_nodelay(true);
+        let Ok(writer) = stream.try_clone() else {
+            return;
+        };
+
+        let player_id = next_id.fetch_add(1, Ordering::Relaxed);
+        let connected = Incoming::Connected {
+            player_id,
+            name,
+            stream: writer,
+        };
+        if sender.send(connected).is_err() {
+            return;
+        }
+        let mut reader = BufReader::new(stream);
+        loop {
+            let incoming = match read_message(&mut reader) {
+                Ok(Message::Disconnect { reason }) => Incoming::Closed { player_id, reason },
+                Ok(message) => Incoming::Received { player_id, message },
+                Err(error) => Incoming::Closed {
+                    player_id,
+                    reason: error.to_string(),
+                },
+            };
+            let closed = matches!(incoming, Incoming::Closed { .. });
+            if sender.send(incoming).is_err() || closed {
+                return;
+            }
+        }
+    }
+
+    fn spawn_udp_reader(udp: UdpSocket, sender: Sender<Incoming>, closed: Arc<AtomicBool>) {
+        std::thread::Builder::new()
+            .name(String::from("Datagrams"))
+            .spawn(move || {
+                let mut buffer = [0; MAX_DATAGRAM_SIZE];
+                while !closed.load(Ordering::Relaxed) {
+                    // Timeouts only let the thread see that the server is closed
+                    let Ok((length, address)) = udp.recv_from(&mut buffer) else {
+                        continue;
+                    };
+                    let bytes = buffer[..length].to_vec();
+                    if sender.send(Incoming::Datagram { address, bytes }).is_err() {
+                        break;
+                    }
+                }
+            })
+            .unwrap();
+    }
+
+    /// Events since the last call, in the order they have happened
+    pub fn poll(&mut self) -> Vec<ServerEvent> {
+        let mut events = vec![];
+        while let Ok(incoming) = self.receiver.try_recv() {
+            if let Some(event) = self.accept(incoming) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    fn accept(&mut self, incoming: Incoming) -> Option<ServerEvent> {
+        match incoming {
+            Incoming::Connected {
+                player_id,
+                name,
+                stream,
+            } => {
+                let connection = Connection {
+                    name: name.clone(),
+                    stream,
+                    udp_address: None,
+                };
+                self.connections.insert(player_id, connection);
+                Some(ServerEvent::Joined { player_id, name })
+            }
+            Incoming::Received { player_id, message } => self
+                .connections
+                .contains_key(&player_id)
+                .then_some(ServerEvent::Received { player_id, message }),
+            Incoming::Closed { player_id, reason } => {
+                self.connections.remove(&player_id)?;
+                Some(ServerEvent::Left { player_id, reason })
+            }
+            Incoming::Datagram { address, bytes } => {
+                let (player_id, message) = decode_datagram(&bytes).ok()?;
+                let connection = self.connections.get_mut(&player_id)?;
+                // Only the host of the connection can send datagrams of its player
+                let host = connection.stream.peer_addr().ok()?.ip();
+                if host != address.ip() || !message.is_unreliable() {
+                    return None;
+                }
+                connection.udp_address = Some(address);
+                Some(ServerEvent::Received { player_id, message })
+            }
+        }
+    }
+
+    /// Sends the message to the player. Unreliable messages go over UDP once the player has sent
+    /// a datagram. A connection which cannot be written to is closed and the player leaves.
+    pub fn send(&mut self, player_id: u32, message: &Message) {
+        let Some(connection) = self.connections.get_mut(&player_id) else {
+            return;
+        };
+        if let (true, Some(udp_address)) = (message.is_unreliable(), connection.udp_address) {
+            if let Ok(datagram) = encode_datagram(0, message) {
+                let _ = self.udp.send_to(&datagram, udp_address);
+                return;
+            }
+        }
+        if write_message(&mut connection.stream, message).is_err() {
+            // The thread of the connection reports that it's closed
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    /// Sends the message to every player except the given one
+    pub fn broadcast(&mut self, message: &Message, except: Option<u32>) {
+        let players = self
+            .connections
+            .keys()
+            .copied()
+            .filter(|player_id| Some(*player_id) != except)
+            .collect::<Vec<u32>>();
+        for player_id in players {
+            self.send(player_id, message);
+        }
+    }
+
+    /// Says goodbye to the player and closes its connection, no `Left` event follows
+    pub fn disconnect(&mut self, player_id: u32, reason: &str) {
+        if let Some(mut connection) = self.connections.remove(&player_id) {
+            let reason = String::from(reason);
+            let _ = write_message(&mut connection.stream, &Message::Disconnect { reason });
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    pub fn local_address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Ids and names of the connected players
+    pub fn players(&self) -> Vec<(u32, String)> {
+        let mut players = self
+            .connections
+            .iter()
+            .map(|(player_id, connection)| (*player_id, connection.name.clone()))
+            .collect::<Vec<(u32, String)>>();
+        players.sort();
+        players
+    }
+}
+
+impl Drop for NetServer {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        let players = self.connections.keys().copied().collect::<Vec<u32>>();
+        for player_id in players {
+            self.disconnect(player_id, "The server is stopped");
+        }
+        // Wakes up the listener, so it sees that the server is closed
+        let _ = TcpStream::connect(loopback(self.address));
+    }
+}
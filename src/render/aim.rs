@@ -8,6 +8,7 @@ pub struct Aim {
     vbo: GLuint,
     model: Mat4,
     program: Program,
+    color: Vec3,
 }
 
 impl Aim {
@@ -20,16 +21,23 @@ impl Aim {
 
     pub fn new(width: f32, height: f32, color: Vec3) -> Aim {
         let program = Self::initialize_shaders();
-        let vbo = Self::create_vbo(width / height, color);
+        let vbo = Self::create_vbo();
+        Self::upload_vertices(vbo, width / height, color);
         let vao = Self::create_vao(vbo);
         Self {
             program,
             vbo,
             vao,
             model: scale(&Mat4::identity(), &vec3(0.02, 0.02, 1.)),
+            color,
         }
     }
 
+    /// Rebuilds the lines for the new aspect ratio of the screen, so the aim isn't stretched
+    pub fn resize(&mut self, width: f32, height: f32) {
+        Self::upload_vertices(self.vbo, width / height, self.color);
+    }
+
     fn initialize_shaders() -> Program {
         Program::from([
             Shader::from_vertex(String::from("res/shaders/aim-vert.glsl")).unwrap(),
@@ -37,7 +45,15 @@ impl Aim {
         ])
     }
 
-    fn create_vbo(aspect_ratio: f32, color: Vec3) -> GLuint {
+    fn create_vbo() -> GLuint {
+        let mut vbo = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut vbo);
+        }
+        vbo
+    }
+
+    fn upload_vertices(vbo: GLuint, aspect_ratio: f32, color: Vec3) {
         let mut vertices = vec![];
         let color_array = [color.x, color.y, color.z];
         for v in Self::VERTICES.clone().iter_mut() {
@@ -46,9 +62,7 @@ impl Aim {
             vertices.extend_from_slice(&color_array);
         }
 
-        let mut vbo = 0;
         unsafe {
-            gl::GenBuffers(1, &mut vbo);
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
 
             gl::BufferData(
@@ -57,8 +71,8 @@ impl Aim {
                 vertices.as_ptr() as *const GLvoid,
                 gl::STATIC_DRAW,
             );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
         }
-        vbo
     }
 
     const STRIDE: GLint = (6 * std::mem::size_of::<f32>()) as GLint;
@@ -1,50 +1,247 @@
-const BLOCKS: [Block; 3] = [
+use crate::game::player::inventory::ToolKind;
+use crate::game::world::{
+    block_state::{BlockProperty, BlockState},
+    light::MAX_LIGHT,
+};
+
+pub const BLOCK_COUNT: usize = BLOCKS.len();
+
+const BLOCKS: [Block; 13] = [
     Block {
         name: BlockType::AIR,
-        texutre_offset: 0,
+        textures: Block::same_textures(0),
+        properties: &[],
+        transparency: Transparency::Transparent,
+        light_emission: 0,
+        hardness: 0.0,
+        tool: None,
+        drop: BlockDrop::Nothing,
     },
     Block {
         name: BlockType::STONE,
-        texutre_offset: 0,
+        textures: Block::same_textures(0),
+        properties: &[],
+        transparency: Transparency::Opaque,
+        light_emission: 0,
+        hardness: 1.5,
+        tool: Some(ToolKind::PICKAXE),
+        drop: BlockDrop::Itself,
     },
     Block {
         name: BlockType::DIRT,
-        texutre_offset: 1,
+        textures: Block::same_textures(1),
+        properties: &[],
+        transparency: Transparency::Opaque,
+        light_emission: 0,
+        hardness: 0.5,
+        tool: Some(ToolKind::SHOVEL),
+        drop: BlockDrop::Itself,
+    },
+    Block {
+        name: BlockType::LOG,
+        textures: [2, 2, 2, 2, 3, 3],
+        properties: &[BlockProperty::Axis],
+        transparency: Transparency::Opaque,
+        light_emission: 0,
+        hardness: 2.0,
+        tool: Some(ToolKind::AXE),
+        drop: BlockDrop::Itself,
+    },
+    Block {
+        name: BlockType::PLANKS,
+        textures: Block::same_textures(4),
+        properties: &[],
+        transparency: Transparency::Opaque,
+        light_emission: 0,
+        hardness: 2.0,
+        tool: Some(ToolKind::AXE),
+        drop: BlockDrop::Itself,
+    },
+    Block {
+        name: BlockType::STAIRS,
+        textures: Block::same_textures(4),
+        properties: &[BlockProperty::Facing],
+        transparency: Transparency::Opaque,
+        light_emission: 0,
+        hardness: 2.0,
+        tool: Some(ToolKind::AXE),
+        drop: BlockDrop::Itself,
+    },
+    Block {
+        name: BlockType::FURNACE,
+        textures: [5, 6, 6, 6, 7, 7],
+        properties: &[BlockProperty::Facing],
+        transparency: Transparency::Opaque,
+        light_emission: 0,
+        hardness: 3.5,
+        tool: Some(ToolKind::PICKAXE),
+        drop: BlockDrop::Itself,
+    },
+    Block {
+        name: BlockType::GLASS,
+        textures: Block::same_textures(8),
+        properties: &[],
+        transparency: Transparency::Transparent,
+        light_emission: 0,
+        hardness: 0.3,
+        tool: None,
+        drop: BlockDrop::Nothing,
+    },
+    Block {
+        name: BlockType::LEAVES,
+        textures: Block::same_textures(9),
+        properties: &[],
+        transparency: Transparency::Transparent,
+        light_emission: 0,
+        hardness: 0.2,
+        tool: None,
+        drop: BlockDrop::Nothing,
+    },
+    Block {
+        name: BlockType::WATER,
+        textures: Block::same_textures(10),
+        properties: &[BlockProperty::FluidLevel],
+        transparency: Transparency::Translucent,
+        light_emission: 0,
+        hardness: Block::UNBREAKABLE,
+        tool: None,
+        drop: BlockDrop::Nothing,
+    },
+    Block {
+        name: BlockType::STAINEDGLASS,
+        textures: Block::same_textures(11),
+        properties: &[],
+        transparency: Transparency::Translucent,
+        light_emission: 0,
+        hardness: 0.3,
+        tool: None,
+        drop: BlockDrop::Nothing,
+    },
+    Block {
+        name: BlockType::LAMP,
+        textures: Block::same_textures(12),
+        properties: &[],
+        transparency: Transparency::Opaque,
+        light_emission: MAX_LIGHT,
+        hardness: 0.3,
+        tool: Some(ToolKind::PICKAXE),
+        drop: BlockDrop::Itself,
+    },
+    Block {
+        name: BlockType::WORKBENCH,
+        textures: [13, 13, 14, 14, 15, 4],
+        properties: &[],
+        transparency: Transparency::Opaque,
+        light_emission: 0,
+        hardness: 2.5,
+        tool: Some(ToolKind::AXE),
+        drop: BlockDrop::Itself,
     },
 ];
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum BlockType {
     AIR,
     STONE,
     DIRT,
+    LOG,
+    PLANKS,
+    STAIRS,
+    FURNACE,
+    GLASS,
+    LEAVES,
+    WATER,
+    STAINEDGLASS,
+    LAMP,
+    WORKBENCH,
+}
+
+impl BlockType {
+    /// Finds the block by its name as it's written in data files, e.g. "PLANKS"
+    pub fn from_name(name: &str) -> Option<BlockType> {
+        BLOCKS
+            .iter()
+            .map(|block| block.name)
+            .find(|block| format!("{:?}", block) == name)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Transparency {
+    /// Hides faces of its neighbours
+    Opaque,
+    /// Texels are either fully opaque or fully transparent, rendered with opaque blocks
+    Transparent,
+    /// Blended with whatever is behind, rendered after all opaque blocks
+    Translucent,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockDrop {
+    Nothing,
+    /// The broken block without its metadata
+    Itself,
 }
 
 pub struct Block {
     name: BlockType,
-    texutre_offset: usize,
+    /// Texture offsets corresponding to `RenderPosition`, given for the block which faces NORTH
+    /// or stands along Y axis
+    textures: [usize; 6],
+    /// Schema of the metadata bits, properties are packed in the given order
+    properties: &'static [BlockProperty],
+    transparency: Transparency,
+    /// Block light level which the block spreads around
+    light_emission: u8,
+    /// Seconds to break the block by hand
+    hardness: f32,
+    /// Tool which breaks the block faster
+    tool: Option<ToolKind>,
+    /// What the player gets after breaking the block
+    drop: BlockDrop,
 }
 
 impl Block {
+    pub const UNBREAKABLE: f32 = f32::INFINITY;
+
+    const fn same_textures(offset: usize) -> [usize; 6] {
+        [offset; 6]
+    }
+
     pub fn name(&self) -> BlockType {
         self.name
     }
 
-    pub fn zoffset_texure(&self) -> usize {
-        self.texutre_offset
+    pub fn zoffset_texure(&self, face: usize) -> usize {
+        self.textures[face]
+    }
+
+    pub fn properties(&self) -> &'static [BlockProperty] {
+        self.properties
+    }
+
+    pub fn transparency(&self) -> Transparency {
+        self.transparency
+    }
+
+    pub fn light_emission(&self) -> u8 {
+        self.light_emission
+    }
+
+    pub fn hardness(&self) -> f32 {
+        self.hardness
+    }
+
+    pub fn tool(&self) -> Option<ToolKind> {
+        self.tool
     }
 
-    const BIT_WIDTH: usize = 16;
-    pub fn is_air(mut block: u64) -> bool {
-        let mut answer = true;
-        for _ in 0..Self::BIT_WIDTH {
-            if block & 1 == 1 {
-                answer = false;
-                break;
-            }
-            block >>= 1;
+    /// Block and its count which the player gets after breaking the given block
+    pub fn drop_of(block: BlockState) -> Option<(BlockState, usize)> {
+        match block.info().drop {
+            BlockDrop::Nothing => None,
+            BlockDrop::Itself => Some((BlockState::new(block.id()), 1)),
         }
-        answer
     }
 }
 
@@ -52,7 +249,13 @@ impl Clone for Block {
     fn clone(&self) -> Self {
         Self {
             name: self.name,
-            texutre_offset: self.texutre_offset,
+            textures: self.textures,
+            properties: self.properties,
+            transparency: self.transparency,
+            light_emission: self.light_emission,
+            hardness: self.hardness,
+            tool: self.tool,
+            drop: self.drop,
         }
     }
 }
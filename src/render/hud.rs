@@ -0,0 +1,398 @@
+use gl::types::*;
+use nalgebra_glm::{ortho, Mat4, Vec4};
+
+use super::{Program, Shader, TextureAtlas, TextureAtlasConfiguration, VaoAttributes};
+
+/// Rectangle on the screen in pixels, the origin is in the top left corner
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// The rectangle shrunk by the margin on every side
+    pub fn inset(&self, margin: f32) -> Self {
+        Self::new(
+            self.x + margin,
+            self.y + margin,
+            self.width - 2.0 * margin,
+            self.height - 2.0 * margin,
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HudTexture {
+    /// Tiles of the block texture atlas
+    Blocks,
+    /// Glyphs of the bitmap font
+    Font,
+}
+
+/// Monospace font from an image with 16x16 cells of glyphs in order of ASCII codes
+pub struct BitmapFont {
+    atlas: TextureAtlas,
+}
+
+impl BitmapFont {
+    const GLYPH_SIZE: usize = 16;
+    /// Advance of a glyph as a part of its size
+    const ADVANCE: f32 = 0.75;
+    const LINE_SPACING: f32 = 1.125;
+    /// Glyph which is drawn instead of characters out of ASCII
+    const REPLACEMENT: char = '?';
+
+    pub fn load(image_path: &str) -> Self {
+        Self {
+            atlas: TextureAtlas::from(TextureAtlasConfiguration {
+                image_path: String::from(image_path),
+                square_size: Self::GLYPH_SIZE,
+            }),
+        }
+    }
+
+    fn glyph(character: char) -> usize {
+        if character.is_ascii() && !character.is_ascii_control() {
+            character as usize
+        } else {
+            Self::REPLACEMENT as usize
+        }
+    }
+
+    /// Size of the text in pixels when its glyphs are `size` pixels high
+    pub fn measure(text: &str, size: f32) -> (f32, f32) {
+        let columns = text
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let lines = text.lines().count().max(1);
+        (
+            columns as f32 * size * Self::ADVANCE,
+            lines as f32 * size * Self::LINE_SPACING,
+        )
+    }
+}
+
+/// Range of vertices which are drawn with the same texture
+struct Batch {
+    texture: Option<HudTexture>,
+    first: usize,
+    count: usize,
+}
+
+/// Overlay drawn on top of the world in screen pixels. Quads are collected during the frame and
+/// drawn with as few draw calls as possible in `render`, later quads cover earlier ones.
+pub struct Hud {
+    vao: GLuint,
+    vbo: GLuint,
+    /// Size of the vertex buffer in floats
+    capacity: usize,
+    program: Program,
+    projection: Mat4,
+    width: f32,
+    height: f32,
+
+    blocks: TextureAtlas,
+    font: BitmapFont,
+
+    vertices: Vec<f32>,
+    batches: Vec<Batch>,
+}
+
+impl Hud {
+    /// Position, texture coordinates with a layer and color
+    const VERTEX_SIZE: usize = 2 + 3 + 4;
+
+    pub fn new(width: f32, height: f32) -> Self {
+        let vbo = Self::create_vbo();
+        let mut hud = Self {
+            vao: Self::create_vao(vbo),
+            vbo,
+            capacity: 0,
+            program: Program::from([
+                Shader::from_vertex(String::from("res/shaders/hud-vert.glsl")).unwrap(),
+                Shader::from_fragment(String::from("res/shaders/hud-frag.glsl")).unwrap(),
+            ]),
+            projection: Mat4::identity(),
+            width,
+            height,
+
+            blocks: TextureAtlas::from(TextureAtlasConfiguration {
+                image_path: String::from("res/images/block-texture-atlas.png"),
+                square_size: 16,
+            }),
+            font: BitmapFont::load("res/images/font.png"),
+
+            vertices: vec![],
+            batches: vec![],
+        };
+        hud.resize(width, height);
+        hud
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+        self.projection = ortho(0.0, width, height, 0.0, -1.0, 1.0);
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    fn create_vbo() -> GLuint {
+        let mut vbo = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut vbo);
+        }
+        vbo
+    }
+
+    const STRIDE: GLint = (Self::VERTEX_SIZE * std::mem::size_of::<f32>()) as GLint;
+    const VAO_ATTRIBS: [VaoAttributes; 3] = [
+        VaoAttributes {
+            position: 0,
+            size: 2,
+            type_: gl::FLOAT,
+            normalized: gl::FALSE,
+            stride: Self::STRIDE,
+            pointer: std::ptr::null(),
+        },
+        VaoAttributes {
+            position: 1,
+            size: 3,
+            type_: gl::FLOAT,
+            normalized: gl::FALSE,
+            stride: Self::STRIDE,
+            pointer: (2 * std::mem::size_of::<f32>()) as *const GLvoid,
+        },
+        VaoAttributes {
+            position: 2,
+            size: 4,
+            type_: gl::FLOAT,
+            normalized: gl::FALSE,
+            stride: Self::STRIDE,
+            pointer: (5 * std::mem::size_of::<f32>()) as *const GLvoid,
+        },
+    ];
+
+    fn create_vao(vbo: GLuint) -> GLuint {
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            for attrs in Self::VAO_ATTRIBS {
+                gl::VertexAttribPointer(
+                    attrs.position,
+                    attrs.size,
+                    attrs.type_,
+                    attrs.normalized,
+                    attrs.stride,
+                    attrs.pointer,
+                );
+                gl::EnableVertexAttribArray(attrs.position);
+            }
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+        vao
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: Vec4) {
+        self.push_quad(rect, None, 0, color);
+    }
+
+    /// Frame of the given thickness inside the rectangle
+    pub fn stroke_rect(&mut self, rect: Rect, thickness: f32, color: Vec4) {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = rect;
+        self.fill_rect(Rect::new(x, y, width, thickness), color);
+        self.fill_rect(
+            Rect::new(x, y + height - thickness, width, thickness),
+            color,
+        );
+        self.fill_rect(
+            Rect::new(x, y + thickness, thickness, height - 2.0 * thickness),
+            color,
+        );
+        self.fill_rect(
+            Rect::new(
+                x + width - thickness,
+                y + thickness,
+                thickness,
+                height - 2.0 * thickness,
+            ),
+            color,
+        );
+    }
+
+    /// Draws the layer of the texture tinted with the color
+    pub fn draw_image(&mut self, rect: Rect, texture: HudTexture, layer: usize, color: Vec4) {
+        self.push_quad(rect, Some(texture), layer, color);
+    }
+
+    /// Draws the text with its top left corner at the point, `size` is the height of glyphs.
+    /// Lines are split by '\n'.
+    pub fn draw_text(&mut self, x: f32, y: f32, size: f32, text: &str, color: Vec4) {
+        for (row, line) in text.lines().enumerate() {
+            let top = y + row as f32 * size * BitmapFont::LINE_SPACING;
+            for (column, character) in line.chars().enumerate() {
+                if character == ' ' {
+                    continue;
+                }
+                let left = x + column as f32 * size * BitmapFont::ADVANCE;
+                self.push_quad(
+                    Rect::new(left, top, size, size),
+                    Some(HudTexture::Font),
+                    BitmapFont::glyph(character),
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Draws the text with a dark shadow, so it can be read on any background
+    pub fn draw_text_with_shadow(&mut self, x: f32, y: f32, size: f32, text: &str, color: Vec4) {
+        let offset = (size / 8.0).max(1.0);
+        let shadow = Vec4::new(0.0, 0.0, 0.0, color.w * 0.75);
+        self.draw_text(x + offset, y + offset, size, text, shadow);
+        self.draw_text(x, y, size, text, color);
+    }
+
+    fn push_quad(&mut self, rect: Rect, texture: Option<HudTexture>, layer: usize, color: Vec4) {
+        let (left, top) = (rect.x, rect.y);
+        let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+        let layer = layer as f32;
+        let corners = [
+            [left, top, 0.0, 0.0],
+            [left, bottom, 0.0, 1.0],
+            [right, top, 1.0, 0.0],
+            [right, top, 1.0, 0.0],
+            [left, bottom, 0.0, 1.0],
+            [right, bottom, 1.0, 1.0],
+        ];
+        for [x, y, u, v] in corners {
+            self.vertices
+                .extend_from_slice(&[x, y, u, v, layer, color.x, color.y, color.z, color.w]);
+        }
+
+        match self.batches.last_mut() {
+            Some(batch) if batch.texture == texture => batch.count += corners.len(),
+            _ => self.batches.push(Batch {
+                texture,
+                first: self.vertices.len() / Self::VERTEX_SIZE - corners.len(),
+                count: corners.len(),
+            }),
+        }
+    }
+
+    fn upload(&mut self) {
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            if self.vertices.len() > self.capacity {
+                self.capacity = self.vertices.len().next_power_of_two();
+                gl::BufferData(
+                    gl::ARRAY_BUFFER,
+                    (self.capacity * std::mem::size_of::<f32>()) as GLsizeiptr,
+                    std::ptr::null(),
+                    gl::DYNAMIC_DRAW,
+                );
+            }
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                std::mem::size_of_val(self.vertices.as_slice()) as GLsizeiptr,
+                self.vertices.as_ptr() as *const GLvoid,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+    }
+
+    /// Draws everything collected since the last call and starts a new frame
+    pub fn render(&mut self) {
+        if self.batches.is_empty() {
+            return;
+        }
+        self.upload();
+
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        self.program.set_used();
+        self.program.insert_mat4(
+            &std::ffi::CString::new("projection").unwrap(),
+            &self.projection,
+        );
+        unsafe {
+            gl::BindVertexArray(self.vao);
+        }
+        for batch in &self.batches {
+            let textured = match batch.texture {
+                Some(HudTexture::Blocks) => {
+                    self.blocks.set_used();
+                    true
+                }
+                Some(HudTexture::Font) => {
+                    self.font.atlas.set_used();
+                    true
+                }
+                None => false,
+            };
+            self.program.insert_int(
+                &std::ffi::CString::new("textured").unwrap(),
+                textured as i32,
+            );
+            unsafe {
+                gl::DrawArrays(gl::TRIANGLES, batch.first as GLint, batch.count as GLsizei);
+            }
+        }
+        unsafe {
+            gl::BindVertexArray(0);
+            gl::Disable(gl::BLEND);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+
+        self.vertices.clear();
+        self.batches.clear();
+    }
+}
+
+impl Drop for Hud {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.vbo);
+        }
+    }
+}
@@ -1,15 +1,18 @@
-use super::{block::*, *};
+use super::{block::Transparency, *};
 use crate::game::world::{
-    chunk::{Chunk, ChunkBlocks},
+    block_state::{Axis, BlockState},
+    chunk::Chunk,
+    light::{passes_light, LightKind, FULL_SKYLIGHT, MAX_LIGHT},
     WorldHelper,
 };
 
 use nalgebra_glm::*;
 use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 
 use crate::foreach_block;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum RenderPosition {
     /// Negative Z
     NORTH = 0,
@@ -25,15 +28,85 @@ pub enum RenderPosition {
     BOTTOM = 5,
 }
 
+impl RenderPosition {
+    pub const ALL: [RenderPosition; 6] = [
+        RenderPosition::NORTH,
+        RenderPosition::SOUTH,
+        RenderPosition::WEST,
+        RenderPosition::EAST,
+        RenderPosition::TOP,
+        RenderPosition::BOTTOM,
+    ];
+
+    /// Horizontal positions in clockwise order looking from the top
+    const CLOCKWISE: [RenderPosition; 4] = [
+        RenderPosition::NORTH,
+        RenderPosition::EAST,
+        RenderPosition::SOUTH,
+        RenderPosition::WEST,
+    ];
+
+    pub fn opposite(self) -> Self {
+        match self {
+            RenderPosition::NORTH => RenderPosition::SOUTH,
+            RenderPosition::SOUTH => RenderPosition::NORTH,
+            RenderPosition::WEST => RenderPosition::EAST,
+            RenderPosition::EAST => RenderPosition::WEST,
+            RenderPosition::TOP => RenderPosition::BOTTOM,
+            RenderPosition::BOTTOM => RenderPosition::TOP,
+        }
+    }
+
+    pub fn axis(self) -> Axis {
+        match self {
+            RenderPosition::WEST | RenderPosition::EAST => Axis::X,
+            RenderPosition::TOP | RenderPosition::BOTTOM => Axis::Y,
+            RenderPosition::NORTH | RenderPosition::SOUTH => Axis::Z,
+        }
+    }
+
+    /// Step to the neighbouring block in this direction
+    pub fn offset(self) -> (isize, isize, isize) {
+        match self {
+            RenderPosition::NORTH => (0, 0, -1),
+            RenderPosition::SOUTH => (0, 0, 1),
+            RenderPosition::WEST => (-1, 0, 0),
+            RenderPosition::EAST => (1, 0, 0),
+            RenderPosition::TOP => (0, 1, 0),
+            RenderPosition::BOTTOM => (0, -1, 0),
+        }
+    }
+
+    /// Quarter turns clockwise from NORTH, vertical positions have none
+    pub fn quarter_turns(self) -> usize {
+        Self::CLOCKWISE.iter().position(|p| *p == self).unwrap_or(0)
+    }
+
+    /// Rotates horizontal position around Y axis, vertical positions stay the same
+    pub fn rotate_clockwise(self, quarter_turns: usize) -> Self {
+        match Self::CLOCKWISE.iter().position(|p| *p == self) {
+            Some(i) => Self::CLOCKWISE[(i + quarter_turns) % 4],
+            None => self,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct ChunkMesh {
     mesh: Vec<BlockMesh>,
+    /// Rendered separately after all opaque blocks, see `ChunkMesh::render_translucent`
+    translucent: Vec<BlockMesh>,
     blocksize: f32,
 }
 
 impl ChunkMesh {
-    pub fn new(blocks: &ChunkBlocks, xoffset: f32, zoffset: f32, blocksize: f32) -> Self {
+    pub fn new(chunk: &Chunk, blocksize: f32) -> Self {
+        let blocks = chunk.blocks();
+        let xoffset = chunk.xoffset();
+        let zoffset = chunk.zoffset();
+        let options = *MESH_OPTIONS.lock();
         let mut mesh = vec![];
+        let mut translucent = vec![];
 
         let mut offset = vec3(xoffset, 0.0, zoffset);
         foreach_block! {
@@ -41,120 +114,391 @@ impl ChunkMesh {
             x, offset = vec3(offset.x + blocksize, offset.y, zoffset);
             z, offset.z += blocksize) {
                 let block = blocks[y][x][z];
-                if Block::is_air(block) {
+                if block.is_air() {
                     offset.z += blocksize;
                     continue;
                 }
 
-                let mut positions = vec![];
-                if x == 0 {
-                    let b = WorldHelper::get_block_at(
-                        &vec3(xoffset - blocksize, offset.y, offset.z),
-                        blocksize,
-                    );
-                    if Block::is_air(b) {
-                        positions.push(RenderPosition::WEST);
+                let mut faces = vec![];
+                let block_position = (x as isize, y as isize, z as isize);
+                for position in RenderPosition::ALL {
+                    // TODO: It's temporary for increasing performance! In future must be valid logic!
+                    if position == RenderPosition::BOTTOM && y == 0 {
+                        continue;
                     }
-                } else if Block::is_air(blocks[y][x - 1][z]) {
-                    positions.push(RenderPosition::WEST);
-                }
-                if x + 1 == Chunk::WIDTH {
-                    let b = WorldHelper::get_block_at(
-                        &vec3(offset.x + blocksize, offset.y, offset.z),
-                        blocksize,
-                    );
-                    if Block::is_air(b) {
-                        positions.push(RenderPosition::EAST);
-                    }
-                } else if Block::is_air(blocks[y][x + 1][z]) {
-                    positions.push(RenderPosition::EAST);
-                }
 
-                if z == 0 {
-                    let b = WorldHelper::get_block_at(
-                        &vec3(offset.x, offset.y, zoffset - blocksize),
+                    let (dx, dy, dz) = position.offset();
+                    let (neighbour, light) = Self::neighbour_at(
+                        chunk,
+                        x as isize + dx,
+                        y as isize + dy,
+                        z as isize + dz,
                         blocksize,
                     );
-                    if Block::is_air(b) {
-                        positions.push(RenderPosition::NORTH);
+                    if Self::is_face_visible(block, neighbour) {
+                        let shading = Self::face_shading(
+                            chunk,
+                            block_position,
+                            position,
+                            light,
+                            blocksize,
+                            options,
+                        );
+                        faces.push((position, shading));
                     }
-                } else if Block::is_air(blocks[y][x][z - 1]) {
-                    positions.push(RenderPosition::NORTH);
                 }
-                if z + 1 == Chunk::WIDTH {
-                    let b = WorldHelper::get_block_at(
-                        &vec3(offset.x, offset.y, offset.z + blocksize),
-                        blocksize,
-                    );
-                    if Block::is_air(b) {
-                        positions.push(RenderPosition::SOUTH);
+
+                if !faces.is_empty() {
+                    let block_mesh = BlockMesh::new(block, &offset, faces);
+                    if block.info().transparency() == Transparency::Translucent {
+                        translucent.push(block_mesh);
+                    } else {
+                        mesh.push(block_mesh);
                     }
-                } else if Block::is_air(blocks[y][x][z + 1]) {
-                    positions.push(RenderPosition::SOUTH);
                 }
+        }};
+
+        Self {
+            mesh,
+            translucent,
+            blocksize,
+        }
+    }
+
+    /// Light of every vertex of the face is the light of the cell in front of it. With smooth
+    /// lighting it's averaged with three more cells around the vertex, and ambient occlusion darkens
+    /// vertices which are surrounded by opaque blocks.
+    fn face_shading(
+        chunk: &Chunk,
+        (x, y, z): (isize, isize, isize),
+        position: RenderPosition,
+        front_light: u8,
+        blocksize: f32,
+        options: MeshOptions,
+    ) -> FaceShading {
+        let mut shading = FaceShading {
+            light: [FaceShading::normalize_light(front_light); 4],
+            occlusion: [1.0; 4],
+        };
+        if !options.ambient_occlusion && !options.smooth_lighting {
+            return shading;
+        }
 
-                // TODO: It's temporary for increasing performance! In future must be valid logic!
-                if y > 0 && Block::is_air(blocks[y - 1][x][z]) {
-                    positions.push(RenderPosition::BOTTOM);
+        let (dx, dy, dz) = position.offset();
+        let normal = [dx, dy, dz];
+        let front = [x + dx, y + dy, z + dz];
+        let vertices = BlockFace::MAPPING_VERTICES[position as usize];
+        for (i, vertex) in vertices.iter().enumerate() {
+            let corner = BlockFace::CUBE_VERTICES[*vertex];
+
+            // Cells next to the front one in the direction of the vertex along both face axes
+            let mut sides = [front; 2];
+            let mut side = 0;
+            for axis in 0..3 {
+                if normal[axis] != 0 {
+                    continue;
                 }
-                if y + 1 == Chunk::HEIGHT || Block::is_air(blocks[y + 1][x][z]) {
-                    positions.push(RenderPosition::TOP);
+                sides[side][axis] += if corner[axis] == 1.0 { 1 } else { -1 };
+                side += 1;
+            }
+            let diagonal = [
+                sides[0][0] + sides[1][0] - front[0],
+                sides[0][1] + sides[1][1] - front[1],
+                sides[0][2] + sides[1][2] - front[2],
+            ];
+
+            let cells = [sides[0], sides[1], diagonal]
+                .map(|[x, y, z]| Self::neighbour_at(chunk, x, y, z, blocksize));
+            if options.ambient_occlusion {
+                shading.occlusion[i] = FaceShading::vertex_occlusion(
+                    !passes_light(cells[0].0),
+                    !passes_light(cells[1].0),
+                    !passes_light(cells[2].0),
+                );
+            }
+            if options.smooth_lighting {
+                let mut sum = shading.light[i];
+                let mut count = 1.0;
+                for (block, light) in cells {
+                    if passes_light(block) {
+                        let light = FaceShading::normalize_light(light);
+                        sum = [sum[0] + light[0], sum[1] + light[1]];
+                        count += 1.0;
+                    }
                 }
+                shading.light[i] = [sum[0] / count, sum[1] / count];
+            }
+        }
+        shading
+    }
 
-                if positions.len() > 0 {
-                    mesh.push(BlockMesh::new(block, &offset, positions));
-                }
-        }};
+    /// Block and its light by coordinates relative to the chunk, blocks outside of the chunk are
+    /// taken from the world.
+    fn neighbour_at(
+        chunk: &Chunk,
+        x: isize,
+        y: isize,
+        z: isize,
+        blocksize: f32,
+    ) -> (BlockState, u8) {
+        if y < 0 {
+            return (BlockState::AIR, 0);
+        }
+        if y >= Chunk::HEIGHT as isize {
+            return (BlockState::AIR, FULL_SKYLIGHT);
+        }
+        if (0..Chunk::WIDTH_ISIZE).contains(&x) && (0..Chunk::WIDTH_ISIZE).contains(&z) {
+            let (x, y, z) = (x as usize, y as usize, z as usize);
+            return (chunk.block_at(x, z, y), chunk.light_at(x, z, y));
+        }
+
+        let position = vec3(
+            chunk.xoffset() + x as f32 * blocksize,
+            y as f32 * blocksize,
+            chunk.zoffset() + z as f32 * blocksize,
+        );
+        (
+            WorldHelper::get_block_at(&position, blocksize),
+            WorldHelper::get_light_at(&position, blocksize),
+        )
+    }
 
-        Self { mesh, blocksize }
+    /// Face is hidden only by opaque neighbours or by the same see-through block, so glass walls
+    /// and water bodies do not render their inner faces.
+    fn is_face_visible(block: BlockState, neighbour: BlockState) -> bool {
+        if neighbour.is_air() {
+            return true;
+        }
+        neighbour.info().transparency() != Transparency::Opaque && neighbour.id() != block.id()
     }
 
     pub fn render(&self, shader_program: &super::Program) {
         for block_mesh in &self.mesh {
-            shader_program
-                .insert_mat4(&std::ffi::CString::new("model").unwrap(), &block_mesh.model);
-            shader_program.insert_float(
-                &std::ffi::CString::new("texture_offset").unwrap(),
-                block_mesh.zoffset_texture,
-            );
-            for face in &block_mesh.data {
-                BLOCK_RENDERER.render(*face);
-            }
+            block_mesh.render(shader_program);
+        }
+    }
+
+    /// Renders translucent blocks of all meshes from back to front, must be called after all
+    /// opaque blocks were rendered.
+    pub fn render_translucent<'a>(
+        meshes: impl Iterator<Item = &'a ChunkMesh>,
+        shader_program: &super::Program,
+        camera_position: &Vec3,
+    ) {
+        let mut translucent = meshes
+            .flat_map(|chunk_mesh| {
+                chunk_mesh.translucent.iter().map(|block_mesh| {
+                    let center = block_mesh.position().add_scalar(chunk_mesh.blocksize / 2.0);
+                    (distance2(&center, camera_position), block_mesh)
+                })
+            })
+            .collect::<Vec<(f32, &BlockMesh)>>();
+        if translucent.is_empty() {
+            return;
+        }
+        translucent.sort_by(|(l, _), (r, _)| r.total_cmp(l));
+
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+        }
+        for (_, block_mesh) in translucent {
+            block_mesh.render(shader_program);
+        }
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+    }
+}
+
+/// Cracks over every face of the block which the player is breaking
+pub struct BlockCracks {
+    mesh: BlockMesh,
+}
+
+impl BlockCracks {
+    /// Texture of the first crack stage, the following stages go one by one in the atlas
+    const FIRST_TEXTURE: usize = 16;
+    const STAGES: usize = 10;
+
+    /// `progress` is the part of the block which is already broken, from 0 to 1
+    pub fn new(position: &Vec3, progress: f32) -> Self {
+        let stage = ((progress * Self::STAGES as f32) as usize).min(Self::STAGES - 1);
+        let shading = FaceShading {
+            light: [[1.0, 0.0]; 4],
+            occlusion: [1.0; 4],
+        };
+        let data = RenderPosition::ALL
+            .iter()
+            .map(|p| FaceMesh {
+                position: *p as usize,
+                zoffset_texture: (Self::FIRST_TEXTURE + stage) as f32,
+                uv_rotation: 0,
+                shading,
+            })
+            .collect::<Vec<FaceMesh>>();
+        let mesh = BlockMesh {
+            model: translate(&Mat4::identity(), position),
+            data,
+        };
+        Self { mesh }
+    }
+
+    /// Draws cracks on top of the already rendered block
+    pub fn render(&self, shader_program: &super::Program) {
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+            gl::Enable(gl::POLYGON_OFFSET_FILL);
+            gl::PolygonOffset(-1.0, -1.0);
+        }
+        self.mesh.render(shader_program);
+        unsafe {
+            gl::Disable(gl::POLYGON_OFFSET_FILL);
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
         }
     }
 }
 
 struct BlockMesh {
     model: Mat4,
-    data: Vec<usize>,
+    data: Vec<FaceMesh>,
+}
+
+#[derive(Clone, Copy)]
+struct FaceMesh {
+    position: usize,
     zoffset_texture: f32,
+    /// Quarter turns of texture coordinates
+    uv_rotation: i32,
+    shading: FaceShading,
+}
+
+/// Light and ambient occlusion of every vertex of the face, in order of `BlockFace` vertices
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FaceShading {
+    /// Skylight and block light in range from 0 to 1
+    pub light: [[f32; 2]; 4],
+    /// 1 means not occluded at all
+    pub occlusion: [f32; 4],
 }
 
+impl FaceShading {
+    /// Brightness by the count of occluding neighbours, when both sides occlude the corner is
+    /// hidden whatever the diagonal block is
+    const OCCLUSION_LEVELS: [f32; 4] = [0.4, 0.6, 0.8, 1.0];
+
+    pub fn vertex_occlusion(side1: bool, side2: bool, corner: bool) -> f32 {
+        let level = if side1 && side2 {
+            0
+        } else {
+            3 - (side1 as usize + side2 as usize + corner as usize)
+        };
+        Self::OCCLUSION_LEVELS[level]
+    }
+
+    fn normalize_light(light: u8) -> [f32; 2] {
+        [
+            LightKind::Sky.get(light) as f32 / MAX_LIGHT as f32,
+            LightKind::Block.get(light) as f32 / MAX_LIGHT as f32,
+        ]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MeshOptions {
+    pub ambient_occlusion: bool,
+    pub smooth_lighting: bool,
+}
+
+/// Options which are applied when chunk meshes are built, meshes must be rebuilt after a change
+pub static MESH_OPTIONS: Mutex<MeshOptions> = Mutex::new(MeshOptions {
+    ambient_occlusion: true,
+    smooth_lighting: true,
+});
+
 impl BlockMesh {
-    fn new(block: u64, offset: &Vec3, postitions: Vec<RenderPosition>) -> Self {
-        let block_info = Block::from(Self::get_block_id(block));
-        let data = postitions
+    fn new(block: BlockState, offset: &Vec3, faces: Vec<(RenderPosition, FaceShading)>) -> Self {
+        let block_info = block.info();
+        let data = faces
             .iter()
-            .map(|p| *p as usize)
-            .collect::<Vec<usize>>();
+            .map(|(p, shading)| {
+                let (face, uv_rotation) = Self::oriented_face(block, *p);
+                FaceMesh {
+                    position: *p as usize,
+                    zoffset_texture: block_info.zoffset_texure(face as usize) as f32,
+                    uv_rotation,
+                    shading: *shading,
+                }
+            })
+            .collect::<Vec<FaceMesh>>();
         let model = translate(&Mat4::identity(), offset);
-        BlockMesh {
-            model,
-            data,
-            zoffset_texture: block_info.zoffset_texure() as f32,
+        BlockMesh { model, data }
+    }
+
+    fn position(&self) -> Vec3 {
+        self.model.column(3).xyz()
+    }
+
+    fn render(&self, shader_program: &super::Program) {
+        shader_program.insert_mat4(&std::ffi::CString::new("model").unwrap(), &self.model);
+        for face in &self.data {
+            shader_program.insert_float(
+                &std::ffi::CString::new("texture_offset").unwrap(),
+                face.zoffset_texture,
+            );
+            shader_program.insert_int(
+                &std::ffi::CString::new("uv_rotation").unwrap(),
+                face.uv_rotation,
+            );
+            shader_program.insert_vec2_array(
+                &std::ffi::CString::new("vertex_light").unwrap(),
+                &face.shading.light,
+            );
+            shader_program.insert_float_array(
+                &std::ffi::CString::new("vertex_occlusion").unwrap(),
+                &face.shading.occlusion,
+            );
+            BLOCK_RENDERER.render(face.position);
         }
     }
 
-    fn get_block_id(block: u64) -> usize {
-        let mut bit = 1;
-        let mut result = 0;
-        for _ in 0..16 {
-            if bit & block == bit {
-                result |= bit;
-            }
-            bit <<= 1;
+    /// Finds which face of the block (as if it faces NORTH or stands along Y axis) is visible from
+    /// the given side of the world and by how many quarter turns its texture must be rotated.
+    fn oriented_face(block: BlockState, position: RenderPosition) -> (RenderPosition, i32) {
+        use RenderPosition::*;
+
+        if let Some(facing) = block.facing() {
+            return match (facing, position) {
+                (TOP, TOP) | (BOTTOM, BOTTOM) => (NORTH, 0),
+                (TOP, BOTTOM) | (BOTTOM, TOP) => (SOUTH, 0),
+                (TOP, SOUTH) | (BOTTOM, NORTH) => (TOP, 0),
+                (TOP, NORTH) | (BOTTOM, SOUTH) => (BOTTOM, 0),
+                (TOP | BOTTOM, side) => (side, 0),
+                (_, TOP | BOTTOM) => (position, facing.quarter_turns() as i32),
+                (_, side) => (side.rotate_clockwise(4 - facing.quarter_turns()), 0),
+            };
+        }
+
+        match block.axis() {
+            Some(Axis::X) => match position {
+                EAST => (TOP, 0),
+                WEST => (BOTTOM, 0),
+                TOP | BOTTOM => (NORTH, 1),
+                side => (side, 1),
+            },
+            Some(Axis::Z) => match position {
+                SOUTH => (TOP, 0),
+                NORTH => (BOTTOM, 0),
+                TOP | BOTTOM => (NORTH, 0),
+                side => (side, 1),
+            },
+            Some(Axis::Y) | None => (position, 0),
         }
-        result as usize
     }
 }
 
@@ -163,7 +507,6 @@ impl Clone for BlockMesh {
         Self {
             model: self.model.clone(),
             data: self.data.clone(),
-            zoffset_texture: self.zoffset_texture,
         }
     }
 }
@@ -399,3 +742,85 @@ impl Drop for BlockFace {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::block::BlockType;
+
+    const OPTIONS: MeshOptions = MeshOptions {
+        ambient_occlusion: true,
+        smooth_lighting: true,
+    };
+
+    #[test]
+    fn vertex_is_darker_with_every_occluding_neighbour() {
+        assert_eq!(FaceShading::vertex_occlusion(false, false, false), 1.0);
+        assert_eq!(FaceShading::vertex_occlusion(false, false, true), 0.8);
+        assert_eq!(FaceShading::vertex_occlusion(true, false, false), 0.8);
+        assert_eq!(FaceShading::vertex_occlusion(true, false, true), 0.6);
+        assert_eq!(FaceShading::vertex_occlusion(false, true, true), 0.6);
+        // Both sides hide the corner, so the diagonal block doesn't matter
+        assert_eq!(FaceShading::vertex_occlusion(true, true, false), 0.4);
+        assert_eq!(FaceShading::vertex_occlusion(true, true, true), 0.4);
+    }
+
+    /// Shading of the top face of the block at (5, 200, 5) with stone placed around its front
+    fn top_shading(stones: &[(usize, usize, usize)]) -> FaceShading {
+        let mut chunk = Chunk::create(0.0, 0.0);
+        let stone = BlockState::from(BlockType::STONE);
+        chunk.set_block_at(5, 5, 200, stone);
+        for (x, y, z) in stones {
+            chunk.set_block_at(*x, *z, *y, stone);
+        }
+        let front_light = chunk.light_at(5, 5, 201);
+        ChunkMesh::face_shading(
+            &chunk,
+            (5, 200, 5),
+            RenderPosition::TOP,
+            front_light,
+            1.0,
+            OPTIONS,
+        )
+    }
+
+    /// Corners of the top face as whether they are on the positive X and Z sides
+    fn top_corners() -> [(bool, bool); 4] {
+        BlockFace::MAPPING_VERTICES[RenderPosition::TOP as usize].map(|vertex| {
+            let corner = BlockFace::CUBE_VERTICES[vertex];
+            (corner[0] == 1.0, corner[2] == 1.0)
+        })
+    }
+
+    #[test]
+    fn open_face_is_lit_evenly() {
+        let shading = top_shading(&[]);
+        assert_eq!(shading.occlusion, [1.0; 4]);
+        assert_eq!(
+            shading.light,
+            [FaceShading::normalize_light(FULL_SKYLIGHT); 4]
+        );
+    }
+
+    #[test]
+    fn neighbouring_block_darkens_the_near_vertices() {
+        let shading = top_shading(&[(6, 201, 5)]);
+        for (i, (east, _)) in top_corners().into_iter().enumerate() {
+            let expected = if east { 0.8 } else { 1.0 };
+            assert_eq!(shading.occlusion[i], expected, "vertex {}", i);
+        }
+    }
+
+    #[test]
+    fn inside_corner_is_the_darkest() {
+        let shading = top_shading(&[(6, 201, 5), (5, 201, 6)]);
+        for (i, corner) in top_corners().into_iter().enumerate() {
+            let expected = match corner {
+                (true, true) => 0.4,
+                (true, false) | (false, true) => 0.8,
+                (false, false) => 1.0,
+            };
+            assert_eq!(shading.occlusion[i], expected, "vertex {}", i);
+        }
+    }
+}
@@ -2,12 +2,26 @@
 
 use gl::types::*;
 use lodepng::Bitmap;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use rgb::*;
 use std::ffi::CString;
 
+#[cfg(feature = "client")]
+pub mod aim;
 pub mod block;
+#[cfg(feature = "client")]
+pub mod hud;
 pub mod mesh;
-pub mod aim;
+#[cfg(feature = "client")]
+pub mod outline;
+#[cfg(feature = "client")]
+pub mod sky;
+#[cfg(feature = "client")]
+pub mod world;
+
+/// Width of the window divided by its height, it stays 1 without a window
+pub static ASPECT_RATIO: Lazy<Mutex<f32>> = Lazy::new(|| Mutex::new(1.0));
 
 pub struct VaoAttributes {
     pub position: GLuint,
@@ -168,6 +182,35 @@ impl Program {
         }
     }
 
+    pub fn insert_float_array(&self, fieldname: &CString, values: &[f32]) {
+        unsafe {
+            gl::Uniform1fv(
+                gl::GetUniformLocation(self.id, fieldname.as_ptr() as *const GLchar),
+                values.len() as GLsizei,
+                values.as_ptr(),
+            );
+        }
+    }
+
+    pub fn insert_vec2_array(&self, fieldname: &CString, vectors: &[[f32; 2]]) {
+        unsafe {
+            gl::Uniform2fv(
+                gl::GetUniformLocation(self.id, fieldname.as_ptr() as *const GLchar),
+                vectors.len() as GLsizei,
+                vectors.as_ptr() as *const GLfloat,
+            );
+        }
+    }
+
+    pub fn insert_int(&self, fieldname: &CString, value: i32) {
+        unsafe {
+            gl::Uniform1i(
+                gl::GetUniformLocation(self.id, fieldname.as_ptr() as *const GLchar),
+                value,
+            );
+        }
+    }
+
     pub fn insert_float(&self, fieldname: &CString, vector: f32) {
         unsafe {
             gl::Uniform1f(
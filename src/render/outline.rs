@@ -0,0 +1,143 @@
+use gl::types::*;
+use nalgebra_glm::{scale, translate, vec3, Mat4, Vec3};
+
+use super::{Program, Shader, VaoAttributes};
+
+/// Wireframe cube around the block which the player looks at
+pub struct BlockOutline {
+    vao: GLuint,
+    vbo: GLuint,
+    color: Vec3,
+    program: Program,
+}
+
+impl BlockOutline {
+    const CORNERS: [[f32; 3]; 8] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 0.0, 1.0],
+        [0.0, 0.0, 1.0],
+        [0.0, 1.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 1.0, 1.0],
+        [0.0, 1.0, 1.0],
+    ];
+    /// Pairs of corners connected by the cube edges
+    const EDGES: [(usize, usize); 12] = [
+        (0, 1),
+        (1, 2),
+        (2, 3),
+        (3, 0),
+        (4, 5),
+        (5, 6),
+        (6, 7),
+        (7, 4),
+        (0, 4),
+        (1, 5),
+        (2, 6),
+        (3, 7),
+    ];
+    /// The outline is a bit bigger than the block, so faces don't cover its lines
+    const GAP: f32 = 0.002;
+
+    pub fn new(color: Vec3) -> Self {
+        let vbo = Self::create_vbo();
+        Self {
+            vao: Self::create_vao(vbo),
+            vbo,
+            color,
+            program: Program::from([
+                Shader::from_vertex(String::from("res/shaders/outline-vert.glsl")).unwrap(),
+                Shader::from_fragment(String::from("res/shaders/outline-frag.glsl")).unwrap(),
+            ]),
+        }
+    }
+
+    fn create_vbo() -> GLuint {
+        let mut vertices = vec![];
+        for (start, end) in Self::EDGES {
+            vertices.extend_from_slice(&Self::CORNERS[start]);
+            vertices.extend_from_slice(&Self::CORNERS[end]);
+        }
+
+        let mut vbo = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (vertices.len() * std::mem::size_of::<f32>()) as GLsizeiptr,
+                vertices.as_ptr() as *const GLvoid,
+                gl::STATIC_DRAW,
+            );
+        }
+        vbo
+    }
+
+    const VAO_ATTRIBS: VaoAttributes = VaoAttributes {
+        position: 0,
+        size: 3,
+        type_: gl::FLOAT,
+        normalized: gl::FALSE,
+        stride: (3 * std::mem::size_of::<f32>()) as GLint,
+        pointer: std::ptr::null(),
+    };
+
+    fn create_vao(vbo: GLuint) -> GLuint {
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            let attrs = Self::VAO_ATTRIBS;
+            gl::VertexAttribPointer(
+                attrs.position,
+                attrs.size,
+                attrs.type_,
+                attrs.normalized,
+                attrs.stride,
+                attrs.pointer,
+            );
+            gl::EnableVertexAttribArray(attrs.position);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+        vao
+    }
+
+    /// Draws the outline around the block with the given world position of its corner
+    pub fn render(&self, projection: &Mat4, view: &Mat4, position: &Vec3, blocksize: f32) {
+        let size = blocksize + 2.0 * Self::GAP;
+        let model = scale(
+            &translate(&Mat4::identity(), &position.add_scalar(-Self::GAP)),
+            &vec3(size, size, size),
+        );
+
+        self.program.set_used();
+        self.program
+            .insert_mat4(&std::ffi::CString::new("projection").unwrap(), projection);
+        self.program
+            .insert_mat4(&std::ffi::CString::new("view").unwrap(), view);
+        self.program
+            .insert_mat4(&std::ffi::CString::new("model").unwrap(), &model);
+        self.program
+            .insert_vec3(&std::ffi::CString::new("color").unwrap(), &self.color);
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::LINES, 0, (Self::EDGES.len() * 2) as GLsizei);
+        }
+    }
+}
+
+impl Drop for BlockOutline {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.vbo);
+        }
+    }
+}
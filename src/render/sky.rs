@@ -0,0 +1,353 @@
+use gl::types::*;
+use nalgebra_glm::{Mat4, Vec3};
+
+use super::{Program, Shader, VaoAttributes};
+use crate::game::world::clock::WorldClock;
+
+/// Sky around the player: gradient dome, sun and moon billboards and a layer of clouds. It's
+/// rendered before chunks, so everything else covers it.
+pub struct Sky {
+    dome_vao: GLuint,
+    dome_vbo: GLuint,
+    dome_ebo: GLuint,
+    dome_indices: GLsizei,
+
+    quad_vao: GLuint,
+    quad_vbo: GLuint,
+
+    dome_program: Program,
+    body_program: Program,
+    cloud_program: Program,
+
+    cloud_offset: f32,
+}
+
+impl Sky {
+    const DOME_RINGS: usize = 16;
+    const DOME_SEGMENTS: usize = 32;
+    /// Distance to the dome and the sky bodies. The sky is farther than the far plane at short
+    /// render distances, so it's rendered with depth clamping instead of being clipped.
+    const DOME_RADIUS: f32 = 100.0;
+
+    const SUN_SIZE: f32 = 12.0;
+    const MOON_SIZE: f32 = 8.0;
+    const SUN_COLOR: [f32; 3] = [1.0, 0.95, 0.7];
+    const MOON_COLOR: [f32; 3] = [0.85, 0.87, 0.95];
+
+    const CLOUD_HEIGHT: f32 = 160.0;
+    /// Half of the cloud layer width around the player
+    const CLOUD_EXTENT: f32 = 160.0;
+    const CLOUD_CELL_SIZE: f32 = 12.0;
+    /// Part of the cloud cells which are empty
+    const CLOUD_GAPS: f32 = 0.6;
+    /// Speed of the wind in blocks per second
+    const CLOUD_SPEED: f32 = 1.5;
+
+    const QUAD: [[f32; 2]; 4] = [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [1.0, 1.0]];
+
+    pub fn new() -> Self {
+        let (dome_vao, dome_vbo, dome_ebo, dome_indices) = Self::create_dome();
+        let (quad_vao, quad_vbo) = Self::create_quad();
+        Self {
+            dome_vao,
+            dome_vbo,
+            dome_ebo,
+            dome_indices,
+
+            quad_vao,
+            quad_vbo,
+
+            dome_program: Self::initialize_shaders("sky"),
+            body_program: Self::initialize_shaders("sky-body"),
+            cloud_program: Self::initialize_shaders("cloud"),
+
+            cloud_offset: 0.0,
+        }
+    }
+
+    fn initialize_shaders(name: &str) -> Program {
+        Program::from([
+            Shader::from_vertex(format!("res/shaders/{}-vert.glsl", name)).unwrap(),
+            Shader::from_fragment(format!("res/shaders/{}-frag.glsl", name)).unwrap(),
+        ])
+    }
+
+    /// Unit sphere, positions of vertices are their directions as well
+    fn create_dome() -> (GLuint, GLuint, GLuint, GLsizei) {
+        let mut vertices: Vec<f32> = vec![];
+        for ring in 0..=Self::DOME_RINGS {
+            let pitch = std::f32::consts::PI * (ring as f32 / Self::DOME_RINGS as f32 - 0.5);
+            for segment in 0..=Self::DOME_SEGMENTS {
+                let yaw = std::f32::consts::TAU * segment as f32 / Self::DOME_SEGMENTS as f32;
+                vertices.extend_from_slice(&[
+                    pitch.cos() * yaw.cos(),
+                    pitch.sin(),
+                    pitch.cos() * yaw.sin(),
+                ]);
+            }
+        }
+
+        let mut indices: Vec<u32> = vec![];
+        let row = (Self::DOME_SEGMENTS + 1) as u32;
+        for ring in 0..Self::DOME_RINGS as u32 {
+            for segment in 0..Self::DOME_SEGMENTS as u32 {
+                let current = ring * row + segment;
+                let above = current + row;
+                indices.extend_from_slice(&[current, above, current + 1]);
+                indices.extend_from_slice(&[current + 1, above, above + 1]);
+            }
+        }
+
+        let vbo = Self::create_buffer(gl::ARRAY_BUFFER, &vertices);
+        let ebo = Self::create_buffer(gl::ELEMENT_ARRAY_BUFFER, &indices);
+        let vao = Self::create_vao(
+            vbo,
+            Some(ebo),
+            VaoAttributes {
+                position: 0,
+                size: 3,
+                type_: gl::FLOAT,
+                normalized: gl::FALSE,
+                stride: (3 * std::mem::size_of::<f32>()) as GLint,
+                pointer: std::ptr::null(),
+            },
+        );
+        (vao, vbo, ebo, indices.len() as GLsizei)
+    }
+
+    /// Quad from -1 to 1, it's stretched to billboards and the cloud layer by shaders
+    fn create_quad() -> (GLuint, GLuint) {
+        let vertices = Self::QUAD.concat();
+        let vbo = Self::create_buffer(gl::ARRAY_BUFFER, &vertices);
+        let vao = Self::create_vao(
+            vbo,
+            None,
+            VaoAttributes {
+                position: 0,
+                size: 2,
+                type_: gl::FLOAT,
+                normalized: gl::FALSE,
+                stride: (2 * std::mem::size_of::<f32>()) as GLint,
+                pointer: std::ptr::null(),
+            },
+        );
+        (vao, vbo)
+    }
+
+    fn create_buffer<T>(target: GLenum, data: &[T]) -> GLuint {
+        let mut buffer = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer);
+            gl::BindBuffer(target, buffer);
+            gl::BufferData(
+                target,
+                std::mem::size_of_val(data) as GLsizeiptr,
+                data.as_ptr() as *const GLvoid,
+                gl::STATIC_DRAW,
+            );
+            gl::BindBuffer(target, 0);
+        }
+        buffer
+    }
+
+    fn create_vao(vbo: GLuint, ebo: Option<GLuint>, attrs: VaoAttributes) -> GLuint {
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            if let Some(ebo) = ebo {
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+            }
+
+            gl::VertexAttribPointer(
+                attrs.position,
+                attrs.size,
+                attrs.type_,
+                attrs.normalized,
+                attrs.stride,
+                attrs.pointer,
+            );
+            gl::EnableVertexAttribArray(attrs.position);
+
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
+        }
+        vao
+    }
+
+    /// Moves clouds by the wind
+    pub fn update(&mut self, delta_time: f32) {
+        let period = Self::CLOUD_CELL_SIZE * 1024.0;
+        self.cloud_offset = (self.cloud_offset + Self::CLOUD_SPEED * delta_time) % period;
+    }
+
+    pub fn render(
+        &self,
+        projection: &Mat4,
+        view: &Mat4,
+        camera_position: &Vec3,
+        clock: &WorldClock,
+    ) {
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::DepthMask(gl::FALSE);
+            gl::Enable(gl::DEPTH_CLAMP);
+        }
+
+        self.render_dome(projection, view, clock);
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        self.render_body(
+            projection,
+            view,
+            &clock.moon_direction(),
+            Self::MOON_SIZE,
+            &Vec3::from(Self::MOON_COLOR),
+            0.2,
+        );
+        self.render_body(
+            projection,
+            view,
+            &clock.sun_direction(),
+            Self::SUN_SIZE,
+            &Vec3::from(Self::SUN_COLOR),
+            1.0,
+        );
+        self.render_clouds(projection, view, camera_position, clock);
+
+        unsafe {
+            gl::Disable(gl::BLEND);
+            gl::Disable(gl::DEPTH_CLAMP);
+            gl::DepthMask(gl::TRUE);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+
+    fn render_dome(&self, projection: &Mat4, view: &Mat4, clock: &WorldClock) {
+        let program = &self.dome_program;
+        program.set_used();
+        program.insert_mat4(&std::ffi::CString::new("projection").unwrap(), projection);
+        program.insert_mat4(&std::ffi::CString::new("view").unwrap(), view);
+        program.insert_float(
+            &std::ffi::CString::new("radius").unwrap(),
+            Self::DOME_RADIUS,
+        );
+        program.insert_vec3(
+            &std::ffi::CString::new("horizon_color").unwrap(),
+            &clock.sky_color(),
+        );
+        program.insert_vec3(
+            &std::ffi::CString::new("zenith_color").unwrap(),
+            &clock.zenith_color(),
+        );
+        program.insert_vec3(
+            &std::ffi::CString::new("sun_direction").unwrap(),
+            &clock.sun_direction(),
+        );
+        unsafe {
+            gl::BindVertexArray(self.dome_vao);
+            gl::DrawElements(
+                gl::TRIANGLES,
+                self.dome_indices,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+        }
+    }
+
+    fn render_body(
+        &self,
+        projection: &Mat4,
+        view: &Mat4,
+        direction: &Vec3,
+        size: f32,
+        color: &Vec3,
+        glow: f32,
+    ) {
+        let program = &self.body_program;
+        program.set_used();
+        program.insert_mat4(&std::ffi::CString::new("projection").unwrap(), projection);
+        program.insert_mat4(&std::ffi::CString::new("view").unwrap(), view);
+        program.insert_vec3(&std::ffi::CString::new("direction").unwrap(), direction);
+        program.insert_float(
+            &std::ffi::CString::new("distance").unwrap(),
+            Self::DOME_RADIUS * 0.9,
+        );
+        program.insert_float(&std::ffi::CString::new("size").unwrap(), size);
+        program.insert_vec3(&std::ffi::CString::new("color").unwrap(), color);
+        program.insert_float(&std::ffi::CString::new("glow").unwrap(), glow);
+        unsafe {
+            gl::BindVertexArray(self.quad_vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, Self::QUAD.len() as GLsizei);
+        }
+    }
+
+    fn render_clouds(
+        &self,
+        projection: &Mat4,
+        view: &Mat4,
+        camera_position: &Vec3,
+        clock: &WorldClock,
+    ) {
+        let program = &self.cloud_program;
+        program.set_used();
+        program.insert_mat4(&std::ffi::CString::new("projection").unwrap(), projection);
+        program.insert_mat4(&std::ffi::CString::new("view").unwrap(), view);
+        program.insert_vec3(
+            &std::ffi::CString::new("camera_position").unwrap(),
+            camera_position,
+        );
+        program.insert_float(
+            &std::ffi::CString::new("height").unwrap(),
+            Self::CLOUD_HEIGHT,
+        );
+        program.insert_float(
+            &std::ffi::CString::new("extent").unwrap(),
+            Self::CLOUD_EXTENT,
+        );
+        program.insert_float(
+            &std::ffi::CString::new("cell_size").unwrap(),
+            Self::CLOUD_CELL_SIZE,
+        );
+        program.insert_float(&std::ffi::CString::new("gaps").unwrap(), Self::CLOUD_GAPS);
+        program.insert_float(
+            &std::ffi::CString::new("offset").unwrap(),
+            self.cloud_offset,
+        );
+        program.insert_vec3(
+            &std::ffi::CString::new("color").unwrap(),
+            &clock.cloud_color(),
+        );
+        program.insert_vec3(
+            &std::ffi::CString::new("fog_color").unwrap(),
+            &clock.fog_color(),
+        );
+        unsafe {
+            gl::BindVertexArray(self.quad_vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, Self::QUAD.len() as GLsizei);
+        }
+    }
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Sky {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.dome_vao);
+            gl::DeleteVertexArrays(1, &self.quad_vao);
+            gl::DeleteBuffers(1, &self.dome_vbo);
+            gl::DeleteBuffers(1, &self.dome_ebo);
+            gl::DeleteBuffers(1, &self.quad_vbo);
+        }
+    }
+}
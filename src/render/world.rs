@@ -0,0 +1,79 @@
+use std::ffi::CString;
+
+use super::mesh::{BlockCracks, ChunkMesh};
+use super::{Program, Shader, TextureAtlas, TextureAtlasConfiguration};
+use crate::game::player::Player;
+use crate::game::storage::STORAGE;
+use crate::game::world::chunk::Chunk;
+use crate::game::world::World;
+
+/// Draws the meshes of the loaded chunks and the cracks of the block which is being broken.
+/// The world itself doesn't touch OpenGL, so it can run on the server without a window.
+pub struct WorldRenderer {
+    texture_atlas: TextureAtlas,
+    shader_program: Program,
+}
+
+impl WorldRenderer {
+    pub fn new() -> Self {
+        Self {
+            texture_atlas: TextureAtlas::from(TextureAtlasConfiguration {
+                image_path: String::from("res/images/block-texture-atlas.png"),
+                square_size: 16,
+            }),
+            shader_program: Program::from([
+                Shader::from_vertex(String::from("res/shaders/block-vert.glsl")).unwrap(),
+                Shader::from_fragment(String::from("res/shaders/block-frag.glsl")).unwrap(),
+            ]),
+        }
+    }
+
+    pub fn render(&self, world: &World, player: &Player) {
+        self.texture_atlas.set_used();
+        self.shader_program.set_used();
+
+        self.shader_program
+            .insert_mat4(&CString::new("projection").unwrap(), player.projection());
+        self.shader_program
+            .insert_mat4(&CString::new("view").unwrap(), &player.look_at());
+
+        self.shader_program.insert_vec3(
+            &CString::new("camera_position").unwrap(),
+            &player.position(),
+        );
+        self.shader_program.insert_vec3(
+            &CString::new("fog_color").unwrap(),
+            &world.clock().fog_color(),
+        );
+        self.shader_program.insert_float(
+            &CString::new("sky_intensity").unwrap(),
+            world.clock().skylight_intensity(),
+        );
+
+        let fog_distance = (world.render_distance() * Chunk::WIDTH) as f32;
+        self.shader_program
+            .insert_float(&CString::new("fog_min_dist").unwrap(), fog_distance - 2.0);
+        self.shader_program
+            .insert_float(&CString::new("fog_max_dist").unwrap(), fog_distance);
+
+        let meshes = STORAGE.lock().all_mesh().clone();
+        for mesh in meshes.values() {
+            mesh.render(&self.shader_program);
+        }
+        ChunkMesh::render_translucent(
+            meshes.values().map(|mesh| mesh.as_ref()),
+            &self.shader_program,
+            &player.position(),
+        );
+
+        if let Some((position, progress)) = world.breaking_block() {
+            BlockCracks::new(&position, progress).render(&self.shader_program);
+        }
+    }
+}
+
+impl Default for WorldRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,380 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::cli::Options;
+use crate::game::command::{Command, Console};
+use crate::game::player::Player;
+use crate::game::world::chunk::Chunk;
+use crate::game::world::info::WorldInfo;
+use crate::game::world::World;
+use crate::game::DEFAULT_BLOCK_SIZE;
+use crate::net::protocol::Message;
+use crate::net::server::{NetServer, ServerEvent};
+use crate::net::DEFAULT_PORT;
+use crate::settings::Settings;
+
+/// Steps of the simulation per second on the dedicated server
+pub const TICKS_PER_SECOND: u32 = 20;
+
+/// Tells when the fixed ticks of the server loop are due
+pub struct TickTimer {
+    tick: Duration,
+    next: Instant,
+}
+
+impl TickTimer {
+    /// Ticks which are run at most at once, a server which falls further behind skips the rest
+    const MAX_CATCH_UP: u32 = 10;
+
+    pub fn new(ticks_per_second: u32, start: Instant) -> Self {
+        Self {
+            tick: Duration::from_secs(1) / ticks_per_second,
+            next: start,
+        }
+    }
+
+    /// Length of a tick in seconds
+    pub fn delta_time(&self) -> f32 {
+        self.tick.as_secs_f32()
+    }
+
+    /// Number of ticks which have to run at the moment
+    pub fn due(&mut self, now: Instant) -> u32 {
+        let mut ticks = 0;
+        while self.next <= now {
+            ticks += 1;
+            self.next += self.tick;
+            if ticks == Self::MAX_CATCH_UP {
+                if self.next <= now {
+                    self.next = now + self.tick;
+                }
+                break;
+            }
+        }
+        ticks
+    }
+
+    /// Time to wait for the next tick
+    pub fn until_next(&self, now: Instant) -> Duration {
+        self.next.saturating_duration_since(now)
+    }
+}
+
+/// Player connected to the server
+struct RemotePlayer {
+    name: String,
+    /// Last `PlayerMove` of the player, it's sent to players who join later
+    last_move: Option<Message>,
+    /// Chunk of the last `PlayerMove`, chunks within the render distance of it are loaded
+    chunk: Option<(isize, isize)>,
+}
+
+/// Dedicated server which simulates the world without a window. Players connect to it over the
+/// network and it's controlled by commands typed into its console.
+pub struct Server {
+    world: World,
+    network: NetServer,
+    players: HashMap<u32, RemotePlayer>,
+    timer: TickTimer,
+    ticks: u64,
+    running: bool,
+}
+
+impl Server {
+    const AUTOSAVE_TICKS: u64 = 5 * 60 * TICKS_PER_SECOND as u64;
+    /// Players further from the spawn in blocks don't get chunks loaded around them
+    const MAX_DISTANCE: f32 = 30_000_000.0;
+
+    /// Listens on the address and generates the world around the spawn, this takes a while
+    /// for a new world
+    pub fn open(
+        info: WorldInfo,
+        settings: &Settings,
+        address: impl ToSocketAddrs,
+    ) -> std::io::Result<Self> {
+        let network = NetServer::bind(address)?;
+        // The world loads chunks around its player, on the server it stays at the spawn
+        let player = Player::new(1.0, settings.fov.to_radians(), 0.1, 200.0);
+        let world = World::headless(
+            info,
+            DEFAULT_BLOCK_SIZE,
+            Rc::new(RefCell::new(player)),
+            settings,
+        );
+        Ok(Self {
+            world,
+            network,
+            players: HashMap::new(),
+            timer: TickTimer::new(TICKS_PER_SECOND, Instant::now()),
+            ticks: 0,
+            running: true,
+        })
+    }
+
+    /// Opens the world of the options without a window and runs it until the `stop` command,
+    /// used by the dedicated server and by the game with `--headless`
+    pub fn host(options: &Options) -> Result<(), String> {
+        let info = options
+            .world_info()
+            .map_err(|error| format!("World cannot be opened: {}", error))?
+            .ok_or_else(|| String::from("No world is given to host"))?;
+        // The settings file belongs to the game, the server uses the defaults
+        let settings = options.apply(&Settings::default());
+        let port = options.port.unwrap_or(DEFAULT_PORT);
+        let mut server = Self::open(info, &settings, ("0.0.0.0", port))
+            .map_err(|error| format!("Server cannot listen on port {}: {}", port, error))?;
+        server.run();
+        server.close();
+        Ok(())
+    }
+
+    /// Runs until the `stop` command, the world is saved then
+    pub fn run(&mut self) {
+        let info = self.world.info();
+        println!(
+            "{:?} with seed {} is on {} at {} ticks per second, type `stop` to save and stop",
+            info.name(),
+            info.seed(),
+            self.network.local_address(),
+            TICKS_PER_SECOND
+        );
+        let console = Console::spawn();
+        self.timer = TickTimer::new(TICKS_PER_SECOND, Instant::now());
+        while self.running {
+            while let Some(command) = console.poll() {
+                self.execute(command);
+            }
+            self.update();
+            std::thread::sleep(self.timer.until_next(Instant::now()));
+        }
+        self.save();
+    }
+
+    /// Handles messages of the players and runs the ticks which are due
+    pub fn update(&mut self) {
+        for event in self.network.poll() {
+            match event {
+                ServerEvent::Joined { player_id, name } => self.join(player_id, name),
+                ServerEvent::Received { player_id, message } => self.receive(player_id, message),
+                ServerEvent::Left { player_id, reason } => self.leave(player_id, &reason),
+            }
+        }
+        for _ in 0..self.timer.due(Instant::now()) {
+            self.tick();
+        }
+    }
+
+    /// Address which the server listens on
+    pub fn address(&self) -> SocketAddr {
+        self.network.local_address()
+    }
+
+    /// Sends the world and the other players to the new player
+    fn join(&mut self, player_id: u32, name: String) {
+        println!("{} has joined", name);
+        let seed = self.world.seed();
+        self.network
+            .send(player_id, &Message::Welcome { player_id, seed });
+        for ((x, z), blocks) in self.world.encode_changed_chunks() {
+            let chunk = Message::ChunkData {
+                x: x as i64,
+                z: z as i64,
+                blocks,
+            };
+            self.network.send(player_id, &chunk);
+        }
+        for (other_id, other) in &self.players {
+            let joined = Message::PlayerJoined {
+                player_id: *other_id,
+                name: other.name.clone(),
+            };
+            self.network.send(player_id, &joined);
+            if let Some(last_move) = &other.last_move {
+                self.network.send(player_id, last_move);
+            }
+        }
+        let joined = Message::PlayerJoined {
+            player_id,
+            name: name.clone(),
+        };
+        self.network.broadcast(&joined, Some(player_id));
+        let player = RemotePlayer {
+            name,
+            last_move: None,
+            chunk: None,
+        };
+        self.players.insert(player_id, player);
+    }
+
+    fn receive(&mut self, player_id: u32, message: Message) {
+        let Some(player) = self.players.get_mut(&player_id) else {
+            return;
+        };
+        match message {
+            Message::BlockChange { x, y, z, block } => {
+                let position = (x as isize, y as isize, z as isize);
+                // Otherwise any client could make the server keep and save a chunk for every
+                // position it sends, the sender gets the block back to undo its change
+                if !self.world.is_loaded(position) && !self.is_near_a_player(position) {
+                    let block = self.world.block_at(position);
+                    self.network
+                        .send(player_id, &Message::BlockChange { x, y, z, block });
+                    return;
+                }
+                self.world.set_block(position, block);
+                self.network.broadcast(&message, Some(player_id));
+            }
+            Message::PlayerMove {
+                position,
+                yaw,
+                pitch,
+                ..
+            } => {
+                let moved = Message::PlayerMove {
+                    player_id,
+                    position,
+                    yaw,
+                    pitch,
+                };
+                self.network.broadcast(&moved, Some(player_id));
+                player.last_move = Some(moved);
+                let [x, _, z] = position;
+                if !(x.abs() < Self::MAX_DISTANCE && z.abs() < Self::MAX_DISTANCE) {
+                    return;
+                }
+                let chunk = Self::chunk_of(x.floor() as isize, z.floor() as isize);
+                if player.chunk != Some(chunk) {
+                    player.chunk = Some(chunk);
+                    self.world.load_chunks_around(chunk.0, chunk.1);
+                }
+            }
+            Message::Chat { text, .. } => {
+                let sender = player.name.clone();
+                println!("<{}> {}", sender, text);
+                self.network
+                    .broadcast(&Message::Chat { sender, text }, None);
+            }
+            // Only the server sends the rest, the disconnect comes as `ServerEvent::Left`
+            Message::Hello { .. }
+            | Message::Welcome { .. }
+            | Message::ChunkData { .. }
+            | Message::PlayerJoined { .. }
+            | Message::PlayerLeft { .. }
+            | Message::Disconnect { .. } => (),
+        }
+    }
+
+    /// Whether the position in blocks is within the render distance of a player, chunks there
+    /// are loaded or about to be
+    fn is_near_a_player(&self, position: (isize, isize, isize)) -> bool {
+        let (x, _, z) = position;
+        let (xchunk, zchunk) = Self::chunk_of(x, z);
+        let radius = self.world.render_distance() as isize;
+        self.players
+            .values()
+            .filter_map(|player| player.chunk)
+            .any(|(xplayer, zplayer)| {
+                (xchunk - xplayer).abs() <= radius && (zchunk - zplayer).abs() <= radius
+            })
+    }
+
+    /// Chunk which the position in blocks is in
+    fn chunk_of(x: isize, z: isize) -> (isize, isize) {
+        (
+            x.div_euclid(Chunk::WIDTH_ISIZE),
+            z.div_euclid(Chunk::WIDTH_ISIZE),
+        )
+    }
+
+    fn leave(&mut self, player_id: u32, reason: &str) {
+        if let Some(player) = self.players.remove(&player_id) {
+            println!("{} has left: {}", player.name, reason);
+        }
+        self.network
+            .broadcast(&Message::PlayerLeft { player_id }, None);
+    }
+
+    fn tick(&mut self) {
+        self.world.update(self.timer.delta_time());
+        self.world.update_state();
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(Self::AUTOSAVE_TICKS) {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        match self.world.save_chunks() {
+            Ok(()) => println!("World is saved"),
+            Err(error) => eprintln!("World cannot be saved: {}", error),
+        }
+    }
+
+    fn execute(&mut self, command: Command) {
+        let clock = self.world.clock_mut();
+        match command {
+            Command::SetTime(time) => clock.set_time_of_day(time),
+            Command::FreezeTime => clock.set_frozen(true),
+            Command::UnfreezeTime => clock.set_frozen(false),
+            Command::SetDayLength(seconds) => clock.set_day_length(seconds),
+            Command::Save => self.save(),
+            Command::Stop => self.running = false,
+            Command::Say(text) => {
+                let sender = String::from("Server");
+                self.network
+                    .broadcast(&Message::Chat { sender, text }, None);
+            }
+            Command::ListPlayers => {
+                for (player_id, name) in self.network.players() {
+                    println!("{} {}", player_id, name);
+                }
+            }
+            Command::SetGameMode(..)
+            | Command::Craft(..)
+            | Command::ListRecipes
+            | Command::Bind(..)
+            | Command::ListBindings
+            | Command::Set(..)
+            | Command::ListSettings => eprintln!("The command is available only in the game"),
+        }
+    }
+
+    /// Waits for the worker threads and unloads the world, the players are disconnected
+    pub fn close(self) {
+        drop(self.network);
+        self.world.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_are_due_at_a_fixed_rate() {
+        let start = Instant::now();
+        let mut timer = TickTimer::new(TICKS_PER_SECOND, start);
+        assert_eq!(timer.delta_time(), 0.05);
+        assert_eq!(timer.due(start), 1);
+        assert_eq!(timer.due(start), 0);
+        assert_eq!(timer.until_next(start), Duration::from_millis(50));
+        assert_eq!(timer.due(start + Duration::from_millis(120)), 2);
+        assert_eq!(
+            timer.until_next(start + Duration::from_millis(120)),
+            Duration::from_millis(30)
+        );
+    }
+
+    #[test]
+    fn missed_ticks_are_skipped_after_a_long_stall() {
+        let start = Instant::now();
+        let mut timer = TickTimer::new(TICKS_PER_SECOND, start);
+        let later = start + Duration::from_secs(10);
+        assert_eq!(timer.due(later), TickTimer::MAX_CATCH_UP);
+        assert_eq!(timer.due(later), 0);
+        assert_eq!(timer.until_next(later), Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,184 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Where the settings are saved when they are changed
+pub const SETTINGS_FILE: &str = "data/settings.toml";
+
+/// Options of the game which are kept between runs, missing values in the file get defaults
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings {
+    /// Radius of the loaded area in chunks
+    pub render_distance: usize,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+    /// Degrees of camera rotation per pixel of mouse movement
+    pub sensitivity: f32,
+    pub window_width: u32,
+    pub window_height: u32,
+    pub vsync: bool,
+    pub fullscreen: bool,
+}
+
+impl Settings {
+    const MIN_RENDER_DISTANCE: usize = 2;
+    const MAX_RENDER_DISTANCE: usize = 32;
+    const MIN_FOV: f32 = 30.0;
+    const MAX_FOV: f32 = 110.0;
+    const MIN_SENSITIVITY: f32 = 0.01;
+    const MAX_SENSITIVITY: f32 = 1.0;
+    const MIN_WINDOW_WIDTH: u32 = 320;
+    const MIN_WINDOW_HEIGHT: u32 = 240;
+    pub const MAX_WINDOW_WIDTH: u32 = 2560;
+    pub const MAX_WINDOW_HEIGHT: u32 = 1440;
+
+    pub const NAMES: [&'static str; 7] = [
+        "render_distance",
+        "fov",
+        "sensitivity",
+        "window_width",
+        "window_height",
+        "vsync",
+        "fullscreen",
+    ];
+
+    /// Reads the settings file, the defaults are used if there is no file yet
+    pub fn load(path: &str) -> Result<Self, String> {
+        if !Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let settings: Self = toml::from_str(text).map_err(|error| error.to_string())?;
+        Ok(settings.clamped())
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        if let Some(directory) = Path::new(path).parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let text = toml::to_string(self)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+        std::fs::write(path, text)
+    }
+
+    /// Values out of their ranges are moved to the nearest allowed value
+    pub fn clamped(mut self) -> Self {
+        self.render_distance = self
+            .render_distance
+            .clamp(Self::MIN_RENDER_DISTANCE, Self::MAX_RENDER_DISTANCE);
+        self.fov = self.fov.clamp(Self::MIN_FOV, Self::MAX_FOV);
+        self.sensitivity = self
+            .sensitivity
+            .clamp(Self::MIN_SENSITIVITY, Self::MAX_SENSITIVITY);
+        self.window_width = self
+            .window_width
+            .clamp(Self::MIN_WINDOW_WIDTH, Self::MAX_WINDOW_WIDTH);
+        self.window_height = self
+            .window_height
+            .clamp(Self::MIN_WINDOW_HEIGHT, Self::MAX_WINDOW_HEIGHT);
+        self
+    }
+
+    /// Changes the setting by its name in the file, e.g. `set("fov", "70")`
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        fn parse<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+            value
+                .parse::<T>()
+                .map_err(|_| format!("Wrong value of {}: {}", name, value))
+        }
+        match name {
+            "render_distance" => self.render_distance = parse(name, value)?,
+            "fov" => self.fov = parse(name, value)?,
+            "sensitivity" => self.sensitivity = parse(name, value)?,
+            "window_width" => self.window_width = parse(name, value)?,
+            "window_height" => self.window_height = parse(name, value)?,
+            "vsync" => self.vsync = parse(name, value)?,
+            "fullscreen" => self.fullscreen = parse(name, value)?,
+            _ => return Err(format!("Unknown setting: {}", name)),
+        }
+        *self = self.clone().clamped();
+        Ok(())
+    }
+
+    /// Value of the setting by its name in the file
+    pub fn get(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "render_distance" => self.render_distance.to_string(),
+            "fov" => self.fov.to_string(),
+            "sensitivity" => self.sensitivity.to_string(),
+            "window_width" => self.window_width.to_string(),
+            "window_height" => self.window_height.to_string(),
+            "vsync" => self.vsync.to_string(),
+            "fullscreen" => self.fullscreen.to_string(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Whether the window has to be changed to go from the other settings to these
+    pub fn window_differs(&self, other: &Settings) -> bool {
+        (
+            self.window_width,
+            self.window_height,
+            self.vsync,
+            self.fullscreen,
+        ) != (
+            other.window_width,
+            other.window_height,
+            other.vsync,
+            other.fullscreen,
+        )
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            render_distance: if cfg!(target_os = "macos") { 4 } else { 8 },
+            fov: 60.0,
+            sensitivity: 0.1,
+            window_width: 1280,
+            window_height: 720,
+            vsync: false,
+            fullscreen: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_values_get_defaults_and_wrong_ones_are_clamped() {
+        let settings = Settings::parse("fov = 70.0\nrender_distance = 100\n").unwrap();
+        assert_eq!(settings.fov, 70.0);
+        assert_eq!(settings.render_distance, Settings::MAX_RENDER_DISTANCE);
+        assert_eq!(settings.sensitivity, Settings::default().sensitivity);
+
+        let text = toml::to_string(&settings).unwrap();
+        assert_eq!(Settings::parse(&text), Ok(settings));
+        assert!(Settings::parse("fov = \"wide\"").is_err());
+    }
+
+    #[test]
+    fn settings_are_changed_by_name() {
+        let mut settings = Settings::default();
+        settings.set("fullscreen", "true").unwrap();
+        settings.set("sensitivity", "5").unwrap();
+        assert!(settings.fullscreen);
+        assert_eq!(settings.sensitivity, Settings::MAX_SENSITIVITY);
+        assert!(settings.window_differs(&Settings::default()));
+
+        assert!(settings.set("vsync", "maybe").is_err());
+        assert!(settings.set("gamma", "1").is_err());
+        for name in Settings::NAMES {
+            assert!(settings.get(name).is_some());
+        }
+    }
+}
@@ -1,11 +1,10 @@
-use sdl2::{video::GLContext, EventPump};
+use sdl2::video::{FullscreenType, GLContext, SwapInterval};
+use sdl2::EventPump;
 
 extern crate sdl2;
 
-use once_cell::sync::Lazy;
-use parking_lot::Mutex;
-
-pub static ASPECT_RATIO: Lazy<Mutex<f32>> = Lazy::new(|| Mutex::new(1.0));
+use crate::render::ASPECT_RATIO;
+use crate::settings::Settings;
 
 pub struct Window {
     sdl: sdl2::Sdl,
@@ -14,15 +13,9 @@ pub struct Window {
 }
 
 impl Window {
-    pub const WIDTH: u32 = 1280;
-    pub const HEIGHT: u32 = 720;
-
-    const MAX_WIDTH: u32 = 2560;
-    const MAX_HEIGHT: u32 = 1440;
-
     pub fn from(title: &str, width: u32, height: u32) -> Result<Self, Box<dyn std::error::Error>> {
-        assert!(width > 0 && width <= Self::MAX_WIDTH);
-        assert!(height > 0 && height <= Self::MAX_HEIGHT);
+        assert!(width > 0 && width <= Settings::MAX_WINDOW_WIDTH);
+        assert!(height > 0 && height <= Settings::MAX_WINDOW_HEIGHT);
 
         let sdl = sdl2::init()?;
         let video_subsystem = sdl.video()?;
@@ -40,6 +33,7 @@ impl Window {
             // .fullscreen_desktop()
             .opengl()
             .allow_highdpi()
+            .resizable()
             .build()?;
 
         let gl_context = window_context.gl_create_context()?;
@@ -47,22 +41,30 @@ impl Window {
 
         sdl.mouse().set_relative_mouse_mode(true);
 
-        let (width, height) = window_context.size();
-        *ASPECT_RATIO.lock() = width as f32 / height as f32;
         unsafe {
-            gl::Viewport(0, 0, width as i32, height as i32);
-            #[cfg(target_os = "macos")]
-            {
-                gl::Viewport(0, 0, width as i32 * 2, height as i32 * 2);
-            }
             gl::ClearColor(0.3, 0.3, 0.5, 1.0);
             gl::Enable(gl::DEPTH_TEST);
         }
-        Ok(Self {
+        let window = Self {
             sdl,
             window_context,
             gl_context,
-        })
+        };
+        window.update_viewport();
+        Ok(window)
+    }
+
+    /// Fits the viewport and `ASPECT_RATIO` to the current size of the window. The drawable size
+    /// is used, because it's bigger than the window size on high DPI screens.
+    pub fn update_viewport(&self) {
+        let (width, height) = self.window_context.drawable_size();
+        if width == 0 || height == 0 {
+            return;
+        }
+        *ASPECT_RATIO.lock() = width as f32 / height as f32;
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
     }
 
     pub fn event_pump(&self) -> Result<EventPump, String> {
@@ -81,10 +83,35 @@ impl Window {
         &self.gl_context
     }
 
+    pub fn mouse(&self) -> sdl2::mouse::MouseUtil {
+        self.sdl.mouse()
+    }
+
     pub fn timer(&self) -> Result<sdl2::TimerSubsystem, String> {
         self.sdl.timer()
     }
 
+    /// Applies the size, fullscreen and vsync of the settings, the size is kept in fullscreen
+    pub fn apply_settings(&mut self, settings: &Settings) -> Result<(), String> {
+        if settings.fullscreen {
+            self.window_context
+                .set_fullscreen(FullscreenType::Desktop)?;
+        } else {
+            self.window_context.set_fullscreen(FullscreenType::Off)?;
+            self.window_context
+                .set_size(settings.window_width, settings.window_height)
+                .map_err(|error| error.to_string())?;
+        }
+        let interval = if settings.vsync {
+            SwapInterval::VSync
+        } else {
+            SwapInterval::Immediate
+        };
+        self.window_context
+            .subsystem()
+            .gl_set_swap_interval(interval)
+    }
+
     pub fn update(&self) {
         self.window_context.gl_swap_window();
     }
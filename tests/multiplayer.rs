@@ -0,0 +1,204 @@
+use std::time::{Duration, Instant};
+
+use terraria3d_rs::game::world::block_state::BlockState;
+use terraria3d_rs::game::world::info::WorldInfo;
+use terraria3d_rs::game::world::run_with_big_stack;
+use terraria3d_rs::net::client::NetClient;
+use terraria3d_rs::net::protocol::{read_message, write_message, Message, PROTOCOL_VERSION};
+use terraria3d_rs::net::server::{NetServer, ServerEvent};
+use terraria3d_rs::server::Server;
+use terraria3d_rs::settings::Settings;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Calls `poll` until it gives something which `matches` accepts, the server runs in between
+fn wait_for<T>(
+    server: &mut dyn FnMut(),
+    mut poll: impl FnMut() -> Option<T>,
+    matches: impl Fn(&T) -> bool,
+) -> T {
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT {
+        server();
+        match poll() {
+            Some(value) if matches(&value) => return value,
+            Some(_) => (),
+            None => std::thread::sleep(Duration::from_millis(5)),
+        }
+    }
+    panic!("Nothing has come in {:?}", TIMEOUT);
+}
+
+#[test]
+fn clients_are_accepted_only_with_the_same_version() {
+    let mut network = NetServer::bind("127.0.0.1:0").unwrap();
+    let address = network.local_address();
+    let mut events = vec![];
+    let mut next_event = || {
+        if events.is_empty() {
+            events = network.poll();
+            events.reverse();
+        }
+        events.pop()
+    };
+
+    let _alex = NetClient::connect(address, "Alex").unwrap();
+    let joined = wait_for(&mut || (), &mut next_event, |_| true);
+    assert!(matches!(joined, ServerEvent::Joined { ref name, .. } if name == "Alex"));
+
+    let mut stranger = std::net::TcpStream::connect(address).unwrap();
+    let hello = Message::Hello {
+        version: PROTOCOL_VERSION + 1,
+        name: String::from("Old"),
+    };
+    write_message(&mut stranger, &hello).unwrap();
+    let answer = read_message(&mut stranger).unwrap();
+    assert!(matches!(answer, Message::Disconnect { .. }));
+    assert_eq!(network.players(), vec![(1, String::from("Alex"))]);
+}
+
+fn run_server_with_two_players() {
+    let directory = std::env::temp_dir().join(format!("t3d-server-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    let info = WorldInfo::create_at(&directory, "Server", 42).unwrap();
+    let settings = Settings {
+        render_distance: 2,
+        ..Settings::default()
+    };
+    let mut server = Server::open(info, &settings, "127.0.0.1:0").unwrap();
+    let address = server.address();
+
+    let mut alex = NetClient::connect(address, "Alex").unwrap();
+    let welcome = wait_for(&mut || server.update(), || alex.poll(), |_| true);
+    assert!(matches!(welcome, Message::Welcome { seed: 42, .. }));
+    let mut sam = NetClient::connect(address, "Sam").unwrap();
+    wait_for(
+        &mut || server.update(),
+        || sam.poll(),
+        |message| matches!(message, Message::PlayerJoined { name, .. } if name == "Alex"),
+    );
+    let joined = wait_for(&mut || server.update(), || alex.poll(), |_| true);
+    let Message::PlayerJoined {
+        player_id: sam_id,
+        name,
+    } = joined
+    else {
+        panic!("Sam has not joined: {:?}", joined);
+    };
+    assert_eq!(name, "Sam");
+
+    // Edits of one player reach the other one. An edit far from every player is undone for
+    // its sender and doesn't reach the others.
+    let far_away = Message::BlockChange {
+        x: 1_000_000,
+        y: 200,
+        z: 0,
+        block: BlockState::new(1),
+    };
+    alex.send(&far_away).unwrap();
+    let change = Message::BlockChange {
+        x: 3,
+        y: 200,
+        z: -5,
+        block: BlockState::new(1),
+    };
+    alex.send(&change).unwrap();
+    let changed = wait_for(
+        &mut || server.update(),
+        || sam.poll(),
+        |message| matches!(message, Message::BlockChange { .. }),
+    );
+    assert_eq!(changed, change);
+    let undone = wait_for(
+        &mut || server.update(),
+        || alex.poll(),
+        |message| matches!(message, Message::BlockChange { .. }),
+    );
+    assert_eq!(
+        undone,
+        Message::BlockChange {
+            x: 1_000_000,
+            y: 200,
+            z: 0,
+            block: BlockState::AIR,
+        }
+    );
+
+    let chat = Message::Chat {
+        sender: String::new(),
+        text: String::from("hello"),
+    };
+    sam.send(&chat).unwrap();
+    let said = Message::Chat {
+        sender: String::from("Sam"),
+        text: String::from("hello"),
+    };
+    wait_for(
+        &mut || server.update(),
+        || alex.poll(),
+        |message| *message == said,
+    );
+
+    // Moves go over UDP and carry the id of the player who has moved
+    let moved = Message::PlayerMove {
+        player_id: 0,
+        position: [1.0, 80.0, 2.0],
+        yaw: 90.0,
+        pitch: -10.0,
+    };
+    sam.send(&moved).unwrap();
+    let moved = wait_for(
+        &mut || server.update(),
+        || alex.poll(),
+        |message| matches!(message, Message::PlayerMove { .. }),
+    );
+    assert!(
+        matches!(moved, Message::PlayerMove { player_id, position, .. }
+            if player_id == sam_id && position == [1.0, 80.0, 2.0])
+    );
+
+    // Chunks are loaded around Sam after a move, so edits there are kept
+    let far = [1_000_000.0, 80.0, 0.0];
+    let moved = Message::PlayerMove {
+        player_id: 0,
+        position: far,
+        yaw: 0.0,
+        pitch: 0.0,
+    };
+    sam.send(&moved).unwrap();
+    wait_for(
+        &mut || server.update(),
+        || alex.poll(),
+        |message| matches!(message, Message::PlayerMove { position, .. } if *position == far),
+    );
+    alex.send(&far_away).unwrap();
+    let changed = wait_for(
+        &mut || server.update(),
+        || sam.poll(),
+        |message| matches!(message, Message::BlockChange { .. }),
+    );
+    assert_eq!(changed, far_away);
+
+    // A player who joins later gets the changed chunk
+    let mut kim = NetClient::connect(address, "Kim").unwrap();
+    wait_for(
+        &mut || server.update(),
+        || kim.poll(),
+        |message| matches!(message, Message::ChunkData { .. }),
+    );
+    kim.disconnect("Bye");
+
+    sam.disconnect("Bye");
+    wait_for(
+        &mut || server.update(),
+        || alex.poll(),
+        |message| *message == Message::PlayerLeft { player_id: sam_id },
+    );
+    server.close();
+    let _ = std::fs::remove_dir_all(&directory);
+}
+
+#[test]
+fn players_on_a_server_see_each_other() {
+    run_with_big_stack(run_server_with_two_players);
+}